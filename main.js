@@ -1,4 +1,4 @@
-import { invoke } from '@tauri-apps/api/core';
+import { invoke, convertFileSrc } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
 import { createIcons, icons } from 'lucide';
 import { initLicenseUI, shouldLimitResults, showLicenseActivationDialog } from './license-ui.js';
@@ -11,6 +11,9 @@ const sidebar = document.getElementById('sidebar');
 const toggleSidebarBtn = document.getElementById('toggle-sidebar');
 const openSidebarBtn = document.getElementById('open-sidebar');
 const showHelpBtn = document.getElementById('show-help');
+const managePasswordsBtn = document.getElementById('manage-passwords');
+const showIndexErrorsBtn = document.getElementById('show-index-errors');
+const showDuplicatesBtn = document.getElementById('show-duplicates');
 const closeHelpBtn = document.getElementById('close-help');
 const helpModal = document.getElementById('help-modal');
 const addFolderBtn = document.getElementById('add-folder');
@@ -19,11 +22,13 @@ const searchInput = document.getElementById('search-input');
 const clearSearchBtn = document.getElementById('clear-search');
 const resultsContainer = document.getElementById('results-container');
 const resultsCount = document.getElementById('results-count');
+const entityFacets = document.getElementById('entity-facets');
 const clearFiltersBtn = document.getElementById('clear-filters');
 const toggleFiltersBtn = document.getElementById('toggle-filters');
 const filtersPanel = document.getElementById('filters-panel');
 const filterIcon = document.getElementById('filter-icon');
 const sortBySelect = document.getElementById('sort-by');
+const searchModeSelect = document.getElementById('search-mode');
 
 // Filters
 const minSizeInput = document.getElementById('min-size');
@@ -162,8 +167,47 @@ addFolderBtn.addEventListener('click', async () => {
   }
 });
 
-// Index a folder
+// Index runs go one at a time, as two runs over one folder would race on
+// its files. Scheduled re-indexing skips a check while any run is pending.
+let indexQueue = Promise.resolve();
+let pendingIndexRuns = 0;
+
+// Run `task` once the index runs before it have finished
+async function queueIndexRun(task) {
+  pendingIndexRuns++;
+  const run = indexQueue.then(task);
+  indexQueue = run.catch(() => {});
+  try {
+    return await run;
+  } finally {
+    pendingIndexRuns--;
+  }
+}
+
+// Index a folder once the runs before it have finished
 async function indexFolder(folderPath, isReindex = false) {
+  await queueIndexRun(() => runIndex(folderPath, isReindex));
+}
+
+// Index the files that failed last time again, e.g. after a password was
+// stored for them
+async function retryFailedFiles() {
+  try {
+    const result = await queueIndexRun(() => invoke('retry_failed', { folderPath: null }));
+    showToast(
+      `Recovered ${result.recovered} file${result.recovered !== 1 ? 's' : ''}` +
+        (result.removed > 0 ? `, forgot ${result.removed} missing` : '') +
+        ` in ${result.duration}ms`,
+      'success'
+    );
+  } catch (error) {
+    console.error('Error retrying failed files:', error);
+    showToast(`Retry failed: ${error}`, 'error');
+  }
+  await loadIndexedFolders();
+}
+
+async function runIndex(folderPath, isReindex) {
   // Show loading state
   if (!isReindex) {
     const loadingMsg = document.createElement('div');
@@ -185,6 +229,403 @@ async function indexFolder(folderPath, isReindex = false) {
   }
 }
 
+// Edit a folder's indexing settings, then offer to re-index it.
+// Empty fields fall back to the app defaults.
+function openFolderSettings(folder) {
+  const settings = folder.settings || {};
+  const mb = (bytes) => (bytes == null ? '' : Math.round(bytes / 1024 / 1024));
+  const kb = (bytes) => (bytes == null ? '' : Math.round(bytes / 1024));
+  const value = (v) => (v == null ? '' : v);
+  const triState = (v) => (v == null ? '' : String(v));
+  const option = (current, v, label) =>
+    `<option value="${v}" ${triState(current) === v ? 'selected' : ''}>${label}</option>`;
+
+  const modal = document.createElement('div');
+  modal.className = 'modal-overlay folder-settings-modal';
+  modal.setAttribute('role', 'dialog');
+  modal.setAttribute('aria-modal', 'true');
+  modal.innerHTML = `
+    <div class="modal">
+      <div class="modal-header">
+        <h2><i data-lucide="settings-2" class="section-icon"></i> Folder Settings</h2>
+        <button class="icon-btn close-modal-btn" aria-label="Close folder settings">
+          <i data-lucide="x"></i>
+        </button>
+      </div>
+      <div class="modal-content">
+        <p class="folder-settings-path">${escapeHtml(folder.path)}</p>
+        <div class="folder-settings-grid">
+          <div class="filter-group">
+            <label for="fs-min-size">Min size (KB)</label>
+            <input type="number" id="fs-min-size" min="0" placeholder="Default" value="${kb(settings.min_file_size)}">
+          </div>
+          <div class="filter-group">
+            <label for="fs-max-size">Max size (MB)</label>
+            <input type="number" id="fs-max-size" min="0" placeholder="100" value="${mb(settings.max_file_size)}">
+          </div>
+          <div class="filter-group">
+            <label for="fs-max-depth">Subfolder depth</label>
+            <input type="number" id="fs-max-depth" min="0" placeholder="Unlimited" value="${value(settings.max_depth)}">
+          </div>
+          <div class="filter-group">
+            <label for="fs-threads">Threads</label>
+            <input type="number" id="fs-threads" min="0" placeholder="All cores" value="${value(settings.max_threads)}">
+          </div>
+          <div class="filter-group">
+            <label for="fs-ocr">OCR scanned PDFs</label>
+            <select id="fs-ocr">
+              ${option(settings.enable_ocr, '', 'Default (on)')}
+              ${option(settings.enable_ocr, 'true', 'On')}
+              ${option(settings.enable_ocr, 'false', 'Off')}
+            </select>
+          </div>
+          <div class="filter-group">
+            <label for="fs-ocr-language">OCR languages</label>
+            <input type="text" id="fs-ocr-language" placeholder="eng" value="${escapeHtml(settings.ocr_language || '')}">
+          </div>
+          <div class="filter-group">
+            <label for="fs-symlinks">Follow symlinks</label>
+            <select id="fs-symlinks">
+              ${option(settings.follow_symlinks, '', 'Default (off)')}
+              ${option(settings.follow_symlinks, 'true', 'On')}
+              ${option(settings.follow_symlinks, 'false', 'Off')}
+            </select>
+          </div>
+          <div class="filter-group">
+            <label for="fs-same-fs">Other drives/mounts</label>
+            <select id="fs-same-fs">
+              ${option(settings.same_file_system, '', 'Default (include)')}
+              ${option(settings.same_file_system, 'false', 'Include')}
+              ${option(settings.same_file_system, 'true', 'Skip')}
+            </select>
+          </div>
+          <div class="filter-group">
+            <label for="fs-archives">PDFs in archives</label>
+            <select id="fs-archives">
+              ${option(settings.index_archives, '', 'Default (on)')}
+              ${option(settings.index_archives, 'true', 'On')}
+              ${option(settings.index_archives, 'false', 'Off')}
+            </select>
+          </div>
+          <div class="filter-group">
+            <label for="fs-other-formats">Text, Office and EPUB files</label>
+            <select id="fs-other-formats">
+              ${option(settings.index_other_formats, '', 'Default (off)')}
+              ${option(settings.index_other_formats, 'true', 'On')}
+              ${option(settings.index_other_formats, 'false', 'Off')}
+            </select>
+          </div>
+          <div class="filter-group">
+            <label for="fs-interval">Re-index every (hours)</label>
+            <input type="number" id="fs-interval" min="0" placeholder="Never" value="${value(settings.reindex_interval_hours)}">
+          </div>
+        </div>
+        <div class="filter-group">
+          <label for="fs-exclude">Exclude patterns, one per line</label>
+          <textarea id="fs-exclude" rows="3" placeholder="**/node_modules/**&#10;*_draft.pdf">${escapeHtml((settings.exclude_patterns || []).join('\n'))}</textarea>
+        </div>
+        <div class="filter-group">
+          <label for="fs-include">Only index matching files, one per line (empty for all)</label>
+          <textarea id="fs-include" rows="2">${escapeHtml((settings.include_patterns || []).join('\n'))}</textarea>
+        </div>
+        <p class="input-hint">A .pdffinderignore file in the folder is also honored.</p>
+        <div class="filter-group">
+          <label for="fs-allowed-roots">Followed symlinks may also lead into, one per line</label>
+          <textarea id="fs-allowed-roots" rows="2">${escapeHtml((settings.allowed_roots || []).join('\n'))}</textarea>
+        </div>
+        <div class="folder-settings-actions">
+          <button class="btn btn-primary save-settings-btn">Save</button>
+          <button class="btn btn-secondary close-modal-btn">Cancel</button>
+        </div>
+      </div>
+    </div>
+  `;
+
+  document.body.appendChild(modal);
+  createIcons({ icons });
+
+  const field = (id) => modal.querySelector(`#${id}`).value.trim();
+  const number = (id, scale = 1) => (field(id) === '' ? null : Math.max(0, Math.round(Number(field(id)) * scale)));
+  const bool = (id) => (field(id) === '' ? null : field(id) === 'true');
+  const lines = (id) => field(id).split('\n').map(p => p.trim()).filter(Boolean);
+
+  modal.querySelectorAll('.close-modal-btn').forEach(btn => btn.addEventListener('click', () => modal.remove()));
+
+  modal.querySelector('.save-settings-btn').addEventListener('click', async () => {
+    const updated = {
+      min_file_size: number('fs-min-size', 1024),
+      max_file_size: number('fs-max-size', 1024 * 1024),
+      max_depth: number('fs-max-depth'),
+      max_threads: number('fs-threads'),
+      enable_ocr: bool('fs-ocr'),
+      ocr_language: field('fs-ocr-language') || null,
+      follow_symlinks: bool('fs-symlinks'),
+      allowed_roots: lines('fs-allowed-roots'),
+      same_file_system: bool('fs-same-fs'),
+      index_archives: bool('fs-archives'),
+      index_other_formats: bool('fs-other-formats'),
+      reindex_interval_hours: number('fs-interval'),
+      exclude_patterns: lines('fs-exclude'),
+      include_patterns: lines('fs-include')
+    };
+
+    try {
+      await invoke('set_folder_settings', { folderPath: folder.path, settings: updated });
+    } catch (error) {
+      console.error('Error saving folder settings:', error);
+      showToast(`Failed to save folder settings: ${error}`, 'error');
+      return;
+    }
+
+    modal.remove();
+    showToast('Folder settings saved', 'success');
+    if (confirm(`Re-index ${folder.path} now to apply the settings?`)) {
+      await indexFolder(folder.path, true);
+    }
+    await loadIndexedFolders();
+  });
+}
+
+// Store passwords for protected PDFs: one for a single file that failed to
+// open, or shared ones tried on every protected PDF. Passwords are never
+// shown again once stored.
+async function openPasswords() {
+  const [status, errors] = await Promise.all([
+    invoke('get_keyring_status').catch(() => null),
+    invoke('get_index_errors', { folderPath: null }).catch(() => []),
+  ]);
+  const protectedFiles = errors.filter(error => error.kind === 'encrypted');
+
+  const modal = document.createElement('div');
+  modal.className = 'modal-overlay passwords-modal';
+  modal.setAttribute('role', 'dialog');
+  modal.setAttribute('aria-modal', 'true');
+  modal.innerHTML = `
+    <div class="modal">
+      <div class="modal-header">
+        <h2><i data-lucide="key-round" class="section-icon"></i> Passwords</h2>
+        <button class="icon-btn close-modal-btn" aria-label="Close passwords">
+          <i data-lucide="x"></i>
+        </button>
+      </div>
+      <div class="modal-content">
+        <p class="input-hint">
+          ${status
+            ? `${status.file_passwords} file password${status.file_passwords !== 1 ? 's' : ''} and ${status.candidate_passwords} shared password${status.candidate_passwords !== 1 ? 's' : ''} stored, encrypted on this computer.`
+            : 'The keyring could not be opened.'}
+        </p>
+        <h3>Protected files that could not be opened</h3>
+        ${protectedFiles.length === 0
+          ? '<p class="input-hint">None</p>'
+          : protectedFiles.map((file, i) => `
+            <div class="protected-file">
+              <span class="folder-settings-path" title="${escapeHtml(file.path)}">${escapeHtml(getFileName(file.path))}</span>
+              <input type="password" data-index="${i}" placeholder="Password" aria-label="Password for ${escapeHtml(getFileName(file.path))}">
+              <button class="btn btn-secondary save-file-password" data-index="${i}">Save</button>
+            </div>
+          `).join('')}
+        <h3>Passwords to try on every protected PDF</h3>
+        <div class="shared-password">
+          <input type="password" id="shared-password" placeholder="Password" aria-label="Shared password">
+          <button class="btn btn-secondary" id="add-shared-password">Add</button>
+          <button class="btn btn-text" id="remove-shared-password">Remove</button>
+        </div>
+        <div class="folder-settings-actions">
+          <button class="btn btn-primary retry-protected-btn">Retry failed files</button>
+          <button class="btn btn-secondary close-modal-btn">Close</button>
+        </div>
+      </div>
+    </div>
+  `;
+
+  document.body.appendChild(modal);
+  createIcons({ icons });
+
+  modal.querySelectorAll('.close-modal-btn').forEach(btn => btn.addEventListener('click', () => modal.remove()));
+
+  modal.querySelectorAll('.save-file-password').forEach(btn => btn.addEventListener('click', async () => {
+    const index = btn.getAttribute('data-index');
+    const input = modal.querySelector(`input[data-index="${index}"]`);
+    if (!input.value) return;
+    try {
+      await invoke('set_pdf_password', { path: protectedFiles[index].path, password: input.value });
+      input.value = '';
+      showToast(`Password saved for ${getFileName(protectedFiles[index].path)}`, 'success');
+    } catch (error) {
+      showToast(`Failed to save password: ${error}`, 'error');
+    }
+  }));
+
+  const sharedInput = modal.querySelector('#shared-password');
+  const updateShared = async (command, message) => {
+    if (!sharedInput.value) return;
+    try {
+      await invoke(command, { password: sharedInput.value });
+      sharedInput.value = '';
+      showToast(message, 'success');
+    } catch (error) {
+      showToast(`Failed to update passwords: ${error}`, 'error');
+    }
+  };
+  modal.querySelector('#add-shared-password').addEventListener('click', () =>
+    updateShared('add_keyring_password', 'Password added'));
+  modal.querySelector('#remove-shared-password').addEventListener('click', () =>
+    updateShared('remove_keyring_password', 'Password removed'));
+
+  modal.querySelector('.retry-protected-btn').addEventListener('click', async () => {
+    modal.remove();
+    await retryFailedFiles();
+  });
+}
+
+managePasswordsBtn.addEventListener('click', openPasswords);
+
+// List the files that failed to index on the last run that saw them, with
+// what failed, and offer to index them again
+async function openIndexErrors() {
+  let errors;
+  try {
+    errors = await invoke('get_index_errors', { folderPath: null });
+  } catch (error) {
+    showToast(`Failed to load failed files: ${error}`, 'error');
+    return;
+  }
+
+  const modal = document.createElement('div');
+  modal.className = 'modal-overlay index-errors-modal';
+  modal.setAttribute('role', 'dialog');
+  modal.setAttribute('aria-modal', 'true');
+  modal.innerHTML = `
+    <div class="modal">
+      <div class="modal-header">
+        <h2><i data-lucide="file-warning" class="section-icon"></i> Failed Files</h2>
+        <button class="icon-btn close-modal-btn" aria-label="Close failed files">
+          <i data-lucide="x"></i>
+        </button>
+      </div>
+      <div class="modal-content">
+        ${errors.length === 0
+          ? '<p class="input-hint">Every file was indexed.</p>'
+          : `<div class="index-errors-list" role="list">
+              ${errors.map(error => `
+                <div class="index-error" role="listitem">
+                  <p class="folder-settings-path" title="${escapeHtml(error.path)}">${escapeHtml(error.path)}</p>
+                  <p class="index-error-message">${escapeHtml(error.message || error.kind)}</p>
+                  <p class="input-hint">
+                    ${escapeHtml(error.phase)} · ${escapeHtml(error.kind)} · ${formatTimestamp(error.timestamp)}
+                    ${error.attempts > 1 ? ` · failed ${error.attempts} times` : ''}
+                  </p>
+                </div>
+              `).join('')}
+            </div>`}
+        <div class="folder-settings-actions">
+          ${errors.some(error => error.kind === 'encrypted')
+            ? '<button class="btn btn-secondary open-passwords-btn">Enter passwords</button>'
+            : ''}
+          ${errors.length > 0 ? '<button class="btn btn-primary retry-failed-btn">Retry all</button>' : ''}
+          <button class="btn btn-secondary close-modal-btn">Close</button>
+        </div>
+      </div>
+    </div>
+  `;
+
+  document.body.appendChild(modal);
+  createIcons({ icons });
+
+  modal.querySelectorAll('.close-modal-btn').forEach(btn => btn.addEventListener('click', () => modal.remove()));
+  modal.querySelector('.open-passwords-btn')?.addEventListener('click', () => {
+    modal.remove();
+    openPasswords();
+  });
+  modal.querySelector('.retry-failed-btn')?.addEventListener('click', async () => {
+    modal.remove();
+    await retryFailedFiles();
+  });
+}
+
+showIndexErrorsBtn.addEventListener('click', openIndexErrors);
+
+// Report groups of byte-identical files and of files with nearly the same
+// text, largest file first, with the space the copies take up
+async function openDuplicates() {
+  let groups;
+  try {
+    groups = await invoke('find_duplicates');
+  } catch (error) {
+    showToast(`Failed to find duplicates: ${error}`, 'error');
+    return;
+  }
+  const reclaimable = groups.filter(group => group.exact).reduce((sum, group) => sum + group.reclaimable_bytes, 0);
+
+  const modal = document.createElement('div');
+  modal.className = 'modal-overlay duplicates-modal';
+  modal.setAttribute('role', 'dialog');
+  modal.setAttribute('aria-modal', 'true');
+  modal.innerHTML = `
+    <div class="modal">
+      <div class="modal-header">
+        <h2><i data-lucide="copy" class="section-icon"></i> Duplicates</h2>
+        <button class="icon-btn close-modal-btn" aria-label="Close duplicates">
+          <i data-lucide="x"></i>
+        </button>
+      </div>
+      <div class="modal-content">
+        <p class="input-hint">
+          ${groups.length === 0
+            ? 'No duplicates found.'
+            : `${groups.length} group${groups.length !== 1 ? 's' : ''}; removing the exact copies would free ${formatFileSize(reclaimable)}.`}
+        </p>
+        <div class="duplicate-groups">
+          ${groups.map(group => `
+            <div class="duplicate-group">
+              <h3>${group.exact ? 'Identical files' : 'Nearly the same text'}
+                ${group.exact ? ` · ${formatFileSize(group.reclaimable_bytes)} in copies` : ''}</h3>
+              ${group.files.map(file => `
+                <button class="duplicate-file" data-path="${escapeHtml(file.path)}" title="Open ${escapeHtml(file.path)}">
+                  <span>${escapeHtml(file.path)}</span>
+                  <span>${formatFileSize(file.size)} · ${formatDate(file.modified)}</span>
+                </button>
+              `).join('')}
+            </div>
+          `).join('')}
+        </div>
+        <div class="folder-settings-actions">
+          <button class="btn btn-secondary close-modal-btn">Close</button>
+        </div>
+      </div>
+    </div>
+  `;
+
+  document.body.appendChild(modal);
+  createIcons({ icons });
+
+  modal.querySelectorAll('.close-modal-btn').forEach(btn => btn.addEventListener('click', () => modal.remove()));
+  modal.querySelectorAll('.duplicate-file').forEach(btn => btn.addEventListener('click', async () => {
+    try {
+      await invoke('open_pdf', { path: btn.getAttribute('data-path') });
+    } catch (error) {
+      console.error('Error opening PDF:', error);
+      showError('Failed to open PDF. The file may have been moved or deleted.');
+    }
+  }));
+}
+
+showDuplicatesBtn.addEventListener('click', openDuplicates);
+
+// Re-index folders whose schedule has come due
+async function runScheduledReindex() {
+  // Folders still due are picked up by the next check
+  if (pendingIndexRuns > 0) return;
+
+  const due = await invoke('get_folders_due_for_reindex').catch(() => []);
+  if (due.length === 0) return;
+
+  for (const folderPath of due) {
+    await indexFolder(folderPath, true);
+  }
+  await loadIndexedFolders();
+}
+
 // Load and display indexed folders
 async function loadIndexedFolders() {
   try {
@@ -215,6 +656,9 @@ async function loadIndexedFolders() {
           </div>
         </div>
         <div class="folder-actions">
+          <button class="icon-btn settings" title="Folder settings" aria-label="Edit settings for folder ${escapeHtml(folderName)}">
+            <i data-lucide="settings-2"></i>
+          </button>
           <button class="icon-btn refresh" title="Re-index" aria-label="Re-index folder ${escapeHtml(folderName)}">
             <i data-lucide="refresh-cw"></i>
           </button>
@@ -227,6 +671,12 @@ async function loadIndexedFolders() {
       // Add event listeners instead of inline onclick
       const refreshBtn = folderItem.querySelector('.refresh');
       const deleteBtn = folderItem.querySelector('.delete');
+      const settingsBtn = folderItem.querySelector('.settings');
+
+      settingsBtn.addEventListener('click', (e) => {
+        e.stopPropagation();
+        openFolderSettings(folder);
+      });
 
       refreshBtn.addEventListener('click', async (e) => {
         e.stopPropagation();
@@ -275,6 +725,7 @@ async function performSearch() {
   if (!query) {
     showEmptyState('default');
     currentResults = [];
+    entityFacets.hidden = true;
     return;
   }
 
@@ -316,17 +767,67 @@ async function performSearch() {
       date_to: dateToInput.value || null,
     };
 
-    const results = await invoke('search_pdfs', { query, filters });
+    const results = await invoke('search_pdfs', { query, filters, mode: searchModeSelect.value });
     currentResults = results;
     displayResults(results);
+    loadEntityFacets(query, filters, results.length);
   } catch (error) {
     console.error('Error searching:', error);
-    // Show user-friendly error message without exposing internals
-    showError('Search failed. Please try different search terms or filters.');
+    // Show user-friendly error message without exposing internals, except
+    // why a semantic search could not run, e.g. no known query words
+    showError(searchModeSelect.value === 'keyword'
+      ? 'Search failed. Please try different search terms or filters.'
+      : `Search failed: ${error}`);
     currentResults = [];
+    entityFacets.hidden = true;
   }
 }
 
+const ENTITY_LABELS = {
+  email: 'Email',
+  phone: 'Phone',
+  date: 'Date',
+  amount: 'Amount',
+  iban: 'IBAN',
+  isbn: 'ISBN',
+  doi: 'DOI',
+};
+
+// Show the entities mentioned most often by the results as chips that
+// narrow the search down to the documents mentioning them
+async function loadEntityFacets(query, filters, resultCount) {
+  let facets = [];
+  if (resultCount > 0) {
+    try {
+      facets = await invoke('get_entity_facets', { query, filters });
+    } catch (error) {
+      console.error('Error loading entities:', error);
+    }
+  }
+  // A newer search has started meanwhile
+  if (searchInput.value.trim() !== query) return;
+
+  entityFacets.hidden = facets.length === 0;
+  entityFacets.innerHTML = facets.map((facet, i) => `
+    <button class="entity-chip" data-facet="${i}">
+      <span class="entity-kind">${ENTITY_LABELS[facet.kind] || facet.kind}</span>
+      ${escapeHtml(facet.value)}
+      <span class="entity-count">${facet.documents}</span>
+    </button>
+  `).join('');
+
+  entityFacets.querySelectorAll('.entity-chip').forEach(chip => {
+    const facet = facets[Number(chip.getAttribute('data-facet'))];
+    chip.title = `Only documents mentioning ${facet.value}`;
+    chip.addEventListener('click', () => {
+      // Operators end at whitespace, and values are matched without it
+      searchInput.value = `${searchInput.value.trim()} ${facet.kind}:${facet.value.replace(/\s+/g, '')}`;
+      clearTimeout(searchTimeout);
+      performSearch();
+    });
+  });
+}
+
 // Enter key triggers immediate search
 searchInput.addEventListener('keypress', (e) => {
   if (e.key === 'Enter') {
@@ -366,6 +867,27 @@ dateToInput.addEventListener('change', () => {
   }
 });
 
+// Rank by keywords, meaning or both
+searchModeSelect.addEventListener('change', () => {
+  if (searchInput.value.trim()) {
+    performSearch();
+  }
+});
+
+// Semantic ranking needs an embedding model, which is installed by hand
+async function loadSemanticStatus() {
+  const status = await invoke('get_semantic_status').catch(() => null);
+  if (status && status.available) return;
+
+  searchModeSelect.value = 'keyword';
+  searchModeSelect.querySelectorAll('option:not([value="keyword"])').forEach(option => {
+    option.disabled = true;
+  });
+  searchModeSelect.title = status
+    ? `Semantic search needs an embedding model at ${status.model_path}`
+    : 'Semantic search is unavailable';
+}
+
 // Sort results
 sortBySelect.addEventListener('change', () => {
   if (currentResults.length > 0) {
@@ -510,6 +1032,22 @@ function displayResults(results) {
   resultsContainer.querySelectorAll('.result-item').forEach(item => {
     const path = item.getAttribute('data-path');
     if (path) {
+      // Export the matched table without opening the document
+      const exportBtn = item.querySelector('.export-table');
+      exportBtn?.addEventListener('click', (e) => {
+        e.stopPropagation();
+        exportTable(path, Number(exportBtn.getAttribute('data-table')));
+      });
+      exportBtn?.addEventListener('keydown', (e) => e.stopPropagation());
+
+      // List every match instead of opening the document
+      const matchesBtn = item.querySelector('.show-matches');
+      matchesBtn?.addEventListener('click', (e) => {
+        e.stopPropagation();
+        openDocumentMatches(path, searchInput.value.trim());
+      });
+      matchesBtn?.addEventListener('keydown', (e) => e.stopPropagation());
+
       // Click handler
       item.addEventListener('click', async () => {
         try {
@@ -535,7 +1073,88 @@ function displayResults(results) {
     }
   });
 
+  // Stop watching the placeholders of the previous results
+  thumbnailObserver.disconnect();
+  resultsContainer.querySelectorAll('.result-thumbnail').forEach(placeholder => thumbnailObserver.observe(placeholder));
+  createIcons({ icons });
+}
+
+// Number of matches loaded at a time in the match list of a document
+const MATCHES_PAGE_SIZE = 50;
+
+// List every match of `query` in the document at `path`, with its page and
+// section, loading further matches on demand
+async function openDocumentMatches(path, query) {
+  const modal = document.createElement('div');
+  modal.className = 'modal-overlay document-matches-modal';
+  modal.setAttribute('role', 'dialog');
+  modal.setAttribute('aria-modal', 'true');
+  modal.innerHTML = `
+    <div class="modal">
+      <div class="modal-header">
+        <h2><i data-lucide="list" class="section-icon"></i> Matches</h2>
+        <button class="icon-btn close-modal-btn" aria-label="Close matches">
+          <i data-lucide="x"></i>
+        </button>
+      </div>
+      <div class="modal-content">
+        <p class="folder-settings-path">${escapeHtml(path)}</p>
+        <p class="input-hint matches-count"></p>
+        <div class="document-matches" role="list"></div>
+        <div class="folder-settings-actions">
+          <button class="btn btn-secondary more-matches-btn" hidden>Show more</button>
+          <button class="btn btn-secondary close-modal-btn">Close</button>
+        </div>
+      </div>
+    </div>
+  `;
+
+  document.body.appendChild(modal);
   createIcons({ icons });
+  modal.querySelectorAll('.close-modal-btn').forEach(btn => btn.addEventListener('click', () => modal.remove()));
+
+  const list = modal.querySelector('.document-matches');
+  const count = modal.querySelector('.matches-count');
+  const moreBtn = modal.querySelector('.more-matches-btn');
+  let offset = 0;
+
+  const loadMore = async () => {
+    moreBtn.disabled = true;
+    let page;
+    try {
+      page = await invoke('get_document_matches', { path, query, offset, limit: MATCHES_PAGE_SIZE });
+    } catch (error) {
+      showToast(`Failed to load matches: ${error}`, 'error');
+      return;
+    } finally {
+      moreBtn.disabled = false;
+    }
+
+    list.insertAdjacentHTML('beforeend', page.matches.map(match => `
+      <div class="document-match" role="listitem">
+        ${match.page ? `<span class="input-hint">Page ${match.page}${match.section.length ? ` · ${match.section.map(escapeHtml).join(' › ')}` : ''}</span>` : ''}
+        <div class="result-snippet">${renderSnippet(match.snippet)}</div>
+      </div>
+    `).join(''));
+    offset += page.matches.length;
+    count.textContent = `${page.total} match${page.total !== 1 ? 'es' : ''}`;
+    moreBtn.hidden = offset >= page.total || page.matches.length === 0;
+  };
+
+  moreBtn.addEventListener('click', loadMore);
+  await loadMore();
+}
+
+// Save table `index` of the document at `path` to a CSV file of the user's choice
+async function exportTable(path, index) {
+  try {
+    // The backend asks where to save, so the page never picks a path to write
+    const destination = await invoke('export_table_csv', { path, index });
+    if (!destination) return;
+    showToast(`Table saved to ${getFileName(destination)}`, 'success');
+  } catch (error) {
+    showToast(`Failed to export table: ${error}`, 'error');
+  }
 }
 
 function showSkeletonLoader(count = 3) {
@@ -556,8 +1175,9 @@ function groupByFolder(results) {
   const grouped = {};
 
   results.forEach(result => {
-    // Extract folder path (everything except filename)
-    const pathParts = result.path.split(/[\\/]/);
+    // Extract folder path (everything except filename); attachments are
+    // grouped with the PDF they are embedded in
+    const pathParts = (result.parent_path || result.path).split(/[\\/]/);
     const folder = pathParts.slice(0, -1).join('/') || '/';
 
     if (!grouped[folder]) {
@@ -583,18 +1203,55 @@ function getFolderName(path) {
 function renderResultItem(result) {
   return `
     <div class="result-item" data-path="${escapeHtml(result.path)}" role="listitem button" tabindex="0" aria-label="Open ${escapeHtml(result.title || getFileName(result.path))}">
+      ${renderThumbnail(result)}
       <div class="result-title">${escapeHtml(result.title || getFileName(result.path))}</div>
       <div class="result-path">${escapeHtml(result.path)}</div>
       <div class="result-metadata">
         <span><i data-lucide="file-text" class="meta-icon"></i> ${formatFileSize(result.size)}</span>
         <span><i data-lucide="calendar" class="meta-icon"></i> Modified: ${formatDate(result.modified)}</span>
         ${result.pages ? `<span><i data-lucide="book-open" class="meta-icon"></i> ${result.pages} pages</span>` : ''}
+        ${result.section && result.section.length ? `<span><i data-lucide="bookmark" class="meta-icon"></i> ${result.section.map(escapeHtml).join(' › ')}${result.page ? ` (page ${result.page})` : ''}</span>` : ''}
+        ${result.parent_path ? `<span><i data-lucide="paperclip" class="meta-icon"></i> Found in attachment ${escapeHtml(getFileName(result.path))} of ${escapeHtml(getFileName(result.parent_path))}</span>` : ''}
+        ${result.snippets && result.snippets.length ? `<button class="icon-btn show-matches" title="Show every match in this document" aria-label="Show every match in this document"><i data-lucide="list" class="meta-icon"></i> All matches</button>` : ''}
+        ${result.table != null ? `<button class="icon-btn export-table" data-table="${result.table}" title="Export table as CSV" aria-label="Export matched table as CSV"><i data-lucide="table" class="meta-icon"></i> Export table</button>` : ''}
       </div>
-      ${result.snippet ? `<div class="result-snippet">${highlightSnippet(result.snippet, searchInput.value)}</div>` : ''}
+      ${(result.snippets || []).map(snippet => `<div class="result-snippet">${renderSnippet(snippet)}</div>`).join('')}
     </div>
   `;
 }
 
+// Preview of the matched page, or the first one, for PDFs that are files of their own
+function renderThumbnail(result) {
+  if (result.parent_path || !/\.pdf$/i.test(result.path)) return '';
+  const page = result.page || 1;
+  return `<div class="result-thumbnail" data-page="${page}" title="Page ${page}"></div>`;
+}
+
+// Thumbnails are rendered on first use, so only ask for those scrolled into view
+const thumbnailObserver = new IntersectionObserver(entries => {
+  entries.forEach(entry => {
+    if (!entry.isIntersecting) return;
+    thumbnailObserver.unobserve(entry.target);
+    loadThumbnail(entry.target);
+  });
+}, { rootMargin: '200px' });
+
+async function loadThumbnail(placeholder) {
+  const path = placeholder.closest('.result-item').getAttribute('data-path');
+  const page = Number(placeholder.getAttribute('data-page'));
+  try {
+    const file = await invoke('get_thumbnail', { path, page });
+    const img = document.createElement('img');
+    img.src = convertFileSrc(file);
+    img.alt = `Preview of page ${page}`;
+    img.addEventListener('error', () => placeholder.remove());
+    placeholder.appendChild(img);
+  } catch (error) {
+    // No rasterizer installed, or a file that moved: show the text only
+    placeholder.remove();
+  }
+}
+
 // Simple hash function for generating stable IDs from folder paths
 function hashString(str) {
   let hash = 0;
@@ -769,19 +1426,54 @@ function formatTimestamp(timestamp) {
   return date.toLocaleDateString();
 }
 
-function highlightSnippet(snippet, query) {
-  if (!query || !snippet) return escapeHtml(snippet || '');
+// Render a plain-text snippet, wrapping the highlighted ranges in <mark>
+// Ranges are char offsets, so index by code point rather than UTF-16 unit
+function renderSnippet(snippet) {
+  if (!snippet || !snippet.text) return '';
+
+  const chars = Array.from(snippet.text);
+  const ranges = [...(snippet.highlights || [])].sort((a, b) => a.start - b.start);
+  let html = '';
+  let pos = 0;
+
+  for (const range of ranges) {
+    const start = Math.max(range.start, pos);
+    const end = Math.min(range.end, chars.length);
+    if (start >= end) continue;
+    html += escapeHtml(chars.slice(pos, start).join(''));
+    html += `<mark>${escapeHtml(chars.slice(start, end).join(''))}</mark>`;
+    pos = end;
+  }
+
+  return html + escapeHtml(chars.slice(pos).join(''));
+}
+
+// How often to look for folders due for a scheduled re-index
+const SCHEDULE_CHECK_INTERVAL_MS = 15 * 60 * 1000;
 
-  // Snippet from database already contains <mark> tags for highlighting
-  // Just sanitize any remaining content while preserving the mark tags
-  // Replace <mark> temporarily, escape everything, then restore marks
-  const marked = snippet.replace(/<mark>/g, '___MARK_START___')
-                        .replace(/<\/mark>/g, '___MARK_END___');
+// Offer to finish indexing runs cut short by a crash or shutdown
+async function resumeInterruptedJobs() {
+  const jobs = await invoke('get_interrupted_jobs').catch(() => []);
 
-  const escaped = escapeHtml(marked);
+  for (const job of jobs) {
+    const done = job.total_files - job.remaining_files;
+    const resume = confirm(
+      `Indexing of ${job.folder_path} was interrupted after ${done} of ${job.total_files} files.\n` +
+      'Resume now? Cancel keeps the files indexed so far and skips the rest.'
+    );
 
-  return escaped.replace(/___MARK_START___/g, '<mark>')
-                .replace(/___MARK_END___/g, '</mark>');
+    try {
+      if (resume) {
+        const result = await invoke('resume_indexing', { folderPath: job.folder_path });
+        showToast(`Indexed ${result.count} remaining PDFs in ${result.duration}ms`, 'success');
+      } else {
+        await invoke('discard_index_job', { folderPath: job.folder_path });
+      }
+    } catch (error) {
+      console.error('Error resuming indexing:', error);
+      showToast('Resuming indexing failed. Please re-index the folder.', 'error');
+    }
+  }
 }
 
 // Initialize
@@ -789,7 +1481,11 @@ async function init() {
   // Initialize license UI first
   await initLicenseUI();
   
+  await resumeInterruptedJobs();
+  await loadSemanticStatus();
   await loadIndexedFolders();
+  await runScheduledReindex();
+  setInterval(runScheduledReindex, SCHEDULE_CHECK_INTERVAL_MS);
 
   const count = await invoke('get_index_stats').catch(() => 0);
   if (count > 0) {
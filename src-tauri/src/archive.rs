@@ -0,0 +1,365 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Separates an archive's path from a member's path inside it, as in
+/// `bundle.zip!/specs/a.pdf`
+pub const MEMBER_SEPARATOR: &str = "!/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    SevenZip,
+    Tar,
+    TarGz,
+}
+
+/// Archive format of `path`, judged by its name
+pub fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".7z") {
+        Some(ArchiveKind::SevenZip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    archive_kind(path).is_some()
+}
+
+/// Virtual path under which a member of an archive is indexed
+pub fn member_path(archive: &Path, member: &str) -> String {
+    format!("{}{}{}", archive.to_string_lossy(), MEMBER_SEPARATOR, member)
+}
+
+/// Split a virtual path into the archive and the member inside it; None for
+/// paths of regular files
+pub fn split_member_path(path: &str) -> Option<(PathBuf, &str)> {
+    path.match_indices(MEMBER_SEPARATOR).find_map(|(index, _)| {
+        let archive = Path::new(&path[..index]);
+        let member = &path[index + MEMBER_SEPARATOR.len()..];
+        (is_archive(archive) && !member.is_empty()).then(|| (archive.to_path_buf(), member))
+    })
+}
+
+pub fn is_member_path(path: &str) -> bool {
+    split_member_path(path).is_some()
+}
+
+/// A PDF copied out of an archive for extraction
+#[derive(Debug, Clone)]
+pub struct ExtractedMember {
+    /// Path of the member inside the archive
+    pub name: String,
+    /// Where the member was written
+    pub file: PathBuf,
+}
+
+/// Copy every PDF in `archive` into `dest`, skipping members larger than
+/// `max_size`. Files in `dest` get generated names, so member paths such as
+/// `../../x.pdf` cannot escape it.
+pub fn extract_pdf_members(archive: &Path, dest: &Path, max_size: u64) -> Result<Vec<ExtractedMember>> {
+    let mut members = Vec::new();
+    for_each_file(archive, &mut |name, size, reader| {
+        if !is_pdf_name(name) {
+            return Ok(true);
+        }
+        if size > max_size {
+            log::info!("Skipping {}, {} bytes exceed the size limit", member_path(archive, name), size);
+            return Ok(true);
+        }
+
+        let file = dest.join(format!("member-{}.pdf", members.len()));
+        if copy_limited(reader, &file, max_size)? {
+            members.push(ExtractedMember { name: name.to_string(), file });
+        } else {
+            // The header understated the size, e.g. a decompression bomb
+            log::warn!("Skipping {}, it expands beyond the size limit", member_path(archive, name));
+            let _ = fs::remove_file(&file);
+        }
+        Ok(true)
+    })?;
+    Ok(members)
+}
+
+/// Copy one member of `archive` to `dest`, failing if it is larger than
+/// `max_size`
+pub fn extract_member(archive: &Path, member: &str, dest: &Path, max_size: u64) -> Result<()> {
+    let mut found = false;
+    for_each_file(archive, &mut |name, _size, reader| {
+        if name != member {
+            return Ok(true);
+        }
+        if !copy_limited(reader, dest, max_size)? {
+            let _ = fs::remove_file(dest);
+            anyhow::bail!("{} expands beyond the size limit of {} bytes", member, max_size);
+        }
+        found = true;
+        Ok(false)
+    })?;
+
+    if !found {
+        anyhow::bail!("{} does not contain {}", archive.display(), member);
+    }
+    Ok(())
+}
+
+/// Delete the entries of `dir` last modified more than `max_age` ago,
+/// returning how many were removed
+pub fn remove_stale_copies(dir: &Path, max_age: Duration) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age >= max_age);
+        if !stale {
+            continue;
+        }
+        let path = entry.path();
+        let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        if result.is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+fn is_pdf_name(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false)
+}
+
+/// Copy at most `max_size` bytes to `dest`; false if there was more
+fn copy_limited(reader: &mut dyn Read, dest: &Path, max_size: u64) -> Result<bool> {
+    let mut out = File::create(dest)
+        .with_context(|| format!("Failed to create {}", dest.display()))?;
+    let copied = std::io::copy(&mut reader.take(max_size.saturating_add(1)), &mut out)
+        .context("Failed to decompress archive member")?;
+    Ok(copied <= max_size)
+}
+
+/// Callback for each regular file in an archive: its path, its uncompressed
+/// size and a reader of its contents. Returns false to stop.
+type FileVisitor<'a> = dyn FnMut(&str, u64, &mut dyn Read) -> Result<bool> + 'a;
+
+fn for_each_file(archive: &Path, visit: &mut FileVisitor) -> Result<()> {
+    let kind = archive_kind(archive)
+        .with_context(|| format!("{} is not a supported archive", archive.display()))?;
+    let open = || File::open(archive).with_context(|| format!("Failed to open {}", archive.display()));
+
+    match kind {
+        ArchiveKind::Zip => for_each_zip_file(open()?, visit),
+        ArchiveKind::Tar => for_each_tar_file(BufReader::new(open()?), visit),
+        ArchiveKind::TarGz => for_each_tar_file(flate2::read::GzDecoder::new(BufReader::new(open()?)), visit),
+        ArchiveKind::SevenZip => for_each_7z_file(archive, visit),
+    }
+    .with_context(|| format!("Failed to read archive {}", archive.display()))
+}
+
+fn for_each_zip_file(file: File, visit: &mut FileVisitor) -> Result<()> {
+    let mut zip = zip::ZipArchive::new(BufReader::new(file))?;
+    for index in 0..zip.len() {
+        let mut entry = match zip.by_index(index) {
+            Ok(entry) => entry,
+            Err(e) => {
+                // E.g. an encrypted or unsupported entry; the rest may be fine
+                log::warn!("Skipping archive entry {}: {}", index, e);
+                continue;
+            }
+        };
+        if !entry.is_file() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let size = entry.size();
+        if !visit(&name, size, &mut entry)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn for_each_tar_file<R: Read>(reader: R, visit: &mut FileVisitor) -> Result<()> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().to_string();
+        let size = entry.size();
+        if !visit(&name, size, &mut entry)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn for_each_7z_file(archive: &Path, visit: &mut FileVisitor) -> Result<()> {
+    let mut reader = sevenz_rust::SevenZReader::open(archive, sevenz_rust::Password::empty())
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let mut failure = None;
+    reader
+        .for_each_entries(|entry, data| {
+            if entry.is_directory() {
+                return Ok(true);
+            }
+            match visit(entry.name(), entry.size(), data) {
+                Ok(keep_going) => {
+                    // Members of a solid block share one stream, so skip
+                    // whatever the visitor left unread
+                    std::io::copy(data, &mut std::io::sink())?;
+                    Ok(keep_going)
+                }
+                Err(e) => {
+                    failure = Some(e);
+                    Ok(false)
+                }
+            }
+        })
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}_{}", name, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        for (name, contents) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path, files: &[(&str, &[u8])]) {
+        let encoder = flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, *contents).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_member_paths() {
+        let path = member_path(Path::new("/docs/bundle.zip"), "specs/a.pdf");
+        assert_eq!(path, "/docs/bundle.zip!/specs/a.pdf");
+        assert_eq!(
+            split_member_path(&path),
+            Some((PathBuf::from("/docs/bundle.zip"), "specs/a.pdf"))
+        );
+        assert_eq!(
+            split_member_path("/docs/old.TAR.GZ!/x!/y.pdf"),
+            Some((PathBuf::from("/docs/old.TAR.GZ"), "x!/y.pdf"))
+        );
+        assert_eq!(split_member_path("/docs/wow!/a.pdf"), None);
+        assert_eq!(split_member_path("/docs/a.pdf"), None);
+    }
+
+    #[test]
+    fn test_extract_pdf_members_from_zip_and_tar_gz() {
+        let dir = scratch_dir("archive_members");
+        let files: &[(&str, &[u8])] = &[
+            ("specs/a.pdf", b"%PDF-1.4 a"),
+            ("notes.txt", b"not a pdf"),
+            ("other/B.PDF", b"%PDF-1.4 b"),
+            ("big.pdf", &[b'x'; 64]),
+        ];
+
+        for archive in [dir.join("bundle.zip"), dir.join("bundle.tar.gz")] {
+            if archive_kind(&archive) == Some(ArchiveKind::Zip) {
+                write_zip(&archive, files);
+            } else {
+                write_tar_gz(&archive, files);
+            }
+            let dest = scratch_dir("archive_dest");
+
+            let members = extract_pdf_members(&archive, &dest, 32).unwrap();
+            let names: Vec<&str> = members.iter().map(|m| m.name.as_str()).collect();
+            assert_eq!(names, vec!["specs/a.pdf", "other/B.PDF"], "{}", archive.display());
+            for member in &members {
+                assert!(member.file.starts_with(&dest));
+            }
+            assert_eq!(fs::read(&members[0].file).unwrap(), b"%PDF-1.4 a");
+
+            let opened = dest.join("a.pdf");
+            extract_member(&archive, "specs/a.pdf", &opened, 32).unwrap();
+            assert_eq!(fs::read(&opened).unwrap(), b"%PDF-1.4 a");
+            assert!(extract_member(&archive, "missing.pdf", &opened, 32).is_err());
+            fs::remove_file(&opened).unwrap();
+            assert!(extract_member(&archive, "specs/a.pdf", &opened, 4).is_err());
+            assert!(!opened.exists());
+
+            // Copies opened earlier are cleaned up once they are old enough
+            assert_eq!(remove_stale_copies(&dest, Duration::from_secs(3600)), 0);
+            assert_eq!(remove_stale_copies(&dest, Duration::ZERO), members.len());
+            assert_eq!(fs::read_dir(&dest).unwrap().count(), 0);
+
+            fs::remove_dir_all(&dest).unwrap();
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_pdf_members_from_7z() {
+        let dir = scratch_dir("archive_7z");
+        fs::create_dir_all(dir.join("src/specs")).unwrap();
+        fs::write(dir.join("src/readme.txt"), b"read me first").unwrap();
+        fs::write(dir.join("src/specs/a.pdf"), b"%PDF-1.4 a").unwrap();
+        fs::write(dir.join("src/specs/b.pdf"), b"%PDF-1.4 b").unwrap();
+        let archive = dir.join("bundle.7z");
+        sevenz_rust::compress_to_path(dir.join("src"), &archive).unwrap();
+
+        let dest = scratch_dir("archive_7z_dest");
+        let mut members = extract_pdf_members(&archive, &dest, 1024).unwrap();
+        members.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].name, "specs/a.pdf");
+        assert_eq!(fs::read(&members[0].file).unwrap(), b"%PDF-1.4 a");
+        assert_eq!(fs::read(&members[1].file).unwrap(), b"%PDF-1.4 b");
+
+        fs::remove_dir_all(&dest).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+use crate::pdf_text;
+use anyhow::Result;
+use flate2::read::ZlibDecoder;
+use pdf_extract::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::Path;
+
+/// Separates a PDF's path from the name of a file embedded in it, as in
+/// `report.pdf!/data.csv`
+pub const ATTACHMENT_SEPARATOR: &str = "!/";
+
+/// Name trees nested deeper than this are treated as malformed
+const MAX_TREE_DEPTH: usize = 32;
+
+/// Extensions of attachments indexed as plain text
+const TEXT_EXTENSIONS: &[&str] = &["txt", "csv", "tsv", "md", "json", "xml", "html", "htm", "log", "ini", "yaml", "yml"];
+
+/// A file embedded in a PDF
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String,
+    /// Sent from extraction workers as base32 rather than an array of numbers
+    #[serde(with = "base32_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// How an attachment is indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Pdf,
+    Text,
+}
+
+impl Attachment {
+    /// None for attachments that are not indexed, e.g. spreadsheets or images
+    pub fn kind(&self) -> Option<AttachmentKind> {
+        let extension = Path::new(&self.name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if extension == "pdf" || self.data.starts_with(b"%PDF") {
+            Some(AttachmentKind::Pdf)
+        } else if TEXT_EXTENSIONS.contains(&extension.as_str()) {
+            Some(AttachmentKind::Text)
+        } else {
+            None
+        }
+    }
+
+    /// Contents of a text attachment, with markup removed from HTML and XML
+    pub fn text(&self) -> String {
+        let data = self.data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&self.data);
+        let text = String::from_utf8_lossy(data);
+        let extension = Path::new(&self.name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "html" | "htm" | "xml" => strip_tags(&text),
+            _ => text.trim().to_string(),
+        }
+    }
+}
+
+/// Virtual path under which an attachment of `parent` is indexed
+pub fn attachment_path(parent: &str, name: &str) -> String {
+    format!("{}{}{}", parent, ATTACHMENT_SEPARATOR, name)
+}
+
+/// Files embedded in `doc`, both in its EmbeddedFiles name tree (which
+/// includes the members of a portfolio) and in file attachment annotations.
+/// Files larger than `max_size` are skipped, checked while decompressing.
+pub fn extract_attachments(doc: &Document, max_size: u64) -> Vec<Attachment> {
+    let mut specs = Vec::new();
+    if let Ok(tree) = doc
+        .catalog()
+        .and_then(|catalog| catalog.get_deref(b"Names", doc))
+        .and_then(Object::as_dict)
+        .and_then(|names| names.get_deref(b"EmbeddedFiles", doc))
+        .and_then(Object::as_dict)
+    {
+        collect_name_tree(doc, tree, 0, &mut specs);
+    }
+    for page_id in doc.get_pages().into_values() {
+        for annotation in doc.get_page_annotations(page_id).unwrap_or_default() {
+            let is_attachment = annotation
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .map(|subtype| subtype == b"FileAttachment")
+                .unwrap_or(false);
+            if let (true, Ok(spec)) = (is_attachment, annotation.get_deref(b"FS", doc).and_then(Object::as_dict)) {
+                specs.push(spec);
+            }
+        }
+    }
+
+    // The same stream may be referenced from the name tree and an annotation
+    let mut seen: HashSet<ObjectId> = HashSet::new();
+    let mut attachments = Vec::new();
+    for spec in specs {
+        let Some((stream_id, name)) = file_spec_stream(doc, spec) else {
+            continue;
+        };
+        if !seen.insert(stream_id) {
+            continue;
+        }
+        let Ok(stream) = doc.get_object(stream_id).and_then(Object::as_stream) else {
+            continue;
+        };
+        match decode_limited(stream, max_size) {
+            Ok(Some(data)) => attachments.push(Attachment { name, data }),
+            Ok(None) => log::debug!("Skipping attachment {}: too large", name),
+            Err(e) => log::debug!("Failed to decode attachment {}: {:#}", name, e),
+        }
+    }
+
+    attachments
+}
+
+/// Decoded data of an embedded file stream, or None when it is larger than
+/// `max_size`. Unlike `Stream::get_plain_content`, a small stream that
+/// inflates to gigabytes is given up on after `max_size` bytes. Only plain
+/// and Flate-compressed streams are read, which covers embedded files in
+/// practice.
+fn decode_limited(stream: &Stream, max_size: u64) -> Result<Option<Vec<u8>>> {
+    let filters = stream.filters().unwrap_or_default();
+    let filters: Vec<&str> = filters.iter().map(String::as_str).collect();
+    let data = match filters.as_slice() {
+        [] => stream.content.clone(),
+        ["FlateDecode"] if stream.dict.get(b"DecodeParms").is_err() => {
+            let mut data = Vec::new();
+            ZlibDecoder::new(stream.content.as_slice())
+                .take(max_size.saturating_add(1))
+                .read_to_end(&mut data)?;
+            data
+        }
+        other => anyhow::bail!("unsupported filters {:?}", other),
+    };
+    Ok((data.len() as u64 <= max_size).then_some(data))
+}
+
+/// Serde representation of attachment data as an unpadded base32 string
+mod base32_bytes {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    const ALPHABET: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base32::encode(ALPHABET, data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base32::decode(ALPHABET, &encoded).ok_or_else(|| de::Error::custom("invalid base32 data"))
+    }
+}
+
+/// Gather the file specifications of a name tree node and its kids
+fn collect_name_tree<'a>(doc: &'a Document, node: &'a Dictionary, depth: usize, specs: &mut Vec<&'a Dictionary>) {
+    if depth > MAX_TREE_DEPTH {
+        return;
+    }
+    if let Ok(names) = node.get_deref(b"Names", doc).and_then(Object::as_array) {
+        // Alternating keys and values
+        for value in names.iter().skip(1).step_by(2) {
+            if let Ok((_, Object::Dictionary(spec))) = doc.dereference(value) {
+                specs.push(spec);
+            }
+        }
+    }
+    if let Ok(kids) = node.get_deref(b"Kids", doc).and_then(Object::as_array) {
+        for kid in kids {
+            if let Ok((_, Object::Dictionary(kid))) = doc.dereference(kid) {
+                collect_name_tree(doc, kid, depth + 1, specs);
+            }
+        }
+    }
+}
+
+/// Embedded file stream and file name of a file specification
+fn file_spec_stream(doc: &Document, spec: &Dictionary) -> Option<(ObjectId, String)> {
+    let embedded = spec.get_deref(b"EF", doc).and_then(Object::as_dict).ok()?;
+    let stream_id = embedded
+        .get(b"UF")
+        .or_else(|_| embedded.get(b"F"))
+        .and_then(Object::as_reference)
+        .ok()?;
+
+    let name = [b"UF".as_slice(), b"F", b"Desc"]
+        .iter()
+        .find_map(|key| spec.get_deref(key, doc).and_then(Object::as_str).ok())
+        .map(pdf_text::decode_text_string)
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| format!("attachment-{}", stream_id.0));
+
+    // Only the last path component, so names cannot look like nested paths
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default().to_string();
+    Some((stream_id, name))
+}
+
+/// Text of an HTML or XML document, without tags
+fn strip_tags(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut in_tag = false;
+    for c in markup.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A PDF with one page, `notes.txt` in its EmbeddedFiles name tree and
+/// `nested.pdf` attached through an annotation
+#[cfg(test)]
+pub(crate) fn write_pdf_with_attachments(path: &Path) {
+    use pdf_extract::StringFormat;
+
+    fn add_file(doc: &mut Document, name: &str, data: &[u8]) -> ObjectId {
+        let stream_id = doc.add_object(Stream::new(Dictionary::new(), data.to_vec()));
+        let mut embedded = Dictionary::new();
+        embedded.set("F", Object::Reference(stream_id));
+        let mut spec = Dictionary::new();
+        spec.set("Type", Object::Name(b"Filespec".to_vec()));
+        spec.set("F", Object::String(name.as_bytes().to_vec(), StringFormat::Literal));
+        spec.set("EF", Object::Dictionary(embedded));
+        doc.add_object(spec)
+    }
+
+    let mut doc = Document::with_version("1.7");
+    let notes = add_file(&mut doc, "notes.txt", b"quarterly reconciliation notes");
+    let nested = add_file(&mut doc, "nested.pdf", b"%PDF-1.4 nested document");
+
+    let mut annotation = Dictionary::new();
+    annotation.set("Type", Object::Name(b"Annot".to_vec()));
+    annotation.set("Subtype", Object::Name(b"FileAttachment".to_vec()));
+    annotation.set("FS", Object::Reference(nested));
+    let annotation_id = doc.add_object(annotation);
+
+    let pages_id = doc.new_object_id();
+    let mut page = Dictionary::new();
+    page.set("Type", Object::Name(b"Page".to_vec()));
+    page.set("Parent", Object::Reference(pages_id));
+    page.set("MediaBox", Object::Array(vec![0.into(), 0.into(), 612.into(), 792.into()]));
+    page.set("Contents", Object::Reference(doc.add_object(Stream::new(Dictionary::new(), Vec::new()))));
+    page.set("Annots", Object::Array(vec![Object::Reference(annotation_id)]));
+    let page_id = doc.add_object(page);
+
+    let mut pages = Dictionary::new();
+    pages.set("Type", Object::Name(b"Pages".to_vec()));
+    pages.set("Kids", Object::Array(vec![Object::Reference(page_id)]));
+    pages.set("Count", Object::Integer(1));
+    doc.objects.insert(pages_id, Object::Dictionary(pages));
+
+    let mut tree = Dictionary::new();
+    tree.set(
+        "Names",
+        Object::Array(vec![Object::String(b"notes.txt".to_vec(), StringFormat::Literal), Object::Reference(notes)]),
+    );
+    let mut names = Dictionary::new();
+    names.set("EmbeddedFiles", Object::Dictionary(tree));
+    let mut catalog = Dictionary::new();
+    catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+    catalog.set("Pages", Object::Reference(pages_id));
+    catalog.set("Names", Object::Dictionary(names));
+    let catalog_id = doc.add_object(catalog);
+    doc.trailer.set("Root", Object::Reference(catalog_id));
+
+    doc.save(path).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_attachments_from_name_tree_and_annotations() {
+        let path = std::env::temp_dir().join(format!("attachments_{}.pdf", uuid::Uuid::new_v4()));
+        write_pdf_with_attachments(&path);
+        let doc = Document::load(&path).unwrap();
+
+        let mut attachments = extract_attachments(&doc, u64::MAX);
+        attachments.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0].name, "nested.pdf");
+        assert_eq!(attachments[0].kind(), Some(AttachmentKind::Pdf));
+        assert_eq!(attachments[1].name, "notes.txt");
+        assert_eq!(attachments[1].kind(), Some(AttachmentKind::Text));
+        assert_eq!(attachments[1].text(), "quarterly reconciliation notes");
+
+        // Too large attachments are left out
+        assert!(extract_attachments(&doc, 10).is_empty());
+
+        // Attachments survive the trip from an extraction worker
+        let json = serde_json::to_string(&attachments[1]).unwrap();
+        assert_eq!(serde_json::from_str::<Attachment>(&json).unwrap().data, attachments[1].data);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_compressed_attachments_are_inflated_up_to_the_limit() {
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&vec![b'a'; 1_000_000]).unwrap();
+        let mut dict = Dictionary::new();
+        dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+        let stream = Stream::new(dict, encoder.finish().unwrap());
+        assert!(stream.content.len() < 10_000);
+
+        assert_eq!(decode_limited(&stream, 1_000_000).unwrap().map(|data| data.len()), Some(1_000_000));
+        assert_eq!(decode_limited(&stream, 999_999).unwrap(), None);
+    }
+
+    #[test]
+    fn test_attachment_names_and_text() {
+        assert_eq!(attachment_path("/docs/report.pdf", "data.csv"), "/docs/report.pdf!/data.csv");
+        assert_eq!(pdf_text::decode_text_string(b"\xFE\xFF\x00a\x00.\x00t\x00x\x00t"), "a.txt");
+        assert_eq!(pdf_text::decode_text_string(b"caf\xE9.txt"), "caf\u{e9}.txt");
+
+        let html = Attachment { name: "page.HTML".to_string(), data: b"<p>Hello <b>world</b></p>".to_vec() };
+        assert_eq!(html.text(), "Hello world");
+        let image = Attachment { name: "scan.png".to_string(), data: vec![0x89, b'P', b'N', b'G'] };
+        assert_eq!(image.kind(), None);
+    }
+}
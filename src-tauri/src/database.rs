@@ -1,10 +1,21 @@
+use crate::archive;
+use crate::entities::{Entity, EntityKind};
+use crate::fingerprint;
+use crate::highlight::{self, Snippet};
+use crate::links::{self, Link, LinkFilter};
+use crate::ocr::OcrPage;
+use crate::outline::{self, OutlineEntry};
+use crate::pdf_text::{Annotation, FormField};
+use crate::semantic::{self, ChunkEmbedding};
+use crate::similarity;
+use crate::tables::{self, Table};
 use rusqlite::{params, Connection, Result as SqliteResult, Transaction};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PdfDocument {
     pub id: Option<i64>,
     pub path: String,
@@ -13,6 +24,155 @@ pub struct PdfDocument {
     pub size: i64,
     pub modified: i64,
     pub pages: Option<i32>,
+    /// Char offset in `content` at which each page starts
+    #[serde(default)]
+    pub page_offsets: Vec<i64>,
+    /// Embedded chunks for semantic search, empty when no model is installed
+    #[serde(skip)]
+    pub chunks: Vec<ChunkEmbedding>,
+    /// Fingerprint of the model that embedded `chunks`
+    #[serde(skip)]
+    pub embedding_model: Option<String>,
+    /// SHA-256 of the file bytes
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// SimHash of the extracted text, for near-duplicate detection
+    #[serde(default)]
+    pub simhash: Option<i64>,
+    /// Whether `content` was recognized by OCR rather than extracted
+    #[serde(default)]
+    pub ocr: bool,
+    /// Per-page OCR output, empty for documents with a text layer
+    #[serde(skip)]
+    pub ocr_pages: Vec<OcrPage>,
+    /// Outcome of text extraction, telling failed files apart from empty ones
+    #[serde(default)]
+    pub extraction_status: ExtractionStatus,
+    /// For files embedded in a PDF, the path of that PDF
+    #[serde(default)]
+    pub parent_path: Option<String>,
+    /// Comments and notes attached to pages
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// Filled-in form fields
+    #[serde(default)]
+    pub form_fields: Vec<FormField>,
+    /// Bookmarks, in reading order
+    #[serde(default)]
+    pub outline: Vec<OutlineEntry>,
+    /// Tables reconstructed from the page layout
+    #[serde(default)]
+    pub tables: Vec<Table>,
+    /// Link targets and URLs written in the text
+    #[serde(default)]
+    pub links: Vec<Link>,
+    /// Emails, dates, amounts and other entities mentioned in the text
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+    /// Version of the extraction that produced this document
+    #[serde(default)]
+    pub extractor_version: i64,
+}
+
+/// Outcome of extracting the text of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionStatus {
+    /// Text was extracted (or recognized by OCR)
+    #[default]
+    Ok,
+    /// The file parsed fine but contains no text
+    Empty,
+    /// The PDF library returned an error
+    Failed,
+    /// The PDF library panicked on this file
+    Panicked,
+    /// Skipped for being below `min_file_size`
+    TooSmall,
+    /// Skipped for being above `max_file_size`
+    TooLarge,
+    /// Extraction took longer than the timeout and was killed
+    TimedOut,
+    /// The extraction worker died, e.g. from a stack overflow or the memory limit
+    Crashed,
+    /// The PDF is encrypted and no known password opens it
+    Encrypted,
+}
+
+impl ExtractionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExtractionStatus::Ok => "ok",
+            ExtractionStatus::Empty => "empty",
+            ExtractionStatus::Failed => "failed",
+            ExtractionStatus::Panicked => "panicked",
+            ExtractionStatus::TooSmall => "too_small",
+            ExtractionStatus::TooLarge => "too_large",
+            ExtractionStatus::TimedOut => "timed_out",
+            ExtractionStatus::Crashed => "crashed",
+            ExtractionStatus::Encrypted => "encrypted",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "empty" => ExtractionStatus::Empty,
+            "failed" => ExtractionStatus::Failed,
+            "panicked" => ExtractionStatus::Panicked,
+            "too_small" => ExtractionStatus::TooSmall,
+            "too_large" => ExtractionStatus::TooLarge,
+            "timed_out" => ExtractionStatus::TimedOut,
+            "crashed" => ExtractionStatus::Crashed,
+            "encrypted" => ExtractionStatus::Encrypted,
+            _ => ExtractionStatus::Ok,
+        }
+    }
+
+    /// Whether extraction broke, as opposed to finishing or being skipped on purpose
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            ExtractionStatus::Failed
+                | ExtractionStatus::Panicked
+                | ExtractionStatus::TimedOut
+                | ExtractionStatus::Crashed
+                | ExtractionStatus::Encrypted
+        )
+    }
+}
+
+/// A file that could not be indexed cleanly, kept until it indexes successfully
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexError {
+    pub path: String,
+    pub folder_path: String,
+    /// Indexing step that failed, e.g. "read", "extract" or "ocr"
+    pub phase: String,
+    /// Machine-readable error category, e.g. "io" or "panicked"
+    pub kind: String,
+    pub message: String,
+    /// Unix time of the latest failure
+    pub timestamp: i64,
+    /// Number of indexing runs that failed on this file
+    pub attempts: i64,
+}
+
+impl IndexError {
+    pub fn new(path: &str, folder_path: &str, phase: &str, kind: &str, message: impl Into<String>) -> Self {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        IndexError {
+            path: path.to_string(),
+            folder_path: folder_path.to_string(),
+            phase: phase.to_string(),
+            kind: kind.to_string(),
+            message: message.into(),
+            timestamp,
+            attempts: 1,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,15 +182,170 @@ pub struct SearchResult {
     pub size: i64,
     pub modified: i64,
     pub pages: Option<i32>,
-    pub snippet: Option<String>,
+    pub snippets: Vec<Snippet>,
+    /// Text of this document was recognized by OCR and may contain errors
+    pub ocr: bool,
+    /// For attachments, the PDF they are embedded in
+    pub parent_path: Option<String>,
+    /// 1-based page of the first match in the text, when the page layout is known
+    pub page: Option<i32>,
+    /// Bookmark titles enclosing `page`, outermost first
+    pub section: Vec<String>,
+    /// Index of the first table with a match, for exporting it
+    pub table: Option<usize>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchFilters {
     pub min_size: Option<i64>,
     pub max_size: Option<i64>,
     pub date_from: Option<String>,
     pub date_to: Option<String>,
+    /// Only documents with comments or notes (`has:comments`)
+    #[serde(default)]
+    pub has_comments: bool,
+    /// Only documents with filled-in form fields (`has:forms`)
+    #[serde(default)]
+    pub has_form_fields: bool,
+    /// Match bookmark titles only (`in:bookmarks`)
+    #[serde(default)]
+    pub bookmarks_only: bool,
+    /// Only documents with tables (`has:tables`)
+    #[serde(default)]
+    pub has_tables: bool,
+    /// Match table cells only (`in:tables`)
+    #[serde(default)]
+    pub tables_only: bool,
+    /// Only documents linking to each of these hosts or URLs (`link:example.com`)
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// Only documents mentioning each of these entities (`doi:10.1000/182`),
+    /// with normalized values
+    #[serde(default)]
+    pub mentions: Vec<(EntityKind, String)>,
+}
+
+impl SearchFilters {
+    /// Move search operators such as `has:comments` out of `query` into these
+    /// filters, returning the rest of the query. Entity names followed by
+    /// something that is not such an entity, as in `Date: 2024` or
+    /// `amount:total`, stay in the query as plain words.
+    pub fn take_query_operators(&mut self, query: &str) -> anyhow::Result<String> {
+        let mut rest: Vec<std::borrow::Cow<str>> = Vec::new();
+        for token in query.split_whitespace() {
+            match token.split_once(':') {
+                Some((operator, value)) if operator.eq_ignore_ascii_case("has") => {
+                    match value.to_lowercase().as_str() {
+                        "comments" | "comment" | "annotations" => self.has_comments = true,
+                        "forms" | "form" | "fields" => self.has_form_fields = true,
+                        "tables" | "table" => self.has_tables = true,
+                        _ => anyhow::bail!("Unknown filter '{}'. Use has:comments, has:forms or has:tables.", token),
+                    }
+                }
+                Some((operator, value)) if operator.eq_ignore_ascii_case("in") => {
+                    match value.to_lowercase().as_str() {
+                        "bookmarks" | "bookmark" | "outline" => self.bookmarks_only = true,
+                        "tables" | "table" => self.tables_only = true,
+                        _ => anyhow::bail!("Unknown filter '{}'. Use in:bookmarks or in:tables.", token),
+                    }
+                }
+                Some((operator, value)) if operator.eq_ignore_ascii_case("link") => {
+                    if LinkFilter::parse(value).is_none() {
+                        anyhow::bail!("Missing link target in '{}'. Use e.g. link:example.com.", token);
+                    }
+                    self.links.push(value.to_string());
+                }
+                Some((operator, value)) => match EntityKind::from_name(operator).map(|kind| (kind, kind.normalize(value))) {
+                    Some((kind, Some(value))) => self.mentions.push((kind, value)),
+                    // Quoted, as FTS5 would read the colon as a column filter
+                    Some((_, None)) => rest.push(format!("\"{}\"", token.replace('"', "\"\"")).into()),
+                    None => rest.push(token.into()),
+                },
+                None => rest.push(token.into()),
+            }
+        }
+        Ok(rest.join(" "))
+    }
+}
+
+/// A document linking to a host, with its links there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkingDocument {
+    pub path: String,
+    pub title: String,
+    pub links: Vec<Link>,
+}
+
+/// How many of the matching documents mention an entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityFacet {
+    pub kind: EntityKind,
+    pub value: String,
+    pub documents: i64,
+}
+
+/// A single occurrence of the query inside a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentMatch {
+    pub snippet: Snippet,
+    /// 1-based page number, when the page layout of the document is known
+    pub page: Option<i32>,
+    /// Bookmark titles enclosing `page`, outermost first
+    pub section: Vec<String>,
+}
+
+/// One page of matches for a document, plus the total number of matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentMatches {
+    pub path: String,
+    pub total: usize,
+    pub offset: usize,
+    pub matches: Vec<DocumentMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateFile {
+    pub path: String,
+    pub size: i64,
+    pub modified: i64,
+}
+
+/// Files sharing the same bytes (`exact`) or nearly the same text (`near`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub exact: bool,
+    pub files: Vec<DuplicateFile>,
+    /// Bytes that would be freed by keeping only the largest file
+    pub reclaimable_bytes: i64,
+}
+
+impl DuplicateGroup {
+    fn new(exact: bool, mut files: Vec<DuplicateFile>) -> Self {
+        files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        let reclaimable_bytes = files.iter().skip(1).map(|f| f.size).sum();
+        DuplicateGroup { exact, files, reclaimable_bytes }
+    }
+}
+
+/// An indexing run that has not finished, e.g. because the app was closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexJob {
+    pub folder_path: String,
+    pub started_at: i64,
+    pub total_files: i64,
+    /// Files queued by the job that are not committed to the index yet
+    pub remaining_files: i64,
+}
+
+/// An archive whose members were all written to the index
+#[derive(Debug, Clone)]
+pub struct ArchiveRecord {
+    pub path: String,
+    pub folder_path: String,
+    pub modified: i64,
+    pub size: i64,
+    /// Virtual paths of the members indexed from it
+    pub members: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +353,46 @@ pub struct IndexedFolder {
     pub path: String,
     pub last_indexed: i64,
     pub pdf_count: i64,
+    #[serde(default)]
+    pub settings: FolderSettings,
+}
+
+/// Indexing settings of one folder
+/// Unset values fall back to the app-wide defaults, so a folder only stores
+/// what its owner changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FolderSettings {
+    /// Skip files larger than this (bytes)
+    pub max_file_size: Option<u64>,
+    /// Skip files smaller than this (bytes)
+    pub min_file_size: Option<u64>,
+    /// Threads used to extract this folder (0 = all cores)
+    pub max_threads: Option<usize>,
+    /// Subfolder levels to descend into (0 = only the folder itself)
+    pub max_depth: Option<usize>,
+    /// Follow symbolic links while scanning
+    pub follow_symlinks: Option<bool>,
+    /// Directories outside the folder that followed symlinks may lead into
+    #[serde(default)]
+    pub allowed_roots: Vec<String>,
+    /// Stay on the folder's filesystem instead of descending into other mounts
+    pub same_file_system: Option<bool>,
+    /// Index PDFs inside ZIP, 7z and tar archives
+    pub index_archives: Option<bool>,
+    /// Index text, Markdown, HTML, EPUB, DOCX and ODT files next to PDFs
+    pub index_other_formats: Option<bool>,
+    /// Run OCR on PDFs without a text layer
+    pub enable_ocr: Option<bool>,
+    /// Tesseract languages OCR recognizes, e.g. `eng+deu`
+    pub ocr_language: Option<String>,
+    /// Re-index the folder automatically once this many hours have passed
+    pub reindex_interval_hours: Option<u32>,
+    /// Globs a file must match one of to be indexed; empty means all files
+    #[serde(default)]
+    pub include_patterns: Vec<String>,
+    /// Globs of files and directories to skip, in gitignore syntax
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
 }
 
 #[derive(Clone)]
@@ -87,7 +442,11 @@ impl Database {
                 size INTEGER NOT NULL,
                 modified INTEGER NOT NULL,
                 pages INTEGER,
-                folder_path TEXT DEFAULT ''
+                folder_path TEXT DEFAULT '',
+                page_offsets TEXT NOT NULL DEFAULT '',
+                content_hash TEXT,
+                simhash INTEGER,
+                ocr INTEGER NOT NULL DEFAULT 0
             )",
             [],
         )?;
@@ -99,6 +458,48 @@ impl Database {
             [],
         );
 
+        // Page offsets were added later; older rows simply have no page information
+        let _ = conn.execute(
+            "ALTER TABLE pdfs ADD COLUMN page_offsets TEXT NOT NULL DEFAULT ''",
+            [],
+        );
+
+        // Annotation and form field text, one entry per line, searchable
+        // alongside the content
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN annotations TEXT NOT NULL DEFAULT ''", []);
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN form_fields TEXT NOT NULL DEFAULT ''", []);
+        // Bookmark titles, one per line
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN outline TEXT NOT NULL DEFAULT ''", []);
+        // Table cells, one row per line and a blank line between tables
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN tables TEXT NOT NULL DEFAULT ''", []);
+
+        // An FTS table missing any of the columns above is rebuilt with them
+        let fts_exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'pdfs_fts'",
+            [],
+            |row| row.get(0),
+        )?;
+        let missing_columns = fts_exists && conn.prepare("SELECT tables FROM pdfs_fts LIMIT 0").is_err();
+        // Earlier triggers removed rows with a plain DELETE, which leaves the
+        // old terms of an external-content table in the index
+        let stale_triggers: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master
+             WHERE type = 'trigger' AND name IN ('pdfs_ad', 'pdfs_au') AND sql LIKE '%DELETE FROM pdfs_fts%'",
+            [],
+            |row| row.get(0),
+        )?;
+        let rebuild_fts = missing_columns || stale_triggers;
+        if rebuild_fts {
+            conn.execute_batch(
+                "DROP TRIGGER IF EXISTS pdfs_ai;
+                 DROP TRIGGER IF EXISTS pdfs_ad;
+                 DROP TRIGGER IF EXISTS pdfs_au;",
+            )?;
+        }
+        if missing_columns {
+            conn.execute("DROP TABLE pdfs_fts", [])?;
+        }
+
         // Create FTS5 virtual table with optimized tokenizer
         // Using porter tokenizer for better stemming support
         conn.execute(
@@ -106,6 +507,10 @@ impl Database {
                 path UNINDEXED,
                 title,
                 content,
+                annotations,
+                form_fields,
+                outline,
+                tables,
                 content=pdfs,
                 content_rowid=id,
                 tokenize='porter unicode61 remove_diacritics 1'
@@ -113,27 +518,277 @@ impl Database {
             [],
         )?;
 
-        // Create triggers to keep FTS index in sync
+        // Create triggers to keep FTS index in sync. Rows of an external-content
+        // table are removed with the 'delete' command and their old values.
         conn.execute(
             "CREATE TRIGGER IF NOT EXISTS pdfs_ai AFTER INSERT ON pdfs BEGIN
-                INSERT INTO pdfs_fts(rowid, path, title, content)
-                VALUES (new.id, new.path, new.title, new.content);
+                INSERT INTO pdfs_fts(rowid, path, title, content, annotations, form_fields, outline, tables)
+                VALUES (new.id, new.path, new.title, new.content, new.annotations, new.form_fields, new.outline,
+                        new.tables);
             END",
             [],
         )?;
 
         conn.execute(
             "CREATE TRIGGER IF NOT EXISTS pdfs_ad AFTER DELETE ON pdfs BEGIN
-                DELETE FROM pdfs_fts WHERE rowid = old.id;
+                INSERT INTO pdfs_fts(pdfs_fts, rowid, path, title, content, annotations, form_fields, outline, tables)
+                VALUES ('delete', old.id, old.path, old.title, old.content, old.annotations, old.form_fields,
+                        old.outline, old.tables);
             END",
             [],
         )?;
 
         conn.execute(
             "CREATE TRIGGER IF NOT EXISTS pdfs_au AFTER UPDATE ON pdfs BEGIN
-                DELETE FROM pdfs_fts WHERE rowid = old.id;
-                INSERT INTO pdfs_fts(rowid, path, title, content)
-                VALUES (new.id, new.path, new.title, new.content);
+                INSERT INTO pdfs_fts(pdfs_fts, rowid, path, title, content, annotations, form_fields, outline, tables)
+                VALUES ('delete', old.id, old.path, old.title, old.content, old.annotations, old.form_fields,
+                        old.outline, old.tables);
+                INSERT INTO pdfs_fts(rowid, path, title, content, annotations, form_fields, outline, tables)
+                VALUES (new.id, new.path, new.title, new.content, new.annotations, new.form_fields, new.outline,
+                        new.tables);
+            END",
+            [],
+        )?;
+
+        if rebuild_fts {
+            conn.execute("INSERT INTO pdfs_fts(pdfs_fts) VALUES('rebuild')", [])?;
+        }
+
+        // Fingerprints for duplicate detection, NULL for rows indexed before they existed
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN content_hash TEXT", []);
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN simhash INTEGER", []);
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN ocr INTEGER NOT NULL DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN extraction_status TEXT NOT NULL DEFAULT 'ok'", []);
+        // Files embedded in a PDF are indexed as documents of their own
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN parent_path TEXT", []);
+        // Whether links and entities were found in the content; rows indexed
+        // before they existed get them from their stored text
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN analyzed INTEGER NOT NULL DEFAULT 0", []);
+        // Rows from before extraction was versioned count as version 0
+        let _ = conn.execute("ALTER TABLE pdfs ADD COLUMN extractor_version INTEGER NOT NULL DEFAULT 0", []);
+
+        // Per-folder file filters, newline-separated globs
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN include_patterns TEXT NOT NULL DEFAULT ''", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN exclude_patterns TEXT NOT NULL DEFAULT ''", []);
+
+        // Per-folder indexing settings, NULL where the folder uses the default
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN max_file_size INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN min_file_size INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN max_threads INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN max_depth INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN follow_symlinks INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN enable_ocr INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN reindex_interval_hours INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN allowed_roots TEXT NOT NULL DEFAULT ''", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN same_file_system INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN index_archives INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN index_other_formats INTEGER", []);
+        let _ = conn.execute("ALTER TABLE indexed_folders ADD COLUMN ocr_language TEXT", []);
+
+        // Files that failed to index, with how often they have failed so far
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS index_errors (
+                path TEXT PRIMARY KEY,
+                folder_path TEXT NOT NULL,
+                phase TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                message TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 1
+            )",
+            [],
+        )?;
+
+        // Indexing runs in progress; a job's queue shrinks as documents are
+        // committed, so a job left behind by a crash records what is left to do
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS index_jobs (
+                folder_path TEXT PRIMARY KEY,
+                started_at INTEGER NOT NULL,
+                total_files INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS index_job_queue (
+                folder_path TEXT NOT NULL,
+                path TEXT NOT NULL,
+                PRIMARY KEY (folder_path, path)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_index_job_queue_path ON index_job_queue(path)",
+            [],
+        )?;
+
+        // Archives whose PDFs are indexed under `archive!/member` paths, with
+        // the archive's own mtime and size for incremental indexing
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS archives (
+                path TEXT PRIMARY KEY,
+                folder_path TEXT NOT NULL,
+                modified INTEGER NOT NULL,
+                size INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Chunk embeddings for semantic search, one row per chunk of a document
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS chunk_embeddings (
+                pdf_id INTEGER NOT NULL,
+                chunk_index INTEGER NOT NULL,
+                start_offset INTEGER NOT NULL,
+                end_offset INTEGER NOT NULL,
+                embedding BLOB NOT NULL,
+                model TEXT NOT NULL DEFAULT '',
+                dim INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (pdf_id, chunk_index)
+            )",
+            [],
+        )?;
+        // Embeddings stored before the model was recorded never match a model
+        // and are replaced on the next indexing run
+        let _ = conn.execute("ALTER TABLE chunk_embeddings ADD COLUMN model TEXT NOT NULL DEFAULT ''", []);
+        let _ = conn.execute("ALTER TABLE chunk_embeddings ADD COLUMN dim INTEGER NOT NULL DEFAULT 0", []);
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS pdfs_ad_chunks AFTER DELETE ON pdfs BEGIN
+                DELETE FROM chunk_embeddings WHERE pdf_id = old.id;
+            END",
+            [],
+        )?;
+
+        // Text recognized by OCR, kept per page with the engine's confidence
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ocr_pages (
+                pdf_id INTEGER NOT NULL,
+                page INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                confidence REAL NOT NULL,
+                PRIMARY KEY (pdf_id, page)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS pdfs_ad_ocr_pages AFTER DELETE ON pdfs BEGIN
+                DELETE FROM ocr_pages WHERE pdf_id = old.id;
+            END",
+            [],
+        )?;
+
+        // Comments and notes, with the page they are on and who wrote them
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS annotations (
+                pdf_id INTEGER NOT NULL,
+                idx INTEGER NOT NULL,
+                page INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                author TEXT,
+                contents TEXT NOT NULL,
+                PRIMARY KEY (pdf_id, idx)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS pdfs_ad_annotations AFTER DELETE ON pdfs BEGIN
+                DELETE FROM annotations WHERE pdf_id = old.id;
+            END",
+            [],
+        )?;
+
+        // Bookmarks in reading order; `level` is the nesting depth
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS outline (
+                pdf_id INTEGER NOT NULL,
+                idx INTEGER NOT NULL,
+                level INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                page INTEGER,
+                PRIMARY KEY (pdf_id, idx)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS pdfs_ad_outline AFTER DELETE ON pdfs BEGIN
+                DELETE FROM outline WHERE pdf_id = old.id;
+            END",
+            [],
+        )?;
+
+        // Tables found in the page layout; `rows` is a JSON array of rows of cells
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS document_tables (
+                pdf_id INTEGER NOT NULL,
+                idx INTEGER NOT NULL,
+                page INTEGER NOT NULL,
+                rows TEXT NOT NULL,
+                PRIMARY KEY (pdf_id, idx)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS pdfs_ad_document_tables AFTER DELETE ON pdfs BEGIN
+                DELETE FROM document_tables WHERE pdf_id = old.id;
+            END",
+            [],
+        )?;
+
+        // Link targets and URLs in the text; `host` is lowercase without
+        // `www.`, NULL for links such as `mailto:` that have none
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS links (
+                pdf_id INTEGER NOT NULL,
+                idx INTEGER NOT NULL,
+                page INTEGER,
+                url TEXT NOT NULL,
+                host TEXT,
+                PRIMARY KEY (pdf_id, idx)
+            )",
+            [],
+        )?;
+
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_links_host ON links(host)", [])?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS pdfs_ad_links AFTER DELETE ON pdfs BEGIN
+                DELETE FROM links WHERE pdf_id = old.id;
+            END",
+            [],
+        )?;
+
+        // Entities mentioned in each document with their normalized value
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS entities (
+                pdf_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                value TEXT NOT NULL,
+                count INTEGER NOT NULL,
+                PRIMARY KEY (pdf_id, kind, value)
+            )",
+            [],
+        )?;
+
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_entities_value ON entities(kind, value)", [])?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS pdfs_ad_entities AFTER DELETE ON pdfs BEGIN
+                DELETE FROM entities WHERE pdf_id = old.id;
+            END",
+            [],
+        )?;
+
+        // Attachments go away with the PDF they were extracted from
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS pdfs_ad_attachments AFTER DELETE ON pdfs BEGIN
+                DELETE FROM pdfs WHERE parent_path = old.path;
             END",
             [],
         )?;
@@ -155,6 +810,16 @@ impl Database {
             [],
         )?;
 
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_pdfs_content_hash ON pdfs(content_hash)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_pdfs_parent_path ON pdfs(parent_path)",
+            [],
+        )?;
+
         // Optimize FTS5 index for better search performance
         let _ = conn.execute("INSERT INTO pdfs_fts(pdfs_fts) VALUES('optimize')", []);
 
@@ -167,21 +832,11 @@ impl Database {
     }
 
     pub fn insert_pdf(&self, doc: &PdfDocument, folder_path: &str) -> anyhow::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let mut conn = self.conn.lock().unwrap();
 
-        conn.execute(
-            "INSERT OR REPLACE INTO pdfs (path, title, content, size, modified, pages, folder_path)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![
-                &doc.path,
-                &doc.title,
-                &doc.content,
-                doc.size,
-                doc.modified,
-                doc.pages,
-                folder_path
-            ],
-        )?;
+        let tx = conn.transaction()?;
+        write_document(&tx, doc, folder_path)?;
+        tx.commit()?;
 
         Ok(())
     }
@@ -193,23 +848,8 @@ impl Database {
 
         let tx = conn.transaction()?;
 
-        {
-            let mut stmt = tx.prepare(
-                "INSERT OR REPLACE INTO pdfs (path, title, content, size, modified, pages, folder_path)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
-            )?;
-
-            for doc in docs {
-                stmt.execute(params![
-                    &doc.path,
-                    &doc.title,
-                    &doc.content,
-                    doc.size,
-                    doc.modified,
-                    doc.pages,
-                    folder_path
-                ])?;
-            }
+        for doc in docs {
+            write_document(&tx, doc, folder_path)?;
         }
 
         tx.commit()?;
@@ -217,12 +857,90 @@ impl Database {
         Ok(())
     }
 
+    /// Documents of a folder that have text but no chunk embeddings made by
+    /// `model`, e.g. because they were indexed before it was installed or
+    /// embedded by a different model
+    pub fn get_documents_without_embeddings(
+        &self,
+        folder_path: &str,
+        model: &str,
+        dim: usize,
+    ) -> anyhow::Result<Vec<(i64, String)>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT p.id, p.content FROM pdfs p
+             WHERE p.folder_path = ?1 AND p.content != ''
+               AND NOT EXISTS (SELECT 1 FROM chunk_embeddings c
+                               WHERE c.pdf_id = p.id AND c.model = ?2 AND c.dim = ?3)"
+        )?;
+
+        let rows = stmt.query_map(params![folder_path, model, dim as i64], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.filter_map(|r| r.ok()).collect())
+    }
+
+    /// Documents of a folder indexed before links and entities were found in
+    /// the text, with their content and page offsets
+    pub fn get_documents_without_analysis(&self, folder_path: &str) -> anyhow::Result<Vec<(i64, String, Vec<i64>)>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, content, page_offsets FROM pdfs WHERE folder_path = ?1 AND analyzed = 0"
+        )?;
+
+        let rows = stmt.query_map(params![folder_path], |row| {
+            Ok((row.get(0)?, row.get(1)?, decode_page_offsets(&row.get::<_, String>(2)?)))
+        })?;
+        Ok(rows.filter_map(|r| r.ok()).collect())
+    }
+
+    /// Store the links and entities found in the text of an already indexed
+    /// document, replacing any it had
+    pub fn set_document_analysis(&self, pdf_id: i64, links: &[Link], entities: &[Entity]) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM links WHERE pdf_id = ?1", params![pdf_id])?;
+        tx.execute("DELETE FROM entities WHERE pdf_id = ?1", params![pdf_id])?;
+        write_links(&tx, pdf_id, links)?;
+        write_entities(&tx, pdf_id, entities)?;
+        tx.execute("UPDATE pdfs SET analyzed = 1 WHERE id = ?1", params![pdf_id])?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Store chunk embeddings computed by `model` for an already indexed
+    /// document, replacing any it had
+    pub fn insert_chunk_embeddings(&self, pdf_id: i64, model: &str, chunks: &[ChunkEmbedding]) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM chunk_embeddings WHERE pdf_id = ?1", params![pdf_id])?;
+        write_chunk_embeddings(&tx, pdf_id, model, chunks)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Number of documents embedded by `model`
+    pub fn count_embedded_documents(&self, model: &str) -> anyhow::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(DISTINCT pdf_id) FROM chunk_embeddings WHERE model = ?1",
+            params![model],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
     /// Get existing files in a folder with their metadata for incremental indexing
+    /// Attachments are left out, as they are indexed along with their PDF
     pub fn get_files_in_folder(&self, folder_path: &str) -> anyhow::Result<HashMap<String, (i64, i64)>> {
         let conn = self.conn.lock().unwrap();
 
         let mut stmt = conn.prepare(
-            "SELECT path, modified, size FROM pdfs WHERE folder_path = ?1"
+            "SELECT path, modified, size FROM pdfs WHERE folder_path = ?1 AND parent_path IS NULL"
         )?;
 
         let rows = stmt.query_map(params![folder_path], |row| {
@@ -243,88 +961,378 @@ impl Database {
         Ok(result)
     }
 
-    /// Remove a specific PDF by path
-    pub fn remove_pdf_by_path(&self, path: &str) -> anyhow::Result<()> {
+    /// SHA-256 of the file bytes recorded when the document was indexed
+    pub fn get_content_hash(&self, path: &str) -> anyhow::Result<Option<String>> {
         let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM pdfs WHERE path = ?1", params![path])?;
-        Ok(())
+        let hash = conn
+            .query_row(
+                "SELECT content_hash FROM pdfs WHERE path = ?1",
+                params![path],
+                |row| row.get(0),
+            )
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(e),
+            })?;
+        Ok(hash)
     }
 
-    pub fn search(&self, query: &str, filters: &SearchFilters) -> anyhow::Result<Vec<SearchResult>> {
+    /// Content hashes of all indexed documents
+    pub fn content_hashes(&self) -> anyhow::Result<HashSet<String>> {
         let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT DISTINCT content_hash FROM pdfs WHERE content_hash IS NOT NULL")?;
+        let hashes = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<SqliteResult<HashSet<String>>>()?;
+        Ok(hashes)
+    }
 
-        // Validate and optimize query
-        // Reference: "Introduction to Information Retrieval" Ch. 2 - Query Processing
-        let optimized_query = optimize_search_query(query);
-
-        // Build the search query with filters
-        // Use BM25 ranking for better relevance
-        // Reference: "Introduction to Information Retrieval" Ch. 6 - Scoring and Ranking
-        let mut sql = String::from(
-            "SELECT p.path, p.title, p.size, p.modified, p.pages,
-                    snippet(pdfs_fts, 2, '<mark>', '</mark>', '...', 64) as snippet
-             FROM pdfs p
-             INNER JOIN pdfs_fts ON p.id = pdfs_fts.rowid
-             WHERE pdfs_fts MATCH ?"
-        );
+    /// Point an indexed document at a new path, keeping its extracted text
+    pub fn move_pdf(
+        &self,
+        old_path: &str,
+        new_path: &str,
+        title: &str,
+        modified: i64,
+        folder_path: &str,
+    ) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE pdfs SET path = ?2, title = ?3, modified = ?4, folder_path = ?5 WHERE path = ?1",
+            params![old_path, new_path, title, modified, folder_path],
+        )?;
+        // Attachments keep their name below the new path
+        tx.execute(
+            "UPDATE pdfs SET path = ?2 || substr(path, length(?1) + 1), parent_path = ?2, modified = ?3,
+                             folder_path = ?4
+             WHERE parent_path = ?1",
+            params![old_path, new_path, modified, folder_path],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
 
-        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(optimized_query)];
+    /// Indexed folder a document was found in
+    pub fn get_folder_path(&self, path: &str) -> anyhow::Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let folder = conn
+            .query_row(
+                "SELECT folder_path FROM pdfs WHERE path = ?1",
+                params![path],
+                |row| row.get(0).map(Some),
+            )
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(e),
+            })?;
+        Ok(folder)
+    }
 
-        if let Some(min_size) = filters.min_size {
-            sql.push_str(" AND p.size >= ?");
-            params_vec.push(Box::new(min_size));
-        }
+    /// Path of the PDF a document is embedded in, None for regular files
+    pub fn get_parent_path(&self, path: &str) -> anyhow::Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let parent = conn
+            .query_row(
+                "SELECT parent_path FROM pdfs WHERE path = ?1",
+                params![path],
+                |row| row.get(0),
+            )
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(e),
+            })?;
+        Ok(parent)
+    }
 
-        if let Some(max_size) = filters.max_size {
-            sql.push_str(" AND p.size <= ?");
-            params_vec.push(Box::new(max_size));
+    /// Remove a specific PDF by path
+    pub fn remove_pdf_by_path(&self, path: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM pdfs WHERE path = ?1", params![path])?;
+        conn.execute("DELETE FROM index_errors WHERE path = ?1", params![path])?;
+        Ok(())
+    }
+
+    /// Record indexing failures, counting repeated failures of the same file
+    pub fn record_index_errors(&self, errors: &[IndexError]) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO index_errors (path, folder_path, phase, kind, message, timestamp, attempts)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1)
+                 ON CONFLICT(path) DO UPDATE SET
+                    folder_path = excluded.folder_path,
+                    phase = excluded.phase,
+                    kind = excluded.kind,
+                    message = excluded.message,
+                    timestamp = excluded.timestamp,
+                    attempts = index_errors.attempts + 1"
+            )?;
+            let mut dequeue = tx.prepare_cached("DELETE FROM index_job_queue WHERE path = ?1")?;
+            for error in errors {
+                stmt.execute(params![
+                    &error.path,
+                    &error.folder_path,
+                    &error.phase,
+                    &error.kind,
+                    &error.message,
+                    error.timestamp
+                ])?;
+                // The file was dealt with, even though it failed
+                dequeue.execute(params![&error.path])?;
+            }
         }
+        tx.commit()?;
 
-        if let Some(date_from) = &filters.date_from {
-            match parse_date_to_timestamp(date_from) {
-                Ok(timestamp) => {
-                    sql.push_str(" AND p.modified >= ?");
-                    params_vec.push(Box::new(timestamp));
-                }
-                Err(e) => {
-                    log::warn!("Invalid date_from format '{}': {}", date_from, e);
-                    // Return error instead of silently ignoring
-                    anyhow::bail!("Invalid 'from' date format. Please use YYYY-MM-DD format.");
-                }
+        Ok(())
+    }
+
+    /// Record a new indexing job for `folder_path` with the files it will
+    /// process, replacing any earlier job for the folder
+    pub fn start_index_job(&self, folder_path: &str, paths: &[String]) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let started_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM index_job_queue WHERE folder_path = ?1", params![folder_path])?;
+        tx.execute(
+            "INSERT OR REPLACE INTO index_jobs (folder_path, started_at, total_files) VALUES (?1, ?2, ?3)",
+            params![folder_path, started_at, paths.len() as i64],
+        )?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO index_job_queue (folder_path, path) VALUES (?1, ?2)"
+            )?;
+            for path in paths {
+                stmt.execute(params![folder_path, path])?;
             }
         }
+        tx.commit()?;
 
-        if let Some(date_to) = &filters.date_to {
-            match parse_date_to_timestamp(date_to) {
-                Ok(timestamp) => {
-                    sql.push_str(" AND p.modified <= ?");
-                    params_vec.push(Box::new(timestamp + 86400)); // Add 1 day to include entire day
-                }
-                Err(e) => {
-                    log::warn!("Invalid date_to format '{}': {}", date_to, e);
-                    // Return error instead of silently ignoring
-                    anyhow::bail!("Invalid 'to' date format. Please use YYYY-MM-DD format.");
-                }
+        Ok(())
+    }
+
+    /// Jobs that did not finish, e.g. because the app was closed mid-index
+    pub fn get_interrupted_jobs(&self) -> anyhow::Result<Vec<IndexJob>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT j.folder_path, j.started_at, j.total_files,
+                    (SELECT COUNT(*) FROM index_job_queue q WHERE q.folder_path = j.folder_path)
+             FROM index_jobs j
+             ORDER BY j.started_at"
+        )?;
+
+        let jobs = stmt.query_map([], |row| {
+            Ok(IndexJob {
+                folder_path: row.get(0)?,
+                started_at: row.get(1)?,
+                total_files: row.get(2)?,
+                remaining_files: row.get(3)?,
+            })
+        })?;
+
+        Ok(jobs.filter_map(|j| j.ok()).collect())
+    }
+
+    /// Files of a folder's job that are not committed to the index yet,
+    /// or `None` when the folder has no job
+    pub fn get_index_job_queue(&self, folder_path: &str) -> anyhow::Result<Option<Vec<String>>> {
+        let conn = self.conn.lock().unwrap();
+
+        let exists: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM index_jobs WHERE folder_path = ?1",
+            params![folder_path],
+            |row| row.get(0),
+        )?;
+        if exists == 0 {
+            return Ok(None);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT path FROM index_job_queue WHERE folder_path = ?1 ORDER BY path"
+        )?;
+        let paths = stmt.query_map(params![folder_path], |row| row.get(0))?;
+        Ok(Some(paths.filter_map(|p| p.ok()).collect()))
+    }
+
+    /// Files and archive members of a folder extracted by a version older
+    /// than `version`; attachments are left out as they follow their PDF
+    pub fn get_outdated_files(&self, folder_path: &str, version: i64) -> anyhow::Result<HashSet<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT path FROM pdfs WHERE folder_path = ?1 AND parent_path IS NULL AND extractor_version < ?2"
+        )?;
+        let paths = stmt
+            .query_map(params![folder_path, version], |row| row.get(0))?
+            .collect::<SqliteResult<HashSet<String>>>()?;
+        Ok(paths)
+    }
+
+    /// Modification time and size of the archives indexed in a folder, by path
+    pub fn get_archives_in_folder(&self, folder_path: &str) -> anyhow::Result<HashMap<String, (i64, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT path, modified, size FROM archives WHERE folder_path = ?1")?;
+        let archives = stmt
+            .query_map(params![folder_path], |row| {
+                Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?)))
+            })?
+            .collect::<SqliteResult<HashMap<_, _>>>()?;
+        Ok(archives)
+    }
+
+    /// Mark an archive as indexed and drop documents of members it no longer
+    /// contains. Called after its members were written.
+    pub fn record_archive(&self, archive: &ArchiveRecord) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let prefix = archive_member_prefix(&archive.path);
+            let members: HashSet<&str> = archive.members.iter().map(String::as_str).collect();
+            let mut stmt = tx.prepare(
+                "SELECT path FROM pdfs WHERE substr(path, 1, length(?1)) = ?1 AND parent_path IS NULL"
+            )?;
+            let stale: Vec<String> = stmt
+                .query_map(params![prefix], |row| row.get::<_, String>(0))?
+                .collect::<SqliteResult<Vec<_>>>()?
+                .into_iter()
+                .filter(|path| !members.contains(path.as_str()))
+                .collect();
+            for path in stale {
+                tx.execute("DELETE FROM pdfs WHERE path = ?1", params![path])?;
+                tx.execute("DELETE FROM index_errors WHERE path = ?1", params![path])?;
             }
+
+            tx.execute(
+                "INSERT INTO archives (path, folder_path, modified, size) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(path) DO UPDATE SET
+                    folder_path = excluded.folder_path,
+                    modified = excluded.modified,
+                    size = excluded.size",
+                params![archive.path, archive.folder_path, archive.modified, archive.size],
+            )?;
+            // The archive itself may have failed to open in an earlier run
+            tx.execute("DELETE FROM index_errors WHERE path = ?1", params![archive.path])?;
         }
+        tx.commit()?;
+        Ok(())
+    }
 
-        // Order by BM25 rank (best matches first) and limit results
-        sql.push_str(" ORDER BY bm25(pdfs_fts) LIMIT 100");
+    /// Forget an archive and every document indexed from it
+    pub fn remove_archive(&self, path: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let prefix = archive_member_prefix(path);
+        conn.execute("DELETE FROM pdfs WHERE substr(path, 1, length(?1)) = ?1", params![prefix])?;
+        conn.execute("DELETE FROM index_errors WHERE substr(path, 1, length(?1)) = ?1 OR path = ?2", params![prefix, path])?;
+        conn.execute("DELETE FROM archives WHERE path = ?1", params![path])?;
+        Ok(())
+    }
 
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+    pub fn finish_index_job(&self, folder_path: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM index_job_queue WHERE folder_path = ?1", params![folder_path])?;
+        conn.execute("DELETE FROM index_jobs WHERE folder_path = ?1", params![folder_path])?;
+        Ok(())
+    }
 
-        let mut stmt = conn.prepare(&sql)?;
-        let results = stmt.query_map(params_refs.as_slice(), |row| {
-            Ok(SearchResult {
+    /// Indexing failures, most recent first, optionally limited to one folder
+    pub fn get_index_errors(&self, folder_path: Option<&str>) -> anyhow::Result<Vec<IndexError>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT path, folder_path, phase, kind, message, timestamp, attempts FROM index_errors
+             WHERE ?1 IS NULL OR folder_path = ?1
+             ORDER BY timestamp DESC, path"
+        )?;
+
+        let errors = stmt.query_map(params![folder_path], |row| {
+            Ok(IndexError {
                 path: row.get(0)?,
-                title: row.get(1)?,
-                size: row.get(2)?,
-                modified: row.get(3)?,
-                pages: row.get(4)?,
-                snippet: row.get(5).ok(),
+                folder_path: row.get(1)?,
+                phase: row.get(2)?,
+                kind: row.get(3)?,
+                message: row.get(4)?,
+                timestamp: row.get(5)?,
+                attempts: row.get(6)?,
             })
         })?;
 
+        Ok(errors.filter_map(|e| e.ok()).collect())
+    }
+
+    /// Forget the recorded failure of a file, e.g. once it no longer exists
+    pub fn clear_index_error(&self, path: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM index_errors WHERE path = ?1", params![path])?;
+        Ok(())
+    }
+
+    /// Extraction outcome recorded for an indexed document
+    pub fn get_extraction_status(&self, path: &str) -> anyhow::Result<Option<ExtractionStatus>> {
+        let conn = self.conn.lock().unwrap();
+        let status = conn
+            .query_row(
+                "SELECT extraction_status FROM pdfs WHERE path = ?1",
+                params![path],
+                |row| row.get::<_, String>(0),
+            )
+            .map(|status| Some(ExtractionStatus::from_db(&status)))
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(e),
+            })?;
+        Ok(status)
+    }
+
+    pub fn search(&self, query: &str, filters: &SearchFilters) -> anyhow::Result<Vec<SearchResult>> {
+        let conn = self.conn.lock().unwrap();
+
+        // Validate and optimize query
+        // Reference: "Introduction to Information Retrieval" Ch. 2 - Query Processing
+        let optimized_query = optimize_search_query(query);
+
+        // Build the search query with filters
+        // Use BM25 ranking for better relevance
+        // Reference: "Introduction to Information Retrieval" Ch. 6 - Scoring and Ranking
+        // Matches are delimited with control characters rather than HTML so the
+        // frontend never has to inject text coming from a PDF as markup
+        // A query of only operators such as `has:comments` lists the matching
+        // documents, most recently modified first
+        if optimized_query.is_empty() {
+            let mut sql = String::from(
+                "SELECT p.path, p.title, p.size, p.modified, p.pages, NULL, p.ocr, p.parent_path, NULL, NULL,
+                        p.page_offsets, NULL, NULL, NULL, NULL, NULL, NULL, NULL
+                 FROM pdfs p
+                 WHERE 1 = 1"
+            );
+            let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+            append_filters(&mut sql, &mut params_vec, filters)?;
+            sql.push_str(" ORDER BY p.modified DESC LIMIT 100");
+
+            let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+            let mut stmt = conn.prepare(&sql)?;
+            let results = stmt
+                .query_map(params_refs.as_slice(), search_result_from_row)?
+                .collect::<SqliteResult<Vec<_>>>()?;
+            return Ok(results);
+        }
+
+        let mut condition = String::from("pdfs_fts MATCH ?");
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(match_expression(optimized_query, filters))];
+        append_filters(&mut condition, &mut params_vec, filters)?;
+
+        // Order by BM25 rank (best matches first) and limit results
+        let sql = format!("{} ORDER BY rank LIMIT 100", match_query(&condition));
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+
+        let mut stmt = conn.prepare(&sql)?;
+        let results = stmt.query_map(params_refs.as_slice(), search_result_from_row)?;
+
         let mut search_results = Vec::new();
         for result in results {
             if let Ok(r) = result {
@@ -332,12 +1340,321 @@ impl Database {
             }
         }
 
+        for result in search_results.iter_mut() {
+            if let Some(page) = result.page {
+                result.section = outline::section_path(&load_outline(&conn, &result.path)?, page as u32);
+            }
+        }
+
         Ok(search_results)
     }
 
+    /// Group indexed files that are byte-identical or have near-identical text
+    /// Exact groups come first; a near-duplicate group is only reported when it
+    /// spans more than one distinct file content
+    pub fn find_duplicates(&self) -> anyhow::Result<Vec<DuplicateGroup>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT path, size, modified, content_hash, simhash FROM pdfs
+             WHERE content_hash IS NOT NULL OR simhash IS NOT NULL"
+        )?;
+        let rows: Vec<(DuplicateFile, Option<String>, Option<i64>)> = stmt
+            .query_map([], |row| {
+                Ok((
+                    DuplicateFile {
+                        path: row.get(0)?,
+                        size: row.get(1)?,
+                        modified: row.get(2)?,
+                    },
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let mut groups = Vec::new();
+
+        let mut by_hash: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (idx, (_, hash, _)) in rows.iter().enumerate() {
+            if let Some(hash) = hash {
+                by_hash.entry(hash.as_str()).or_default().push(idx);
+            }
+        }
+        let mut exact: Vec<Vec<usize>> = by_hash.into_values().filter(|g| g.len() > 1).collect();
+        exact.sort();
+        for group in exact {
+            groups.push(DuplicateGroup::new(true, group.iter().map(|&i| rows[i].0.clone()).collect()));
+        }
+
+        let with_simhash: Vec<usize> = (0..rows.len()).filter(|&i| rows[i].2.is_some()).collect();
+        let simhashes: Vec<u64> = with_simhash.iter().map(|&i| rows[i].2.unwrap_or(0) as u64).collect();
+        for group in fingerprint::group_near_duplicates(&simhashes, fingerprint::NEAR_DUPLICATE_DISTANCE) {
+            let members: Vec<usize> = group.iter().map(|&g| with_simhash[g]).collect();
+            let distinct_contents: std::collections::HashSet<Option<&String>> =
+                members.iter().map(|&i| rows[i].1.as_ref()).collect();
+            if distinct_contents.len() > 1 {
+                groups.push(DuplicateGroup::new(false, members.iter().map(|&i| rows[i].0.clone()).collect()));
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Rank documents by the cosine similarity between `query_vector` and their
+    /// best matching chunk; the snippet is the text of that chunk. Only chunks
+    /// embedded by `model` are compared.
+    pub fn semantic_search(
+        &self,
+        query_vector: &[f32],
+        model: &str,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut sql = String::from(
+            "SELECT p.id, c.start_offset, c.end_offset, c.embedding
+             FROM chunk_embeddings c
+             INNER JOIN pdfs p ON p.id = c.pdf_id
+             WHERE c.model = ? AND c.dim = ?"
+        );
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> =
+            vec![Box::new(model.to_string()), Box::new(query_vector.len() as i64)];
+        append_filters(&mut sql, &mut params_vec, filters)?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+
+        // Brute-force scan: a desktop index holds at most a few million chunks
+        let mut best: HashMap<i64, (f32, i64, i64)> = HashMap::new();
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params_refs.as_slice())?;
+        while let Some(row) = rows.next()? {
+            let pdf_id: i64 = row.get(0)?;
+            let blob: Vec<u8> = row.get(3)?;
+            let score = semantic::cosine_similarity(query_vector, &semantic::blob_to_vector(&blob));
+
+            let entry = best.entry(pdf_id).or_insert((f32::MIN, 0, 0));
+            if score > entry.0 {
+                *entry = (score, row.get(1)?, row.get(2)?);
+            }
+        }
+
+        let mut ranked: Vec<(i64, (f32, i64, i64))> = best.into_iter().collect();
+        ranked.sort_by(|a, b| b.1 .0.partial_cmp(&a.1 .0).unwrap_or(std::cmp::Ordering::Equal));
+        ranked.truncate(limit);
+
+        let mut doc_stmt = conn.prepare(
+            "SELECT path, title, size, modified, pages, substr(content, ?2 + 1, ?3 - ?2), ocr, parent_path,
+                    page_offsets
+             FROM pdfs WHERE id = ?1"
+        )?;
+
+        let mut results = Vec::with_capacity(ranked.len());
+        for (pdf_id, (_, start, end)) in ranked {
+            let mut result = doc_stmt.query_row(params![pdf_id, start, end], |row| {
+                let chunk: String = row.get(5)?;
+                let page_offsets: String = row.get(8)?;
+                Ok(SearchResult {
+                    path: row.get(0)?,
+                    title: row.get(1)?,
+                    size: row.get(2)?,
+                    modified: row.get(3)?,
+                    pages: row.get(4)?,
+                    snippets: vec![highlight::excerpt(&chunk, highlight::DEFAULT_CONTEXT_CHARS * 2)],
+                    ocr: row.get(6)?,
+                    parent_path: row.get(7)?,
+                    page: page_for_offset(&decode_page_offsets(&page_offsets), start as usize),
+                    section: Vec::new(),
+                    table: None,
+                })
+            })?;
+            if let Some(page) = result.page {
+                result.section = outline::section_path(&load_outline(&conn, &result.path)?, page as u32);
+            }
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    /// Fuse BM25 and vector similarity rankings with reciprocal rank fusion
+    /// Keyword results are preferred for display since they carry highlights
+    pub fn hybrid_search(
+        &self,
+        query: &str,
+        query_vector: &[f32],
+        model: &str,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        let keyword_results = self.search(query, filters)?;
+        let semantic_results = self.semantic_search(query_vector, model, filters, limit)?;
+
+        let rankings = vec![
+            keyword_results.iter().map(|r| r.path.clone()).collect(),
+            semantic_results.iter().map(|r| r.path.clone()).collect(),
+        ];
+
+        let mut by_path: HashMap<String, SearchResult> = HashMap::new();
+        for result in semantic_results.into_iter().chain(keyword_results) {
+            by_path.insert(result.path.clone(), result);
+        }
+
+        Ok(semantic::reciprocal_rank_fusion(&rankings)
+            .into_iter()
+            .take(limit)
+            .filter_map(|(path, _)| by_path.remove(&path))
+            .collect())
+    }
+
+    /// Find documents similar to the one at `path`
+    /// Every term of the document is weighted by TF-IDF and the heaviest ones
+    /// are kept. Each other document scores the sum, over the kept terms it
+    /// contains, of the term's weight times its BM25 score for that term alone.
+    /// Reference: "Introduction to Information Retrieval" Ch. 9.1 - Relevance feedback
+    pub fn find_similar(&self, path: &str, limit: usize) -> anyhow::Result<Vec<SearchResult>> {
+        let conn = self.conn.lock().unwrap();
+
+        let content: String = match conn.query_row(
+            "SELECT content FROM pdfs WHERE path = ?1",
+            params![path],
+            |row| row.get(0),
+        ) {
+            Ok(content) => content,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                anyhow::bail!("Document is not in the index: {}", path)
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let total_docs: i64 = conn.query_row("SELECT COUNT(*) FROM pdfs", [], |row| row.get(0))?;
+        if total_docs < 2 {
+            return Ok(Vec::new());
+        }
+
+        // Only full-text rows that still belong to a document are counted
+        let mut df_stmt = conn.prepare(
+            "SELECT COUNT(*) FROM pdfs_fts
+             INNER JOIN pdfs p ON p.id = pdfs_fts.rowid
+             WHERE pdfs_fts MATCH ?1",
+        )?;
+        let mut weighted = Vec::new();
+        for (term, tf) in similarity::term_frequencies(&content) {
+            let doc_freq: i64 = df_stmt.query_row(params![similarity::quote_term(&term)], |row| row.get(0))?;
+            // Terms unique to this document cannot match anything else, and
+            // terms present everywhere do not discriminate
+            if doc_freq < 2 || doc_freq >= total_docs {
+                continue;
+            }
+            weighted.push((term, similarity::tf_idf(tf, doc_freq, total_docs)));
+        }
+
+        let terms = similarity::top_weighted_terms(weighted, similarity::MAX_QUERY_TERMS);
+        let query = match similarity::build_or_query(&terms) {
+            Some(query) => query,
+            None => return Ok(Vec::new()),
+        };
+
+        // bm25() is negative, lower meaning a better match
+        let mut term_stmt = conn.prepare(
+            "SELECT p.id, bm25(pdfs_fts)
+             FROM pdfs p
+             INNER JOIN pdfs_fts ON p.id = pdfs_fts.rowid
+             WHERE pdfs_fts MATCH ?1 AND p.path != ?2",
+        )?;
+        let mut scores: HashMap<i64, f64> = HashMap::new();
+        for (term, weight) in &terms {
+            let matches = term_stmt.query_map(params![similarity::quote_term(term), path], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?))
+            })?;
+            for row in matches {
+                let (id, rank) = row?;
+                *scores.entry(id).or_insert(0.0) += weight * -rank;
+            }
+        }
+
+        let mut stmt = conn.prepare(&match_query("pdfs_fts MATCH ?1 AND p.id = ?2"))?;
+        let mut results = Vec::new();
+        for id in similarity::rank_by_score(scores, limit) {
+            if let Ok(result) = stmt.query_row(params![query, id], search_result_from_row) {
+                results.push(result);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// List every match of `query` inside a single document, with surrounding
+    /// context and page numbers, paged by `offset` and `limit`
+    pub fn get_document_matches(
+        &self,
+        path: &str,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<DocumentMatches> {
+        let conn = self.conn.lock().unwrap();
+        let optimized_query = optimize_search_query(query);
+
+        // Every match is needed to count and page them, so the whole text is
+        // marked here; only the contexts of the requested page are returned
+        let row = conn.query_row(
+            "SELECT highlight(pdfs_fts, 2, char(2), char(3)), p.page_offsets
+             FROM pdfs p
+             INNER JOIN pdfs_fts ON p.id = pdfs_fts.rowid
+             WHERE pdfs_fts MATCH ?1 AND p.path = ?2",
+            params![optimized_query, path],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        );
+
+        let (marked_content, page_offsets) = match row {
+            Ok(row) => row,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                return Ok(DocumentMatches {
+                    path: path.to_string(),
+                    total: 0,
+                    offset,
+                    matches: Vec::new(),
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let (text, ranges) = highlight::parse_marked_text(&marked_content);
+        let page_offsets = decode_page_offsets(&page_offsets);
+        let total = ranges.len();
+        let page_ranges: Vec<_> = ranges.into_iter().skip(offset).take(limit).collect();
+        let outline = load_outline(&conn, path)?;
+
+        let matches = highlight::build_match_contexts(&text, &page_ranges, highlight::DEFAULT_CONTEXT_CHARS)
+            .into_iter()
+            .zip(page_ranges.iter())
+            .map(|(snippet, range)| {
+                let page = page_for_offset(&page_offsets, range.start);
+                DocumentMatch {
+                    snippet,
+                    page,
+                    section: page.map(|page| outline::section_path(&outline, page as u32)).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Ok(DocumentMatches {
+            path: path.to_string(),
+            total,
+            offset,
+            matches,
+        })
+    }
+
     pub fn clear(&self) -> anyhow::Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM pdfs", [])?;
+        conn.execute("DELETE FROM index_errors", [])?;
+        conn.execute("DELETE FROM index_job_queue", [])?;
+        conn.execute("DELETE FROM index_jobs", [])?;
+        conn.execute("DELETE FROM archives", [])?;
         Ok(())
     }
 
@@ -347,77 +1664,745 @@ impl Database {
         Ok(count)
     }
 
-    pub fn add_indexed_folder(&self, folder_path: &str) -> anyhow::Result<()> {
-        let conn = self.conn.lock().unwrap();
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
+    pub fn add_indexed_folder(&self, folder_path: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT INTO indexed_folders (path, last_indexed) VALUES (?1, ?2)
+             ON CONFLICT(path) DO UPDATE SET last_indexed = excluded.last_indexed",
+            params![folder_path, timestamp],
+        )?;
+        Ok(())
+    }
+
+    /// Save the indexing settings of an indexed folder. Fails for folders
+    /// that were never indexed, which would otherwise show up as indexed
+    /// without any documents.
+    pub fn set_folder_settings(&self, folder_path: &str, settings: &FolderSettings) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE indexed_folders SET
+                include_patterns = ?2,
+                exclude_patterns = ?3,
+                max_file_size = ?4,
+                min_file_size = ?5,
+                max_threads = ?6,
+                max_depth = ?7,
+                follow_symlinks = ?8,
+                enable_ocr = ?9,
+                reindex_interval_hours = ?10,
+                allowed_roots = ?11,
+                same_file_system = ?12,
+                index_archives = ?13,
+                index_other_formats = ?14,
+                ocr_language = ?15
+             WHERE path = ?1",
+            params![
+                folder_path,
+                encode_patterns(&settings.include_patterns),
+                encode_patterns(&settings.exclude_patterns),
+                settings.max_file_size.map(|v| v as i64),
+                settings.min_file_size.map(|v| v as i64),
+                settings.max_threads.map(|v| v as i64),
+                settings.max_depth.map(|v| v as i64),
+                settings.follow_symlinks,
+                settings.enable_ocr,
+                settings.reindex_interval_hours,
+                encode_patterns(&settings.allowed_roots),
+                settings.same_file_system,
+                settings.index_archives,
+                settings.index_other_formats,
+                settings.ocr_language,
+            ],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("{} is not an indexed folder", folder_path);
+        }
+        Ok(())
+    }
+
+    /// Indexing settings of a folder, all defaults if none were saved
+    pub fn get_folder_settings(&self, folder_path: &str) -> anyhow::Result<FolderSettings> {
+        let conn = self.conn.lock().unwrap();
+        let settings = conn
+            .query_row(
+                &format!("SELECT {} FROM indexed_folders WHERE path = ?1", FOLDER_SETTINGS_COLUMNS),
+                params![folder_path],
+                |row| folder_settings_from_row(row, 0),
+            )
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(FolderSettings::default()),
+                e => Err(e),
+            })?;
+        Ok(settings)
+    }
+
+    /// Folders with a re-index schedule whose interval has passed since they
+    /// were last indexed
+    pub fn get_folders_due_for_reindex(&self, now: i64) -> anyhow::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT path FROM indexed_folders
+             WHERE reindex_interval_hours > 0
+               AND last_indexed + reindex_interval_hours * 3600 <= ?1
+             ORDER BY last_indexed"
+        )?;
+        let folders = stmt
+            .query_map(params![now], |row| row.get(0))?
+            .collect::<SqliteResult<Vec<String>>>()?;
+        Ok(folders)
+    }
+
+    pub fn get_indexed_folders(&self) -> anyhow::Result<Vec<IndexedFolder>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT f.path, f.last_indexed, COUNT(p.id) as pdf_count, {}
+                 FROM indexed_folders f
+                 LEFT JOIN pdfs p ON p.folder_path = f.path
+                 GROUP BY f.path
+                 ORDER BY f.last_indexed DESC",
+                FOLDER_SETTINGS_COLUMNS
+            )
+        )?;
+
+        let folders = stmt.query_map([], |row| {
+            Ok(IndexedFolder {
+                path: row.get(0)?,
+                last_indexed: row.get(1)?,
+                pdf_count: row.get(2)?,
+                settings: folder_settings_from_row(row, 3)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for folder in folders {
+            if let Ok(f) = folder {
+                result.push(f);
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn remove_indexed_folder(&self, folder_path: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM pdfs WHERE folder_path = ?1", params![folder_path])?;
+        conn.execute("DELETE FROM index_errors WHERE folder_path = ?1", params![folder_path])?;
+        conn.execute("DELETE FROM index_job_queue WHERE folder_path = ?1", params![folder_path])?;
+        conn.execute("DELETE FROM index_jobs WHERE folder_path = ?1", params![folder_path])?;
+        conn.execute("DELETE FROM archives WHERE folder_path = ?1", params![folder_path])?;
+        conn.execute("DELETE FROM indexed_folders WHERE path = ?1", params![folder_path])?;
+        Ok(())
+    }
+
+    pub fn remove_pdfs_for_folder(&self, folder_path: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM pdfs WHERE folder_path = ?1", params![folder_path])?;
+        conn.execute("DELETE FROM index_errors WHERE folder_path = ?1", params![folder_path])?;
+        conn.execute("DELETE FROM archives WHERE folder_path = ?1", params![folder_path])?;
+        Ok(())
+    }
+
+    /// Comments and notes of a document, in page order
+    pub fn get_annotations(&self, path: &str) -> anyhow::Result<Vec<Annotation>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT a.page, a.kind, a.author, a.contents FROM annotations a
+             INNER JOIN pdfs p ON p.id = a.pdf_id
+             WHERE p.path = ?1
+             ORDER BY a.page, a.idx"
+        )?;
+        let annotations = stmt
+            .query_map(params![path], |row| {
+                Ok(Annotation {
+                    page: row.get(0)?,
+                    kind: row.get(1)?,
+                    author: row.get(2)?,
+                    contents: row.get(3)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(annotations)
+    }
+
+    /// The entities mentioned in most of the documents matching `query` and
+    /// `filters`, at most `per_kind` values of each kind. An empty query
+    /// counts all documents passing the filters.
+    pub fn entity_facets(&self, query: &str, filters: &SearchFilters, per_kind: usize) -> anyhow::Result<Vec<EntityFacet>> {
+        let conn = self.conn.lock().unwrap();
+
+        let optimized_query = optimize_search_query(query);
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        let mut matching = String::from("SELECT p.id FROM pdfs p");
+        if optimized_query.is_empty() {
+            matching.push_str(" WHERE 1 = 1");
+        } else {
+            matching.push_str(" INNER JOIN pdfs_fts ON p.id = pdfs_fts.rowid WHERE pdfs_fts MATCH ?");
+            params_vec.push(Box::new(match_expression(optimized_query, filters)));
+        }
+        append_filters(&mut matching, &mut params_vec, filters)?;
+
+        let sql = format!(
+            "SELECT kind, value, documents FROM (
+                 SELECT e.kind, e.value, COUNT(*) AS documents,
+                        ROW_NUMBER() OVER (PARTITION BY e.kind ORDER BY COUNT(*) DESC, e.value) AS rank
+                 FROM entities e
+                 WHERE e.pdf_id IN ({})
+                 GROUP BY e.kind, e.value
+             )
+             WHERE rank <= ?
+             ORDER BY kind, rank",
+            matching
+        );
+        params_vec.push(Box::new(per_kind as i64));
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = conn.prepare(&sql)?;
+        let facets = stmt
+            .query_map(params_refs.as_slice(), |row| {
+                let kind: String = row.get(0)?;
+                Ok((kind, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?
+            .into_iter()
+            .filter_map(|(kind, value, documents)| {
+                EntityKind::from_name(&kind).map(|kind| EntityFacet { kind, value, documents })
+            })
+            .collect();
+        Ok(facets)
+    }
+
+    /// Documents linking to `host` or one of its subdomains, with those
+    /// links, by path. A value with a path such as `example.com/wiki` lists
+    /// the links whose URL contains it instead.
+    pub fn documents_linking_to(&self, host: &str) -> anyhow::Result<Vec<LinkingDocument>> {
+        let conn = self.conn.lock().unwrap();
+        let mut sql = String::from(
+            "SELECT p.path, p.title, l.page, l.url FROM links l
+             INNER JOIN pdfs p ON p.id = l.pdf_id
+             WHERE "
+        );
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        push_link_condition(&mut sql, &mut params_vec, host)?;
+        sql.push_str(" ORDER BY p.path, l.idx");
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params_refs.as_slice())?;
+        let mut documents: Vec<LinkingDocument> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let path: String = row.get(0)?;
+            let link = Link { page: row.get(2)?, url: row.get(3)? };
+            match documents.last_mut() {
+                Some(document) if document.path == path => document.links.push(link),
+                _ => documents.push(LinkingDocument { path, title: row.get(1)?, links: vec![link] }),
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Tables of a document in the order they were found, which is the
+    /// order `SearchResult::table` counts them in
+    pub fn get_tables(&self, path: &str) -> anyhow::Result<Vec<Table>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT t.page, t.rows FROM document_tables t
+             INNER JOIN pdfs p ON p.id = t.pdf_id
+             WHERE p.path = ?1
+             ORDER BY t.idx"
+        )?;
+        let rows = stmt
+            .query_map(params![path], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(page, rows)| Ok(Table { page, rows: serde_json::from_str(&rows)? }))
+            .collect()
+    }
+
+    /// Check if a PDF path exists in the database
+    pub fn is_pdf_indexed(&self, path: &str) -> anyhow::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pdfs WHERE path = ?1",
+            params![path],
+            |row| row.get(0)
+        )?;
+        Ok(count > 0)
+    }
+}
+
+fn parse_date_to_timestamp(date_str: &str) -> anyhow::Result<i64> {
+    use chrono::NaiveDate;
+    let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")?;
+    let datetime = date.and_hms_opt(0, 0, 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid time components for date: {}", date_str))?;
+    Ok(datetime.and_utc().timestamp())
+}
+
+/// Append the size and date filters to a query over `pdfs p`
+fn append_filters(
+    sql: &mut String,
+    params: &mut Vec<Box<dyn rusqlite::ToSql>>,
+    filters: &SearchFilters,
+) -> anyhow::Result<()> {
+    if filters.has_comments {
+        sql.push_str(" AND p.annotations != ''");
+    }
+
+    if filters.has_form_fields {
+        sql.push_str(" AND p.form_fields != ''");
+    }
+
+    if filters.bookmarks_only {
+        sql.push_str(" AND p.outline != ''");
+    }
+
+    if filters.has_tables || filters.tables_only {
+        sql.push_str(" AND p.tables != ''");
+    }
+
+    for link in &filters.links {
+        sql.push_str(" AND p.id IN (SELECT l.pdf_id FROM links l WHERE ");
+        push_link_condition(sql, params, link)?;
+        sql.push(')');
+    }
+
+    for (kind, value) in &filters.mentions {
+        sql.push_str(" AND p.id IN (SELECT e.pdf_id FROM entities e WHERE e.kind = ? AND e.value = ?)");
+        params.push(Box::new(kind.as_str()));
+        params.push(Box::new(value.clone()));
+    }
+
+    if let Some(min_size) = filters.min_size {
+        sql.push_str(" AND p.size >= ?");
+        params.push(Box::new(min_size));
+    }
+
+    if let Some(max_size) = filters.max_size {
+        sql.push_str(" AND p.size <= ?");
+        params.push(Box::new(max_size));
+    }
+
+    if let Some(date_from) = &filters.date_from {
+        match parse_date_to_timestamp(date_from) {
+            Ok(timestamp) => {
+                sql.push_str(" AND p.modified >= ?");
+                params.push(Box::new(timestamp));
+            }
+            Err(e) => {
+                log::warn!("Invalid date_from format '{}': {}", date_from, e);
+                // Return error instead of silently ignoring
+                anyhow::bail!("Invalid 'from' date format. Please use YYYY-MM-DD format.");
+            }
+        }
+    }
+
+    if let Some(date_to) = &filters.date_to {
+        match parse_date_to_timestamp(date_to) {
+            Ok(timestamp) => {
+                sql.push_str(" AND p.modified <= ?");
+                params.push(Box::new(timestamp + 86400)); // Add 1 day to include entire day
+            }
+            Err(e) => {
+                log::warn!("Invalid date_to format '{}': {}", date_to, e);
+                // Return error instead of silently ignoring
+                anyhow::bail!("Invalid 'to' date format. Please use YYYY-MM-DD format.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Number of tokens FTS5 `snippet()` cuts around the best match of a column
+const SNIPPET_TOKENS: usize = 64;
+
+/// Full-text matches satisfying `condition`, as rows for `search_result_from_row`
+/// Each column is cut to a bounded fragment around its matches by
+/// `snippet()`, so a match never loads a whole document. The text and table
+/// fragments carry no ellipsis; instead their position in the column is
+/// selected, to place the match on a page or in a table. The rows can be
+/// ordered by `rank`.
+fn match_query(condition: &str) -> String {
+    format!(
+        "SELECT path, title, size, modified, pages, content_fragment, ocr, parent_path, annotations_fragment,
+                form_fields_fragment, page_offsets, outline_fragment, tables_fragment, content_at,
+                length(content), tables_at, length(tables),
+                (length(substr(tables, 1, tables_at - 1))
+                 - length(replace(substr(tables, 1, tables_at - 1), char(10) || char(10), ''))) / 2
+         FROM (SELECT p.path, p.title, p.size, p.modified, p.pages, p.ocr, p.parent_path, p.page_offsets,
+                      p.content, p.tables, bm25(pdfs_fts) AS rank,
+                      snippet(pdfs_fts, 2, char(2), char(3), '', {tokens}) AS content_fragment,
+                      snippet(pdfs_fts, 3, char(2), char(3), '...', {tokens}) AS annotations_fragment,
+                      snippet(pdfs_fts, 4, char(2), char(3), '...', {tokens}) AS form_fields_fragment,
+                      snippet(pdfs_fts, 5, char(2), char(3), '...', {tokens}) AS outline_fragment,
+                      snippet(pdfs_fts, 6, char(2), char(3), '', {tokens}) AS tables_fragment,
+                      instr(p.content, snippet(pdfs_fts, 2, '', '', '', {tokens})) AS content_at,
+                      instr(p.tables, snippet(pdfs_fts, 6, '', '', '', {tokens})) AS tables_at
+               FROM pdfs p
+               INNER JOIN pdfs_fts ON p.id = pdfs_fts.rowid
+               WHERE {condition})",
+        tokens = SNIPPET_TOKENS,
+        condition = condition,
+    )
+}
+
+/// Map a row of `match_query`, or one with NULL fragments, to a search
+/// result with plain-text snippets
+fn search_result_from_row(row: &rusqlite::Row) -> SqliteResult<SearchResult> {
+    // Char offset and length of the text the fragment in `column` was cut from
+    let fragment_at = |column: usize| -> SqliteResult<(usize, usize)> {
+        let at = row.get::<_, Option<i64>>(column)?.unwrap_or(1).max(1) as usize - 1;
+        let len = row.get::<_, Option<i64>>(column + 1)?.unwrap_or(0) as usize;
+        Ok((at, len))
+    };
+
+    // Snippets come from the text when it matches, else from the comments,
+    // form fields, bookmarks or table cells that did
+    let mut page = None;
+    let mut snippets = Vec::new();
+    for column in [5, 8, 9, 11, 12] {
+        let Some(marked) = row.get::<_, Option<String>>(column).ok().flatten() else {
+            continue;
+        };
+        let (mut text, mut matches) = highlight::parse_marked_text(&marked);
+        let Some(first) = matches.first().copied() else {
+            continue;
+        };
+        // The text and table fragments are marked as cut here, where their
+        // position is known
+        if column == 5 || column == 12 {
+            let (at, len) = fragment_at(if column == 5 { 13 } else { 15 })?;
+            if column == 5 {
+                let page_offsets: String = row.get(10)?;
+                page = page_for_offset(&decode_page_offsets(&page_offsets), at + first.start);
+            }
+            let cut_after = at + text.chars().count() < len;
+            (text, matches) = highlight::mark_cut_ends(&text, &matches, at > 0, cut_after);
+        }
+        snippets = highlight::build_snippets(
+            &text,
+            &matches,
+            highlight::DEFAULT_CONTEXT_CHARS,
+            highlight::DEFAULT_MAX_SNIPPETS,
+        );
+        break;
+    }
+
+    // Tables are separated by a blank line, so the first match's table is
+    // the number of blank lines before it, in and before the fragment
+    let table = match row.get::<_, Option<String>>(12)? {
+        Some(marked) => {
+            let (text, matches) = highlight::parse_marked_text(&marked);
+            match matches.first() {
+                Some(first) => {
+                    let before: String = text.chars().take(first.start).collect();
+                    let blank_lines_before = row.get::<_, Option<i64>>(17)?.unwrap_or(0) as usize;
+                    Some(blank_lines_before + before.matches("\n\n").count())
+                }
+                None => None,
+            }
+        }
+        None => None,
+    };
+
+    Ok(SearchResult {
+        path: row.get(0)?,
+        title: row.get(1)?,
+        size: row.get(2)?,
+        modified: row.get(3)?,
+        pages: row.get(4)?,
+        snippets,
+        ocr: row.get(6)?,
+        parent_path: row.get(7)?,
+        page,
+        section: Vec::new(),
+        table,
+    })
+}
+
+/// The FTS5 query for `optimized_query`, with a column filter restricting
+/// every term to the bookmark titles or the table cells when asked for
+fn match_expression(optimized_query: String, filters: &SearchFilters) -> String {
+    match (filters.bookmarks_only, filters.tables_only) {
+        (true, true) => format!("{{outline tables}} : ({})", optimized_query),
+        (true, false) => format!("outline : ({})", optimized_query),
+        (false, true) => format!("tables : ({})", optimized_query),
+        (false, false) => optimized_query,
+    }
+}
+
+/// Append the condition on `links l` matching the `link:` filter `value`:
+/// the host or one of its subdomains, or URLs containing a given path
+fn push_link_condition(sql: &mut String, params: &mut Vec<Box<dyn rusqlite::ToSql>>, value: &str) -> anyhow::Result<()> {
+    match LinkFilter::parse(value) {
+        Some(LinkFilter::Host(host)) => {
+            sql.push_str("(l.host = ? OR substr(l.host, -?) = ?)");
+            params.push(Box::new(host.clone()));
+            params.push(Box::new(host.chars().count() as i64 + 1));
+            params.push(Box::new(format!(".{}", host)));
+        }
+        Some(LinkFilter::Url(url)) => {
+            sql.push_str("instr(lower(l.url), ?) > 0");
+            params.push(Box::new(url));
+        }
+        None => anyhow::bail!("Invalid link filter: {}", value),
+    }
+    Ok(())
+}
+
+/// Bookmarks of the document at `path`, empty when it has none
+fn load_outline(conn: &Connection, path: &str) -> SqliteResult<Vec<OutlineEntry>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT o.level, o.title, o.page FROM outline o
+         INNER JOIN pdfs p ON p.id = o.pdf_id
+         WHERE p.path = ?1
+         ORDER BY o.idx"
+    )?;
+    let outline = stmt
+        .query_map(params![path], |row| {
+            Ok(OutlineEntry {
+                level: row.get(0)?,
+                title: row.get(1)?,
+                page: row.get(2)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    Ok(outline)
+}
+
+/// Insert or replace a document along with its chunk embeddings, OCR pages,
+/// annotations, bookmarks, tables, links and entities
+fn write_document(tx: &Transaction, doc: &PdfDocument, folder_path: &str) -> anyhow::Result<()> {
+    // INSERT OR REPLACE does not fire delete triggers, so remove the previous
+    // version first: the triggers drop its full-text entry, the rows hanging
+    // off it and its attachments
+    tx.execute("DELETE FROM pdfs WHERE path = ?1", params![&doc.path])?;
+
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO pdfs (path, title, content, size, modified, pages, folder_path, page_offsets,
+                              content_hash, simhash, ocr, extraction_status, parent_path,
+                              annotations, form_fields, outline, tables, analyzed, extractor_version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, 1, ?18)"
+    )?;
+    stmt.execute(params![
+        &doc.path,
+        &doc.title,
+        &doc.content,
+        doc.size,
+        doc.modified,
+        doc.pages,
+        folder_path,
+        encode_page_offsets(&doc.page_offsets),
+        &doc.content_hash,
+        doc.simhash,
+        doc.ocr,
+        doc.extraction_status.as_str(),
+        &doc.parent_path,
+        annotations_text(&doc.annotations),
+        form_fields_text(&doc.form_fields),
+        doc.outline.iter().map(|e| e.title.as_str()).collect::<Vec<_>>().join("\n"),
+        doc.tables.iter().map(tables::table_text).collect::<Vec<_>>().join("\n\n"),
+        doc.extractor_version
+    ])?;
+    let pdf_id = tx.last_insert_rowid();
+
+    // A clean extraction resolves any failure recorded for this file earlier
+    if !doc.extraction_status.is_failure() {
+        tx.execute("DELETE FROM index_errors WHERE path = ?1", params![&doc.path])?;
+    }
+
+    // Checkpoint any running job in the same transaction as the document
+    tx.execute("DELETE FROM index_job_queue WHERE path = ?1", params![&doc.path])?;
 
-        conn.execute(
-            "INSERT OR REPLACE INTO indexed_folders (path, last_indexed) VALUES (?1, ?2)",
-            params![folder_path, timestamp],
+    if let (false, Some(model)) = (doc.chunks.is_empty(), &doc.embedding_model) {
+        write_chunk_embeddings(tx, pdf_id, model, &doc.chunks)?;
+    }
+
+    if !doc.ocr_pages.is_empty() {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO ocr_pages (pdf_id, page, text, confidence) VALUES (?1, ?2, ?3, ?4)"
         )?;
-        Ok(())
+        for page in &doc.ocr_pages {
+            stmt.execute(params![pdf_id, page.page, &page.text, page.confidence as f64])?;
+        }
     }
 
-    pub fn get_indexed_folders(&self) -> anyhow::Result<Vec<IndexedFolder>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT f.path, f.last_indexed, COUNT(p.id) as pdf_count
-             FROM indexed_folders f
-             LEFT JOIN pdfs p ON p.folder_path = f.path
-             GROUP BY f.path
-             ORDER BY f.last_indexed DESC"
+    if !doc.annotations.is_empty() {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO annotations (pdf_id, idx, page, kind, author, contents)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
         )?;
+        for (idx, annotation) in doc.annotations.iter().enumerate() {
+            stmt.execute(params![
+                pdf_id,
+                idx as i64,
+                annotation.page,
+                &annotation.kind,
+                &annotation.author,
+                &annotation.contents
+            ])?;
+        }
+    }
 
-        let folders = stmt.query_map([], |row| {
-            Ok(IndexedFolder {
-                path: row.get(0)?,
-                last_indexed: row.get(1)?,
-                pdf_count: row.get(2)?,
-            })
-        })?;
+    if !doc.outline.is_empty() {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO outline (pdf_id, idx, level, title, page) VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+        for (idx, entry) in doc.outline.iter().enumerate() {
+            stmt.execute(params![pdf_id, idx as i64, entry.level, &entry.title, entry.page])?;
+        }
+    }
 
-        let mut result = Vec::new();
-        for folder in folders {
-            if let Ok(f) = folder {
-                result.push(f);
-            }
+    if !doc.tables.is_empty() {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO document_tables (pdf_id, idx, page, rows) VALUES (?1, ?2, ?3, ?4)"
+        )?;
+        for (idx, table) in doc.tables.iter().enumerate() {
+            stmt.execute(params![pdf_id, idx as i64, table.page, serde_json::to_string(&table.rows)?])?;
         }
-        Ok(result)
     }
 
-    pub fn remove_indexed_folder(&self, folder_path: &str) -> anyhow::Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM pdfs WHERE folder_path = ?1", params![folder_path])?;
-        conn.execute("DELETE FROM indexed_folders WHERE path = ?1", params![folder_path])?;
-        Ok(())
+    write_links(tx, pdf_id, &doc.links)?;
+    write_entities(tx, pdf_id, &doc.entities)?;
+
+    Ok(())
+}
+
+fn write_links(tx: &Transaction, pdf_id: i64, links: &[Link]) -> anyhow::Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT OR REPLACE INTO links (pdf_id, idx, page, url, host) VALUES (?1, ?2, ?3, ?4, ?5)"
+    )?;
+    for (idx, link) in links.iter().enumerate() {
+        stmt.execute(params![pdf_id, idx as i64, link.page, &link.url, links::host(&link.url)])?;
     }
+    Ok(())
+}
 
-    pub fn remove_pdfs_for_folder(&self, folder_path: &str) -> anyhow::Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM pdfs WHERE folder_path = ?1", params![folder_path])?;
-        Ok(())
+fn write_entities(tx: &Transaction, pdf_id: i64, entities: &[Entity]) -> anyhow::Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT OR REPLACE INTO entities (pdf_id, kind, value, count) VALUES (?1, ?2, ?3, ?4)"
+    )?;
+    for entity in entities {
+        stmt.execute(params![pdf_id, entity.kind.as_str(), &entity.value, entity.count])?;
     }
+    Ok(())
+}
 
-    /// Check if a PDF path exists in the database
-    pub fn is_pdf_indexed(&self, path: &str) -> anyhow::Result<bool> {
-        let conn = self.conn.lock().unwrap();
-        let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM pdfs WHERE path = ?1",
-            params![path],
-            |row| row.get(0)
-        )?;
-        Ok(count > 0)
+/// Searchable text of annotations, one per line, prefixed with the author
+/// so comments can be found by who wrote them
+fn annotations_text(annotations: &[Annotation]) -> String {
+    annotations
+        .iter()
+        .map(|a| match &a.author {
+            Some(author) => format!("{}: {}", author, a.contents),
+            None => a.contents.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Searchable text of form fields, one `name: value` pair per line
+fn form_fields_text(fields: &[FormField]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("{}: {}", f.name, f.value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn write_chunk_embeddings(tx: &Transaction, pdf_id: i64, model: &str, chunks: &[ChunkEmbedding]) -> anyhow::Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT OR REPLACE INTO chunk_embeddings (pdf_id, chunk_index, start_offset, end_offset, embedding, model, dim)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+    )?;
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        stmt.execute(params![
+            pdf_id,
+            index as i64,
+            chunk.start as i64,
+            chunk.end as i64,
+            semantic::vector_to_blob(&chunk.vector),
+            model,
+            chunk.vector.len() as i64
+        ])?;
     }
+
+    Ok(())
 }
 
-fn parse_date_to_timestamp(date_str: &str) -> anyhow::Result<i64> {
-    use chrono::NaiveDate;
-    let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")?;
-    let datetime = date.and_hms_opt(0, 0, 0)
-        .ok_or_else(|| anyhow::anyhow!("Invalid time components for date: {}", date_str))?;
-    Ok(datetime.and_utc().timestamp())
+fn encode_page_offsets(offsets: &[i64]) -> String {
+    offsets
+        .iter()
+        .map(|o| o.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Columns of `indexed_folders` read by `folder_settings_from_row`, in order
+const FOLDER_SETTINGS_COLUMNS: &str = "include_patterns, exclude_patterns, max_file_size, min_file_size, \
+     max_threads, max_depth, follow_symlinks, enable_ocr, reindex_interval_hours, allowed_roots, same_file_system, \
+     index_archives, index_other_formats, ocr_language";
+
+fn folder_settings_from_row(row: &rusqlite::Row, first: usize) -> SqliteResult<FolderSettings> {
+    Ok(FolderSettings {
+        include_patterns: decode_patterns(&row.get::<_, String>(first)?),
+        exclude_patterns: decode_patterns(&row.get::<_, String>(first + 1)?),
+        max_file_size: row.get::<_, Option<i64>>(first + 2)?.map(|v| v as u64),
+        min_file_size: row.get::<_, Option<i64>>(first + 3)?.map(|v| v as u64),
+        max_threads: row.get::<_, Option<i64>>(first + 4)?.map(|v| v as usize),
+        max_depth: row.get::<_, Option<i64>>(first + 5)?.map(|v| v as usize),
+        follow_symlinks: row.get(first + 6)?,
+        enable_ocr: row.get(first + 7)?,
+        reindex_interval_hours: row.get(first + 8)?,
+        allowed_roots: decode_patterns(&row.get::<_, String>(first + 9)?),
+        same_file_system: row.get(first + 10)?,
+        index_archives: row.get(first + 11)?,
+        index_other_formats: row.get(first + 12)?,
+        ocr_language: row.get(first + 13)?,
+    })
+}
+
+/// Start of the virtual paths of an archive's members
+fn archive_member_prefix(archive_path: &str) -> String {
+    format!("{}{}", archive_path, archive::MEMBER_SEPARATOR)
+}
+
+fn encode_patterns(patterns: &[String]) -> String {
+    patterns
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn decode_patterns(encoded: &str) -> Vec<String> {
+    encoded
+        .lines()
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn decode_page_offsets(encoded: &str) -> Vec<i64> {
+    encoded
+        .split(',')
+        .filter_map(|o| o.trim().parse().ok())
+        .collect()
+}
+
+/// Map a char offset in a document's content to its 1-based page number
+fn page_for_offset(page_offsets: &[i64], offset: usize) -> Option<i32> {
+    if page_offsets.is_empty() {
+        return None;
+    }
+    let page = page_offsets.partition_point(|&start| start <= offset as i64);
+    Some(page.max(1) as i32)
 }
 
 /// Optimize search query for better FTS5 performance
@@ -440,6 +2425,7 @@ fn optimize_search_query(query: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::entities;
     use std::fs;
 
     fn create_test_db() -> Database {
@@ -457,6 +2443,7 @@ mod tests {
             size: 1024,
             modified: 1000000,
             pages: Some(5),
+            ..Default::default()
         }
     }
 
@@ -502,6 +2489,8 @@ mod tests {
         assert_eq!(files.len(), 2);
         assert!(files.contains_key("/test/doc1.pdf"));
         assert!(files.contains_key("/test/doc2.pdf"));
+        assert_eq!(db.get_folder_path("/test/doc1.pdf").unwrap().as_deref(), Some("/test"));
+        assert_eq!(db.get_folder_path("/test/missing.pdf").unwrap(), None);
     }
 
     #[test]
@@ -516,6 +2505,7 @@ mod tests {
             size: 2048,
             modified: 1000000,
             pages: Some(10),
+            ..Default::default()
         };
 
         db.insert_pdf(&doc, "/test").unwrap();
@@ -525,6 +2515,7 @@ mod tests {
             max_size: None,
             date_from: None,
             date_to: None,
+            ..Default::default()
         };
 
         let results = db.search("machine", &filters).unwrap();
@@ -532,6 +2523,485 @@ mod tests {
         assert_eq!(results[0].title, "Machine Learning");
     }
 
+    #[test]
+    fn test_search_returns_plain_snippets_with_highlights() {
+        let db = create_test_db();
+
+        let mut doc = create_test_document("/test/xss.pdf");
+        doc.content = "Payload <img src=x onerror=alert(1)> near the keyword".to_string();
+        db.insert_pdf(&doc, "/test").unwrap();
+
+        let filters = SearchFilters {
+            min_size: None,
+            max_size: None,
+            date_from: None,
+            date_to: None,
+            ..Default::default()
+        };
+
+        let results = db.search("keyword", &filters).unwrap();
+        assert_eq!(results.len(), 1);
+
+        let snippet = &results[0].snippets[0];
+        assert_eq!(snippet.text, doc.content);
+        assert!(!snippet.text.contains("<mark>"));
+
+        let chars: Vec<char> = snippet.text.chars().collect();
+        let highlighted: Vec<String> = snippet.highlights
+            .iter()
+            .map(|r| chars[r.start..r.end].iter().collect())
+            .collect();
+        assert_eq!(highlighted, vec!["keyword"]);
+    }
+
+    #[test]
+    fn test_get_document_matches() {
+        let db = create_test_db();
+
+        let mut doc = create_test_document("/test/manual.pdf");
+        doc.content = "alpha beta gamma alpha delta alpha".to_string();
+        doc.page_offsets = vec![0, 11, 23];
+        db.insert_pdf(&doc, "/test").unwrap();
+
+        let all = db.get_document_matches("/test/manual.pdf", "alpha", 0, 50).unwrap();
+        assert_eq!(all.total, 3);
+        let pages: Vec<_> = all.matches.iter().map(|m| m.page).collect();
+        assert_eq!(pages, vec![Some(1), Some(2), Some(3)]);
+
+        let paged = db.get_document_matches("/test/manual.pdf", "alpha", 1, 1).unwrap();
+        assert_eq!(paged.total, 3);
+        assert_eq!(paged.matches.len(), 1);
+        assert_eq!(paged.matches[0].page, Some(2));
+
+        let none = db.get_document_matches("/test/manual.pdf", "omega", 0, 50).unwrap();
+        assert_eq!(none.total, 0);
+        assert!(none.matches.is_empty());
+    }
+
+    #[test]
+    fn test_page_for_offset() {
+        assert_eq!(page_for_offset(&[], 5), None);
+        assert_eq!(page_for_offset(&[0, 10, 10, 20], 0), Some(1));
+        assert_eq!(page_for_offset(&[0, 10, 10, 20], 15), Some(3));
+        assert_eq!(page_for_offset(&[0, 10, 10, 20], 25), Some(4));
+    }
+
+    #[test]
+    fn test_find_similar() {
+        let db = create_test_db();
+
+        let docs = [
+            ("/test/spec_v1.pdf", "grounding electrode conductor bonding jumper panelboard"),
+            ("/test/spec_v2.pdf", "grounding electrode conductor bonding jumper switchboard"),
+            ("/test/contract.pdf", "payment terms invoice net thirty days penalty"),
+            ("/test/invoice.pdf", "invoice payment received penalty waived"),
+        ];
+        for (path, content) in docs {
+            let mut doc = create_test_document(path);
+            doc.content = content.to_string();
+            db.insert_pdf(&doc, "/test").unwrap();
+        }
+
+        let similar = db.find_similar("/test/spec_v1.pdf", 10).unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].path, "/test/spec_v2.pdf");
+
+        let similar = db.find_similar("/test/contract.pdf", 10).unwrap();
+        assert_eq!(similar[0].path, "/test/invoice.pdf");
+
+        assert!(db.find_similar("/test/missing.pdf", 10).is_err());
+    }
+
+    #[test]
+    fn test_semantic_and_hybrid_search() {
+        let db = create_test_db();
+        let filters = SearchFilters {
+            min_size: None,
+            max_size: None,
+            date_from: None,
+            date_to: None,
+            ..Default::default()
+        };
+
+        let docs = [
+            ("/test/cars.pdf", "automobile maintenance schedule", vec![1.0, 0.0]),
+            ("/test/billing.pdf", "invoice due next month", vec![0.0, 1.0]),
+        ];
+        for (path, content, vector) in docs {
+            let mut doc = create_test_document(path);
+            doc.content = content.to_string();
+            doc.chunks = vec![ChunkEmbedding { start: 0, end: content.chars().count(), vector }];
+            doc.embedding_model = Some("model-a".to_string());
+            db.insert_pdf(&doc, "/test").unwrap();
+        }
+
+        let query_vector = vec![0.9f32, 0.1];
+        let results = db.semantic_search(&query_vector, "model-a", &filters, 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].path, "/test/cars.pdf");
+        assert_eq!(results[0].snippets[0].text, "automobile maintenance schedule");
+
+        // Billing is found by both rankings, cars only semantically
+        let results = db.hybrid_search("invoice", &query_vector, "model-a", &filters, 10).unwrap();
+        let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["/test/billing.pdf", "/test/cars.pdf"]);
+        assert!(!results[0].snippets[0].highlights.is_empty());
+
+        // Re-indexing a document replaces its chunks instead of duplicating them
+        let mut doc = create_test_document("/test/cars.pdf");
+        doc.content = "automobile".to_string();
+        doc.chunks = vec![ChunkEmbedding { start: 0, end: 10, vector: vec![1.0, 0.0] }];
+        doc.embedding_model = Some("model-a".to_string());
+        db.insert_pdf(&doc, "/test").unwrap();
+        assert_eq!(db.count_embedded_documents("model-a").unwrap(), 2);
+        assert!(db.get_documents_without_embeddings("/test", "model-a", 2).unwrap().is_empty());
+
+        // Chunks of another model are neither searched nor counted, and
+        // the documents are listed for embedding again
+        let query_vector = vec![0.5f32, 0.5, 0.5];
+        assert!(db.semantic_search(&query_vector, "model-b", &filters, 10).unwrap().is_empty());
+        assert_eq!(db.count_embedded_documents("model-b").unwrap(), 0);
+        let pending = db.get_documents_without_embeddings("/test", "model-b", 3).unwrap();
+        assert_eq!(pending.len(), 2);
+
+        let (pdf_id, _) = pending[0].clone();
+        let chunks = vec![ChunkEmbedding { start: 0, end: 10, vector: vec![1.0, 0.0, 0.0] }];
+        db.insert_chunk_embeddings(pdf_id, "model-b", &chunks).unwrap();
+        assert_eq!(db.semantic_search(&query_vector, "model-b", &filters, 10).unwrap().len(), 1);
+        assert_eq!(db.count_embedded_documents("model-a").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let db = create_test_db();
+
+        let files = [
+            ("/a/report.pdf", 500, "hash-1", 0b1111_0000i64),
+            ("/b/report copy.pdf", 500, "hash-1", 0b1111_0000),
+            ("/c/report-v2.pdf", 520, "hash-2", 0b1111_0001),
+            ("/d/unrelated.pdf", 100, "hash-3", -1),
+        ];
+        for (path, size, hash, simhash) in files {
+            let mut doc = create_test_document(path);
+            doc.size = size;
+            doc.content_hash = Some(hash.to_string());
+            doc.simhash = Some(simhash);
+            db.insert_pdf(&doc, "/").unwrap();
+        }
+
+        let groups = db.find_duplicates().unwrap();
+        assert_eq!(groups.len(), 2);
+
+        assert!(groups[0].exact);
+        assert_eq!(groups[0].files.len(), 2);
+        assert_eq!(groups[0].reclaimable_bytes, 500);
+
+        assert!(!groups[1].exact);
+        let near: Vec<&str> = groups[1].files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(near, vec!["/c/report-v2.pdf", "/a/report.pdf", "/b/report copy.pdf"]);
+    }
+
+    #[test]
+    fn test_ocr_documents_are_flagged() {
+        let db = create_test_db();
+
+        let mut doc = create_test_document("/test/scan.pdf");
+        doc.content = "scanned purchase order".to_string();
+        doc.ocr = true;
+        doc.ocr_pages = vec![OcrPage { page: 1, text: "scanned purchase order".to_string(), confidence: 87.5 }];
+        db.insert_pdf(&doc, "/test").unwrap();
+        // Re-indexing replaces the stored pages
+        db.insert_pdf(&doc, "/test").unwrap();
+
+        let filters = SearchFilters {
+            min_size: None,
+            max_size: None,
+            date_from: None,
+            date_to: None,
+            ..Default::default()
+        };
+        let results = db.search("purchase", &filters).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].ocr);
+
+        let conn = db.conn.lock().unwrap();
+        let pages: i64 = conn.query_row("SELECT COUNT(*) FROM ocr_pages", [], |row| row.get(0)).unwrap();
+        assert_eq!(pages, 1);
+    }
+
+    #[test]
+    fn test_search_annotations_and_form_fields() {
+        let db = create_test_db();
+
+        let mut reviewed = create_test_document("/test/reviewed.pdf");
+        reviewed.annotations = vec![Annotation {
+            page: 3,
+            kind: "Text".to_string(),
+            author: Some("Alice".to_string()),
+            contents: "Please verify the totals".to_string(),
+        }];
+        let mut form = create_test_document("/test/form.pdf");
+        form.form_fields = vec![FormField { name: "applicant.city".to_string(), value: "Rotterdam".to_string() }];
+        db.batch_insert_pdfs(&[reviewed, form, create_test_document("/test/plain.pdf")], "/test").unwrap();
+
+        // Comments are found by their text and by who wrote them
+        let results = db.search("verify", &SearchFilters::default()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "/test/reviewed.pdf");
+        assert!(results[0].snippets[0].text.contains("verify the totals"));
+        assert_eq!(db.search("alice", &SearchFilters::default()).unwrap().len(), 1);
+        assert_eq!(db.search("rotterdam", &SearchFilters::default()).unwrap()[0].path, "/test/form.pdf");
+
+        let annotations = db.get_annotations("/test/reviewed.pdf").unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].page, 3);
+
+        // Operators narrow the results, or list documents on their own
+        let mut filters = SearchFilters::default();
+        let rest = filters.take_query_operators("test has:comments").unwrap();
+        assert_eq!(rest, "test");
+        assert!(filters.has_comments && !filters.has_form_fields);
+        let results = db.search(&rest, &filters).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "/test/reviewed.pdf");
+
+        let mut filters = SearchFilters::default();
+        let rest = filters.take_query_operators("HAS:forms").unwrap();
+        assert_eq!(rest, "");
+        let results = db.search(&rest, &filters).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "/test/form.pdf");
+
+        assert!(SearchFilters::default().take_query_operators("has:nothing").is_err());
+    }
+
+    #[test]
+    fn test_search_results_carry_section_path() {
+        let db = create_test_db();
+
+        let mut manual = create_test_document("/test/manual.pdf");
+        manual.content = "Introduction to the device Wiring diagrams Grounding the chassis safely".to_string();
+        manual.page_offsets = vec![0, 27, 44];
+        manual.outline = vec![
+            OutlineEntry { level: 0, title: "Introduction".to_string(), page: Some(1) },
+            OutlineEntry { level: 0, title: "Chapter 4".to_string(), page: Some(2) },
+            OutlineEntry { level: 1, title: "Safety".to_string(), page: Some(2) },
+            OutlineEntry { level: 2, title: "4.2 Electrical bonding".to_string(), page: Some(3) },
+        ];
+        db.batch_insert_pdfs(&[manual, create_test_document("/test/plain.pdf")], "/test").unwrap();
+
+        let results = db.search("chassis", &SearchFilters::default()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].page, Some(3));
+        assert_eq!(results[0].section, vec!["Chapter 4", "Safety", "4.2 Electrical bonding"]);
+
+        let matches = db.get_document_matches("/test/manual.pdf", "wiring", 0, 10).unwrap();
+        assert_eq!(matches.matches[0].section, vec!["Chapter 4", "Safety"]);
+
+        // Bookmark titles are searchable, on their own with in:bookmarks
+        assert_eq!(db.search("bonding", &SearchFilters::default()).unwrap().len(), 1);
+        let mut filters = SearchFilters::default();
+        let rest = filters.take_query_operators("in:bookmarks chassis").unwrap();
+        assert!(filters.bookmarks_only);
+        assert!(db.search(&rest, &filters).unwrap().is_empty());
+        let results = db.search("bonding OR safety", &filters).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].snippets[0].text.contains("4.2 Electrical bonding"));
+        assert_eq!(results[0].snippets[0].highlights.len(), 2);
+    }
+
+    #[test]
+    fn test_search_snippets_are_cut_from_long_documents() {
+        let db = create_test_db();
+
+        let filler = "lorem ipsum dolor sit amet ".repeat(400);
+        let row = |cells: &[&str]| cells.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let mut doc = create_test_document("/test/long.pdf");
+        doc.content = format!("{}{}zeppelin landing{}", filler, filler, filler);
+        let second_page = filler.chars().count() as i64;
+        doc.page_offsets = vec![0, second_page, second_page * 2];
+        doc.tables = (0..30)
+            .map(|i| Table { page: 1, rows: vec![row(&["Item", &format!("value{}", i)])] })
+            .collect();
+        db.insert_pdf(&doc, "/test").unwrap();
+
+        let results = db.search("zeppelin", &SearchFilters::default()).unwrap();
+        assert_eq!(results[0].page, Some(3));
+        let snippet = &results[0].snippets[0];
+        assert!(snippet.text.chars().count() < 1000);
+        assert!(snippet.text.starts_with("...") && snippet.text.ends_with("..."));
+        let marked: String = snippet.text.chars()
+            .skip(snippet.highlights[0].start)
+            .take(snippet.highlights[0].end - snippet.highlights[0].start)
+            .collect();
+        assert_eq!(marked, "zeppelin");
+
+        let results = db.search("value27", &SearchFilters::default()).unwrap();
+        assert_eq!(results[0].table, Some(27));
+    }
+
+    #[test]
+    fn test_table_cells_are_searchable() {
+        let db = create_test_db();
+
+        let row = |cells: &[&str]| cells.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let mut sheet = create_test_document("/test/pricing.pdf");
+        sheet.content = "Model Weight Price Model X100 X200 12 kg 15 kg 1,200 1,450".to_string();
+        sheet.tables = vec![
+            Table { page: 1, rows: vec![row(&["Model", "Weight"]), row(&["X100", "12 kg"])] },
+            Table { page: 2, rows: vec![row(&["Model", "Price"]), row(&["X200", "1,450"])] },
+        ];
+        db.batch_insert_pdfs(&[sheet, create_test_document("/test/plain.pdf")], "/test").unwrap();
+
+        let results = db.search("X200", &SearchFilters::default()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].table, Some(1));
+        let tables = db.get_tables("/test/pricing.pdf").unwrap();
+        assert_eq!(tables[1].page, 2);
+        assert_eq!(tables[1].rows[1], vec!["X200", "1,450"]);
+
+        let mut filters = SearchFilters::default();
+        let rest = filters.take_query_operators("in:tables weight").unwrap();
+        assert!(filters.tables_only);
+        let results = db.search(&rest, &filters).unwrap();
+        assert_eq!(results[0].table, Some(0));
+        assert!(results[0].snippets[0].text.contains("Model | Weight"));
+
+        let mut filters = SearchFilters::default();
+        filters.take_query_operators("has:tables").unwrap();
+        assert_eq!(db.search("", &filters).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_link_filter_and_host_report() {
+        let db = create_test_db();
+
+        let link = |page: u32, url: &str| Link { page: Some(page), url: url.to_string() };
+        let mut handbook = create_test_document("/test/handbook.pdf");
+        handbook.links = vec![link(2, "https://wiki.old-example.com/display/ENG"), link(5, "mailto:it@old-example.com")];
+        let mut notes = create_test_document("/test/notes.pdf");
+        notes.links = vec![link(1, "http://www.Old-Example.com/start")];
+        let mut lookalike = create_test_document("/test/lookalike.pdf");
+        lookalike.links = vec![link(1, "https://notold-example.com/")];
+        db.batch_insert_pdfs(&[handbook, notes, lookalike], "/test").unwrap();
+
+        // Subdomains count, hosts that merely end the same way do not
+        let mut filters = SearchFilters::default();
+        let rest = filters.take_query_operators("link:old-example.com test").unwrap();
+        let mut paths: Vec<String> = db.search(&rest, &filters).unwrap().into_iter().map(|r| r.path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/test/handbook.pdf", "/test/notes.pdf"]);
+
+        let mut filters = SearchFilters::default();
+        filters.take_query_operators("link:https://wiki.old-example.com/display").unwrap();
+        assert_eq!(db.search("", &filters).unwrap().len(), 1);
+        assert!(SearchFilters::default().take_query_operators("link:").is_err());
+
+        let report = db.documents_linking_to("wiki.old-example.com").unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].path, "/test/handbook.pdf");
+        assert_eq!(report[0].links, vec![link(2, "https://wiki.old-example.com/display/ENG")]);
+        assert_eq!(db.documents_linking_to("old-example.com").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_entity_filter_and_facets() {
+        let db = create_test_db();
+
+        let mut invoice = create_test_document("/test/invoice.pdf");
+        invoice.content = "Invoice 2024-03-05 for EUR 1,200.00, contact billing@example.com".to_string();
+        let mut reminder = create_test_document("/test/reminder.pdf");
+        reminder.content = "Reminder: 5 March 2024 is the due date: write to billing@example.com test".to_string();
+        let mut paper = create_test_document("/test/paper.pdf");
+        paper.content = "See doi:10.1000/182 test".to_string();
+        for doc in [&mut invoice, &mut reminder, &mut paper] {
+            doc.entities = entities::extract_entities(&doc.content);
+        }
+        db.batch_insert_pdfs(&[invoice, reminder, paper], "/test").unwrap();
+
+        // Operator values are normalized like the text they are compared with
+        let mut filters = SearchFilters::default();
+        let rest = filters.take_query_operators("date:2024-03-05 Email:Billing@Example.com").unwrap();
+        assert_eq!(rest, "");
+        let mut paths: Vec<String> = db.search("", &filters).unwrap().into_iter().map(|r| r.path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/test/invoice.pdf", "/test/reminder.pdf"]);
+
+        // Without a valid value, entity names are searched as words
+        let mut filters = SearchFilters::default();
+        let rest = filters.take_query_operators("Date: 2024 iban:DE00 total amount:").unwrap();
+        assert_eq!(rest, "\"Date:\" 2024 \"iban:DE00\" total \"amount:\"");
+        assert!(filters.mentions.is_empty());
+        assert!(db.search(&rest, &filters).is_ok());
+        let rest = filters.take_query_operators("due date:").unwrap();
+        assert_eq!(db.search(&rest, &filters).unwrap()[0].path, "/test/reminder.pdf");
+
+        let mut filters = SearchFilters::default();
+        filters.take_query_operators("amount:€1.200").unwrap();
+        assert_eq!(db.search("", &filters).unwrap()[0].path, "/test/invoice.pdf");
+
+        let facets = db.entity_facets("test", &SearchFilters::default(), 5).unwrap();
+        let email = facets.iter().find(|f| f.kind == EntityKind::Email).unwrap();
+        assert_eq!((email.value.as_str(), email.documents), ("billing@example.com", 2));
+        assert!(facets.iter().any(|f| f.kind == EntityKind::Doi && f.value == "10.1000/182"));
+
+        let mut filters = SearchFilters::default();
+        filters.take_query_operators("doi:10.1000/182").unwrap();
+        let facets = db.entity_facets("", &filters, 5).unwrap();
+        assert!(facets.iter().all(|f| f.kind == EntityKind::Doi));
+        assert_eq!(db.entity_facets("", &SearchFilters::default(), 1).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_documents_indexed_before_analysis_are_backfilled() {
+        let db = create_test_db();
+        let mut doc = create_test_document("/test/old.pdf");
+        doc.content = "Write to billing@example.com or see https://example.com/help".to_string();
+        db.insert_pdf(&doc, "/test").unwrap();
+        assert!(db.get_documents_without_analysis("/test").unwrap().is_empty());
+
+        // A row written before links and entities existed
+        db.conn.lock().unwrap().execute("UPDATE pdfs SET analyzed = 0", []).unwrap();
+        let pending = db.get_documents_without_analysis("/test").unwrap();
+        assert_eq!(pending.len(), 1);
+        let (pdf_id, content, page_offsets) = &pending[0];
+        let links = links::document_links(Vec::new(), content, page_offsets);
+        db.set_document_analysis(*pdf_id, &links, &entities::extract_entities(content)).unwrap();
+
+        assert!(db.get_documents_without_analysis("/test").unwrap().is_empty());
+        assert_eq!(db.documents_linking_to("example.com").unwrap()[0].path, "/test/old.pdf");
+        let facets = db.entity_facets("", &SearchFilters::default(), 5).unwrap();
+        assert!(facets.iter().any(|f| f.kind == EntityKind::Email && f.value == "billing@example.com"));
+    }
+
+    #[test]
+    fn test_index_errors_count_attempts_until_resolved() {
+        let db = create_test_db();
+
+        let mut doc = create_test_document("/test/broken.pdf");
+        doc.content = String::new();
+        doc.extraction_status = ExtractionStatus::Panicked;
+        let error = IndexError::new(&doc.path, "/test", "extract", "panicked", "bad xref");
+
+        for _ in 0..2 {
+            db.insert_pdf(&doc, "/test").unwrap();
+            db.record_index_errors(std::slice::from_ref(&error)).unwrap();
+        }
+
+        let errors = db.get_index_errors(Some("/test")).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].attempts, 2);
+        assert_eq!(errors[0].kind, "panicked");
+        assert!(db.get_index_errors(Some("/other")).unwrap().is_empty());
+        assert_eq!(db.get_extraction_status(&doc.path).unwrap(), Some(ExtractionStatus::Panicked));
+
+        // A clean extraction resolves the error
+        doc.extraction_status = ExtractionStatus::Empty;
+        db.insert_pdf(&doc, "/test").unwrap();
+        assert!(db.get_index_errors(None).unwrap().is_empty());
+        assert_eq!(db.get_extraction_status(&doc.path).unwrap(), Some(ExtractionStatus::Empty));
+    }
+
     #[test]
     fn test_remove_pdf_by_path() {
         let db = create_test_db();
@@ -544,6 +3014,53 @@ mod tests {
         assert_eq!(db.get_count().unwrap(), 0);
     }
 
+    #[test]
+    fn test_moved_document_loses_its_old_terms() {
+        let db_path = std::env::temp_dir().join(format!("test_pdf_db_{}.db", uuid::Uuid::new_v4()));
+        let db = Database::new(db_path.clone()).unwrap();
+        // Triggers created by earlier versions are replaced on open
+        db.conn.lock().unwrap().execute_batch(
+            "DROP TRIGGER pdfs_au;
+             CREATE TRIGGER pdfs_au AFTER UPDATE ON pdfs BEGIN
+                 DELETE FROM pdfs_fts WHERE rowid = old.id;
+                 INSERT INTO pdfs_fts(rowid, path, title, content, annotations, form_fields, outline, tables)
+                 VALUES (new.id, new.path, new.title, new.content, new.annotations, new.form_fields, new.outline,
+                         new.tables);
+             END;",
+        ).unwrap();
+        let db = Database::new(db_path).unwrap();
+
+        let mut doc = create_test_document("/test/quarterly.pdf");
+        doc.title = "Quarterly".to_string();
+        db.insert_pdf(&doc, "/test").unwrap();
+        db.move_pdf("/test/quarterly.pdf", "/test/annual.pdf", "Annual", 2000000, "/test").unwrap();
+
+        let filters = SearchFilters::default();
+        assert!(db.search("quarterly", &filters).unwrap().is_empty());
+        assert_eq!(db.search("annual", &filters).unwrap().len(), 1);
+        assert_eq!(db.search("searching", &filters).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_reindexed_document_loses_its_old_terms() {
+        let db = create_test_db();
+        let mut doc = create_test_document("/test/report.pdf");
+        doc.content = "Draft figures for the budget".to_string();
+        db.insert_pdf(&doc, "/test").unwrap();
+
+        doc.content = "Final figures for the budget".to_string();
+        doc.modified = 2000000;
+        db.insert_pdf(&doc, "/test").unwrap();
+
+        let filters = SearchFilters::default();
+        assert!(db.search("draft", &filters).unwrap().is_empty());
+        assert_eq!(db.search("final", &filters).unwrap().len(), 1);
+        assert_eq!(db.get_count().unwrap(), 1);
+        db.conn.lock().unwrap()
+            .execute("INSERT INTO pdfs_fts(pdfs_fts) VALUES ('integrity-check')", [])
+            .unwrap();
+    }
+
     #[test]
     fn test_indexed_folders() {
         let db = create_test_db();
@@ -555,6 +3072,59 @@ mod tests {
         assert_eq!(folders.len(), 2);
     }
 
+    #[test]
+    fn test_folder_settings_survive_reindex() {
+        let db = create_test_db();
+        let settings = FolderSettings {
+            max_file_size: Some(10 * 1024 * 1024),
+            max_depth: Some(2),
+            enable_ocr: Some(false),
+            reindex_interval_hours: Some(24),
+            allowed_roots: vec!["/shared/projects".to_string()],
+            same_file_system: Some(true),
+            index_archives: Some(false),
+            index_other_formats: Some(true),
+            ocr_language: Some("eng+fra".to_string()),
+            include_patterns: vec!["invoices/**".to_string()],
+            exclude_patterns: vec!["**/node_modules/**".to_string(), "*_draft.pdf".to_string()],
+            ..Default::default()
+        };
+
+        db.add_indexed_folder("/test/folder").unwrap();
+        db.set_folder_settings("/test/folder", &settings).unwrap();
+        db.add_indexed_folder("/test/folder").unwrap();
+
+        assert_eq!(db.get_folder_settings("/test/folder").unwrap(), settings);
+        let folders = db.get_indexed_folders().unwrap();
+        assert_eq!(folders[0].settings, settings);
+        assert!(folders[0].last_indexed > 0);
+
+        assert_eq!(db.get_folder_settings("/test/other").unwrap(), FolderSettings::default());
+        // Settings of a folder that was never indexed are not saved
+        assert!(db.set_folder_settings("/test/other", &settings).is_err());
+        assert_eq!(db.get_indexed_folders().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_folders_due_for_reindex() {
+        let db = create_test_db();
+        let daily = FolderSettings { reindex_interval_hours: Some(24), ..Default::default() };
+
+        db.add_indexed_folder("/test/scheduled").unwrap();
+        db.set_folder_settings("/test/scheduled", &daily).unwrap();
+        db.add_indexed_folder("/test/manual").unwrap();
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        assert!(db.get_folders_due_for_reindex(now).unwrap().is_empty());
+        assert_eq!(
+            db.get_folders_due_for_reindex(now + 25 * 3600).unwrap(),
+            vec!["/test/scheduled".to_string()]
+        );
+    }
+
     #[test]
     fn test_optimize_search_query() {
         assert_eq!(optimize_search_query(""), "");
@@ -580,6 +3150,7 @@ mod tests {
             size: 1000,
             modified: 1000000,
             pages: Some(1),
+            ..Default::default()
         };
 
         let doc2 = PdfDocument {
@@ -590,6 +3161,7 @@ mod tests {
             size: 10000,
             modified: 2000000,
             pages: Some(10),
+            ..Default::default()
         };
 
         db.insert_pdf(&doc1, "/test").unwrap();
@@ -601,6 +3173,7 @@ mod tests {
             max_size: None,
             date_from: None,
             date_to: None,
+            ..Default::default()
         };
 
         let results = db.search("document", &filters).unwrap();
@@ -0,0 +1,532 @@
+use crate::links;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Documents keep at most this many distinct entities
+const MAX_ENTITIES: usize = 1000;
+
+/// Phone numbers and IBANs are spread over at most this many words
+const MAX_SPAN_TOKENS: usize = 9;
+
+const MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+
+/// Currency symbols and the ISO 4217 code they stand for. Longer symbols
+/// come first, so "US$" is not read as "$".
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[
+    ("US$", "USD"),
+    ("C$", "CAD"),
+    ("A$", "AUD"),
+    ("$", "USD"),
+    ("€", "EUR"),
+    ("£", "GBP"),
+    ("¥", "JPY"),
+    ("₹", "INR"),
+];
+
+const CURRENCY_CODES: &[&str] = &[
+    "USD", "EUR", "GBP", "JPY", "CHF", "CAD", "AUD", "NZD", "CNY", "INR", "SEK", "NOK", "DKK", "PLN", "CZK",
+];
+
+/// What kind of thing an entity is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Email,
+    Phone,
+    Date,
+    Amount,
+    Iban,
+    Isbn,
+    Doi,
+}
+
+impl EntityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Email => "email",
+            EntityKind::Phone => "phone",
+            EntityKind::Date => "date",
+            EntityKind::Amount => "amount",
+            EntityKind::Iban => "iban",
+            EntityKind::Isbn => "isbn",
+            EntityKind::Doi => "doi",
+        }
+    }
+
+    /// Kind named `name`, as in the `doi:` search operator
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "email" => Some(EntityKind::Email),
+            "phone" => Some(EntityKind::Phone),
+            "date" => Some(EntityKind::Date),
+            "amount" => Some(EntityKind::Amount),
+            "iban" => Some(EntityKind::Iban),
+            "isbn" => Some(EntityKind::Isbn),
+            "doi" => Some(EntityKind::Doi),
+            _ => None,
+        }
+    }
+
+    /// `value` written the way entities of this kind are stored, so that
+    /// `isbn:0-306-40615-2` finds documents citing ISBN 978-0-306-40615-7
+    pub fn normalize(&self, value: &str) -> Option<String> {
+        // ISBN-10s are only recognized after a label
+        let text = match self {
+            EntityKind::Isbn => format!("ISBN {}", value),
+            _ => value.to_string(),
+        };
+        extract_entities(&text)
+            .into_iter()
+            .find(|entity| entity.kind == *self)
+            .map(|entity| entity.value)
+    }
+}
+
+/// An entity mentioned in a document, with how often
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entity {
+    pub kind: EntityKind,
+    /// Normalized value: lowercase emails and DOIs, phone numbers as digits
+    /// with an optional leading `+`, ISO 8601 dates, amounts as
+    /// `EUR 1200.50`, compact IBANs and ISBN-13s
+    pub value: String,
+    pub count: u32,
+}
+
+/// Find the emails, phone numbers, dates, currency amounts, IBANs, ISBNs
+/// and DOIs mentioned in `text`
+pub fn extract_entities(text: &str) -> Vec<Entity> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let mut counts: BTreeMap<(EntityKind, String), u32> = BTreeMap::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        // Patterns spanning several words are tried first, so that the
+        // groups of an IBAN are not taken for a phone number
+        let found = match_iban(&tokens[i..])
+            .or_else(|| match_isbn(&tokens[i..]))
+            .or_else(|| match_date(&tokens[i..]))
+            .or_else(|| match_amount(&tokens[i..]))
+            .or_else(|| match_phone(&tokens[i..]))
+            .or_else(|| match_email(tokens[i]))
+            .or_else(|| match_doi(tokens[i]));
+        match found {
+            Some((kind, value, used)) => {
+                if counts.len() < MAX_ENTITIES || counts.contains_key(&(kind, value.clone())) {
+                    *counts.entry((kind, value)).or_default() += 1;
+                }
+                i += used;
+            }
+            None => i += 1,
+        }
+    }
+    counts
+        .into_iter()
+        .map(|((kind, value), count)| Entity { kind, value, count })
+        .collect()
+}
+
+type Match = (EntityKind, String, usize);
+
+/// A word without the quotes, brackets and punctuation around it
+fn trim_token(token: &str) -> &str {
+    token
+        .trim_start_matches(['"', '\'', '(', '[', '{', '<', '“', '‘'])
+        .trim_end_matches(['"', '\'', ')', ']', '}', '>', '.', ',', ';', ':', '!', '?', '”', '’'])
+}
+
+fn match_email(token: &str) -> Option<Match> {
+    let token = trim_token(token);
+    let token = token.strip_prefix("mailto:").unwrap_or(token);
+    let (local, domain) = token.split_once('@')?;
+    let valid_local = !local.is_empty()
+        && local.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-'));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let valid_domain = labels.len() >= 2
+        && labels.iter().all(|label| !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-'))
+        && labels.last().is_some_and(|tld| tld.len() >= 2 && tld.chars().all(char::is_alphabetic));
+    (valid_local && valid_domain).then(|| (EntityKind::Email, token.to_lowercase(), 1))
+}
+
+/// DOIs such as `10.1000/182`, also inside `doi:` prefixes and doi.org links
+fn match_doi(token: &str) -> Option<Match> {
+    let start = token
+        .match_indices("10.")
+        .map(|(i, _)| i)
+        .find(|&i| token[..i].chars().next_back().is_none_or(|c| !c.is_alphanumeric() && c != '.'))?;
+    let doi = links::trim_url(trim_token(&token[start..]));
+    let (prefix, suffix) = doi.split_once('/')?;
+    let registrant = &prefix[3..];
+    let valid = (4..=9).contains(&registrant.len())
+        && registrant.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && !suffix.is_empty();
+    valid.then(|| (EntityKind::Doi, doi.to_lowercase(), 1))
+}
+
+/// IBANs, written in one piece or in groups of four: two letters, two check
+/// digits and up to 30 letters and digits, verified with the mod-97 check
+fn match_iban(tokens: &[&str]) -> Option<Match> {
+    let first = trim_token(tokens[0]);
+    let mut chars = first.chars();
+    let starts_like_iban = chars.by_ref().take(2).all(|c| c.is_ascii_uppercase())
+        && chars.take(2).all(|c| c.is_ascii_digit());
+    if !starts_like_iban || first.len() < 4 {
+        return None;
+    }
+
+    // Compact forms after each word, longest first
+    let mut compact = String::new();
+    let mut candidates = Vec::new();
+    for (used, token) in tokens.iter().take(MAX_SPAN_TOKENS).enumerate() {
+        let group = trim_token(token);
+        let is_group = !group.is_empty() && group.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+        if !is_group || (used > 0 && group.len() > 4) || compact.len() + group.len() > 34 {
+            break;
+        }
+        compact.push_str(group);
+        candidates.push((compact.clone(), used + 1));
+    }
+    candidates
+        .into_iter()
+        .rev()
+        .find(|(iban, _)| iban.len() >= 15 && iban_checksum_ok(iban))
+        .map(|(iban, used)| (EntityKind::Iban, iban, used))
+}
+
+fn iban_checksum_ok(iban: &str) -> bool {
+    let mut remainder = 0u32;
+    for c in iban[4..].chars().chain(iban[..4].chars()) {
+        let Some(value) = c.to_digit(36) else {
+            return false;
+        };
+        remainder = if value >= 10 { (remainder * 100 + value) % 97 } else { (remainder * 10 + value) % 97 };
+    }
+    remainder == 1
+}
+
+/// ISBNs after an `ISBN` label, in either form, and unlabeled ISBN-13s.
+/// ISBN-10s are stored in their ISBN-13 form.
+fn match_isbn(tokens: &[&str]) -> Option<Match> {
+    let first = trim_token(tokens[0]);
+    if let Some(label) = first.get(..4).filter(|label| label.eq_ignore_ascii_case("isbn")) {
+        // "ISBN", "ISBN:", "ISBN-13:" or the number glued to the label
+        let rest = first[label.len()..].trim_start_matches(['-', ':']);
+        let rest = match rest.strip_prefix("13").or_else(|| rest.strip_prefix("10")) {
+            Some(after) if after.is_empty() || after.starts_with(':') => after,
+            _ => rest,
+        };
+        let rest = rest.trim_start_matches(':');
+
+        let mut digits = String::new();
+        let mut used = 1;
+        if !rest.is_empty() {
+            digits.push_str(rest);
+        } else if let Some(next) = tokens.get(1) {
+            digits.push_str(trim_token(next));
+            used = 2;
+        }
+        let digits: String = digits.chars().filter(|&c| c != '-').collect();
+        return isbn13(&digits).map(|isbn| (EntityKind::Isbn, isbn, used));
+    }
+
+    let digits: String = first.chars().filter(|&c| c != '-').collect();
+    let is_isbn13 = digits.len() == 13
+        && (digits.starts_with("978") || digits.starts_with("979"))
+        && first.chars().all(|c| c.is_ascii_digit() || c == '-');
+    if !is_isbn13 {
+        return None;
+    }
+    isbn13(&digits).map(|isbn| (EntityKind::Isbn, isbn, 1))
+}
+
+/// The ISBN-13 of a valid ISBN-10 or ISBN-13
+fn isbn13(digits: &str) -> Option<String> {
+    let values: Vec<u32> = digits
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            'X' | 'x' if i == 9 && digits.len() == 10 => Some(10),
+            c => c.to_digit(10),
+        })
+        .collect::<Option<_>>()?;
+    match values.len() {
+        13 => {
+            let sum: u32 = values.iter().enumerate().map(|(i, v)| if i % 2 == 0 { *v } else { v * 3 }).sum();
+            sum.is_multiple_of(10).then(|| digits.to_string())
+        }
+        10 => {
+            let sum: u32 = values.iter().enumerate().map(|(i, v)| (10 - i as u32) * v).sum();
+            if !sum.is_multiple_of(11) {
+                return None;
+            }
+            let body: String = std::iter::once("978".to_string())
+                .chain(values[..9].iter().map(u32::to_string))
+                .collect();
+            let sum: u32 = body
+                .chars()
+                .filter_map(|c| c.to_digit(10))
+                .enumerate()
+                .map(|(i, v)| if i % 2 == 0 { v } else { v * 3 })
+                .sum();
+            Some(format!("{}{}", body, (10 - sum % 10) % 10))
+        }
+        _ => None,
+    }
+}
+
+/// Dates as `2024-03-15`, `15.03.2024`, `03/15/2024`, `15 March 2024` or
+/// `March 15, 2024`. Dates with slashes are read month first, unless that
+/// gives no valid date; dates with dots or dashes are read day first.
+fn match_date(tokens: &[&str]) -> Option<Match> {
+    let first = trim_token(tokens[0]);
+    if let Some(date) = numeric_date(first) {
+        return Some((EntityKind::Date, date.format("%Y-%m-%d").to_string(), 1));
+    }
+
+    let word = |i: usize| tokens.get(i).map(|t| trim_token(t));
+    let date = match (word(0), word(1), word(2)) {
+        (Some(day), Some(month), Some(year)) if day_number(day).is_some() => {
+            date_from_parts(year, month_number(month)?, day_number(day)?)
+        }
+        (Some(month), Some(day), Some(year)) if month_number(month).is_some() => {
+            date_from_parts(year, month_number(month)?, day_number(day)?)
+        }
+        _ => None,
+    }?;
+    Some((EntityKind::Date, date.format("%Y-%m-%d").to_string(), 3))
+}
+
+fn numeric_date(token: &str) -> Option<NaiveDate> {
+    let separator = token.chars().find(|c| matches!(c, '-' | '.' | '/'))?;
+    let parts: Vec<&str> = token.split(separator).collect();
+    if parts.len() != 3 || !parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let number = |i: usize| parts[i].parse::<u32>().ok();
+    if parts[0].len() == 4 && separator == '-' {
+        return (parts[1].len() == 2 && parts[2].len() == 2)
+            .then(|| date_from_parts(parts[0], number(1)?, number(2)?))
+            .flatten();
+    }
+    if parts[2].len() != 4 || parts[0].len() > 2 || parts[1].len() > 2 {
+        return None;
+    }
+    match separator {
+        '/' => date_from_parts(parts[2], number(0)?, number(1)?).or_else(|| date_from_parts(parts[2], number(1)?, number(0)?)),
+        _ => date_from_parts(parts[2], number(1)?, number(0)?),
+    }
+}
+
+fn date_from_parts(year: &str, month: u32, day: u32) -> Option<NaiveDate> {
+    if year.len() != 4 {
+        return None;
+    }
+    let year: i32 = year.parse().ok()?;
+    if !(1000..3000).contains(&year) {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// 1-based month of an English month name or its abbreviation
+fn month_number(word: &str) -> Option<u32> {
+    let word = word.to_lowercase();
+    let word = word.trim_end_matches('.');
+    if word.len() < 3 {
+        return None;
+    }
+    let word = if word == "sept" { "sep" } else { word };
+    MONTHS
+        .iter()
+        .position(|month| *month == word || (word.len() == 3 && month.starts_with(word)))
+        .map(|i| i as u32 + 1)
+}
+
+/// Day of the month, allowing ordinals such as "15th"
+fn day_number(word: &str) -> Option<u32> {
+    let digits = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix))
+        .unwrap_or(word);
+    if digits.is_empty() || digits.len() > 2 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+/// Currency amounts with a symbol or ISO code before or after the number,
+/// in one word (`$1,200.50`, `1200€`) or two (`EUR 1.200,50`, `300 CHF`)
+fn match_amount(tokens: &[&str]) -> Option<Match> {
+    let first = trim_token(tokens[0]);
+    let amount = |currency: &str, number: &str| parse_number(number).map(|n| format!("{} {}", currency, n));
+    let found = if let Some((currency, number)) = split_currency_prefix(first).filter(|(_, n)| !n.is_empty()) {
+        amount(currency, number).map(|a| (a, 1))
+    } else if let Some((number, currency)) = split_currency_suffix(first).filter(|(n, _)| !n.is_empty()) {
+        amount(currency, number).map(|a| (a, 1))
+    } else if let (Some(currency), Some(next)) = (currency_code(first), tokens.get(1)) {
+        amount(currency, trim_token(next)).map(|a| (a, 2))
+    } else if let Some(next) = tokens.get(1).and_then(|next| currency_code(trim_token(next))) {
+        amount(next, first).map(|a| (a, 2))
+    } else {
+        None
+    };
+    found.map(|(value, used)| (EntityKind::Amount, value, used))
+}
+
+/// ISO code of a currency written as a symbol or a code
+fn currency_code(word: &str) -> Option<&'static str> {
+    CURRENCY_SYMBOLS
+        .iter()
+        .find(|(symbol, _)| *symbol == word)
+        .map(|(_, code)| *code)
+        .or_else(|| CURRENCY_CODES.iter().find(|code| **code == word).copied())
+}
+
+fn split_currency_prefix(word: &str) -> Option<(&'static str, &str)> {
+    for (symbol, code) in CURRENCY_SYMBOLS {
+        if let Some(rest) = word.strip_prefix(symbol) {
+            return Some((code, rest));
+        }
+    }
+    CURRENCY_CODES.iter().find_map(|code| word.strip_prefix(code).map(|rest| (*code, rest)))
+}
+
+fn split_currency_suffix(word: &str) -> Option<(&str, &'static str)> {
+    for (symbol, code) in CURRENCY_SYMBOLS {
+        if let Some(rest) = word.strip_suffix(symbol) {
+            return Some((rest, code));
+        }
+    }
+    CURRENCY_CODES.iter().find_map(|code| word.strip_suffix(code).map(|rest| (rest, *code)))
+}
+
+/// A number written with thousands separators and a decimal point or comma,
+/// as in `1,200.50`, `1.200,50` or `1'200`, as `1200.50`. Zero cents are dropped.
+fn parse_number(number: &str) -> Option<String> {
+    // "5,-" and "5,–" mean a round amount
+    let number = number.trim_end_matches([',', '.', '-', '–']);
+    if number.is_empty()
+        || !number.starts_with(|c: char| c.is_ascii_digit())
+        || !number.chars().all(|c| c.is_ascii_digit() || matches!(c, ',' | '.' | '\''))
+    {
+        return None;
+    }
+
+    // The decimal separator is the last separator when both are used, or
+    // a lone one not followed by a group of three digits
+    let separators = number.matches([',', '.']).count();
+    let decimal = match number.rfind([',', '.']) {
+        Some(i) if number.contains(',') && number.contains('.') => Some(i),
+        Some(i) if separators == 1 && number.len() - i - 1 != 3 => Some(i),
+        _ => None,
+    };
+    let (integer, fraction) = match decimal {
+        Some(i) => (&number[..i], &number[i + 1..]),
+        None => (number, ""),
+    };
+    let integer: String = integer.chars().filter(char::is_ascii_digit).collect();
+    let integer = integer.trim_start_matches('0');
+    let integer = if integer.is_empty() { "0" } else { integer };
+    // "1,200.00" is the same amount as "1.200"
+    if fraction.chars().all(|c| c == '0') {
+        Some(integer.to_string())
+    } else if fraction.len() <= 2 && fraction.chars().all(|c| c.is_ascii_digit()) {
+        Some(format!("{}.{}", integer, fraction))
+    } else {
+        None
+    }
+}
+
+/// Phone numbers in international form (`+31 20 123 4567`), with an area
+/// code in parentheses (`(020) 123-4567`) or in dashed or dotted groups
+/// (`555-123-4567`). Bare digit strings are too ambiguous to count.
+fn match_phone(tokens: &[&str]) -> Option<Match> {
+    let mut parts = Vec::new();
+    for (i, token) in tokens.iter().take(MAX_SPAN_TOKENS).enumerate() {
+        let mut part = token.trim_end_matches([',', ';', ':', '.']);
+        if i == 0 && part.starts_with('(') && !part.contains(')') {
+            part = &part[1..];
+        }
+        let is_part = part.chars().any(|c| c.is_ascii_digit())
+            && part.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | '(' | ')' | '-' | '.' | '/'));
+        if !is_part {
+            break;
+        }
+        parts.push(part);
+        // Punctuation after the number ends it
+        if !token.ends_with(part) {
+            break;
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    let span = parts.join(" ");
+    let digits: String = span.chars().filter(char::is_ascii_digit).collect();
+    let international = span.starts_with('+') && span.matches('+').count() == 1;
+    let area_code = span.find('(').zip(span.find(')')).is_some_and(|(open, close)| {
+        close > open + 1 && span[open + 1..close].chars().all(|c| c.is_ascii_digit())
+    });
+    let groups: Vec<&str> = span.split(['-', '.']).collect();
+    let grouped = parts.len() == 1
+        && groups.len() >= 3
+        && (span.contains('-') != span.contains('.'))
+        && groups.iter().all(|g| (2..=4).contains(&g.len()) && g.chars().all(|c| c.is_ascii_digit()));
+
+    let valid = match () {
+        _ if international => (8..=15).contains(&digits.len()),
+        _ if area_code => (9..=15).contains(&digits.len()) && !span.contains('+'),
+        _ if grouped => (9..=15).contains(&digits.len()),
+        _ => false,
+    };
+    if !valid {
+        return None;
+    }
+    let value = if international { format!("+{}", digits) } else { digits };
+    Some((EntityKind::Phone, value, parts.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str, kind: EntityKind) -> Vec<String> {
+        extract_entities(text)
+            .into_iter()
+            .filter(|entity| entity.kind == kind)
+            .map(|entity| entity.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let text = "Mail Jane.Doe@Example.com or <mailto:sales@example.co.uk>. Pay to NL91 ABNA 0417 1643 00, \
+                    not NL91 ABNA 0417 1643 01. See doi:10.1000/XYZ-123. and ISBN 0-306-40615-2, \
+                    ISBN-13: 978-0-306-40615-7 or 978-3-16-148410-0 (but not 978-3-16-148410-1).";
+        assert_eq!(values(text, EntityKind::Email), vec!["jane.doe@example.com", "sales@example.co.uk"]);
+        assert_eq!(values(text, EntityKind::Iban), vec!["NL91ABNA0417164300"]);
+        assert_eq!(values(text, EntityKind::Doi), vec!["10.1000/xyz-123"]);
+        assert_eq!(values(text, EntityKind::Isbn), vec!["9780306406157", "9783161484100"]);
+        // Both forms of the same ISBN count as one entity
+        let isbn = extract_entities(text).into_iter().find(|e| e.value == "9780306406157").unwrap();
+        assert_eq!(isbn.count, 2);
+    }
+
+    #[test]
+    fn test_dates_amounts_and_phone_numbers() {
+        let text = "Signed 2024-03-15 and 15.03.2024, due 03/31/2024 or 31/03/2024, March 15, 2024 \
+                    and 1st April 2024; not 2024-13-01. Total $1,200.50, EUR 1.200,50, 300 CHF, 99€ \
+                    and 5 apples. Call +31 20 123 4567, (020) 123-4567 or 555-123-4567, not 2024 2025 2026.";
+        assert_eq!(values(text, EntityKind::Date), vec!["2024-03-15", "2024-03-31", "2024-04-01"]);
+        assert_eq!(values(text, EntityKind::Amount), vec!["CHF 300", "EUR 1200.50", "EUR 99", "USD 1200.50"]);
+        assert_eq!(values(text, EntityKind::Phone), vec!["+31201234567", "0201234567", "5551234567"]);
+
+        assert_eq!(EntityKind::Isbn.normalize("0-306-40615-2").as_deref(), Some("9780306406157"));
+        assert_eq!(EntityKind::Date.normalize("15.03.2024").as_deref(), Some("2024-03-15"));
+        assert_eq!(EntityKind::Doi.normalize("1234"), None);
+    }
+}
@@ -0,0 +1,368 @@
+use crate::attachments::Attachment;
+use crate::links::Link;
+use crate::outline::OutlineEntry;
+use crate::pdf_text::{self, Annotation, EncryptionError, ExtractOptions, FormField, PageTexts};
+use crate::tables::Table;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Command-line argument that starts the app as an extraction worker
+pub const WORKER_ARG: &str = "--extract-worker";
+
+/// Prefix of protocol lines on the worker's stdout. The PDF libraries print
+/// diagnostics to stdout, so every other line is ignored.
+const RESPONSE_PREFIX: &str = "@@pdf-finder-worker@@ ";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WorkerRequest {
+    path: PathBuf,
+    /// Passwords to try if the PDF is encrypted; only ever sent over the pipe
+    #[serde(default)]
+    passwords: Vec<String>,
+    #[serde(flatten)]
+    options: ExtractOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum WorkerResponse {
+    Ok {
+        pages: Vec<String>,
+        #[serde(default)]
+        annotations: Vec<Annotation>,
+        #[serde(default)]
+        form_fields: Vec<FormField>,
+        #[serde(default)]
+        outline: Vec<OutlineEntry>,
+        #[serde(default)]
+        tables: Vec<Table>,
+        #[serde(default)]
+        links: Vec<Link>,
+        #[serde(default)]
+        attachments: Vec<Attachment>,
+        #[serde(default)]
+        password_protected: bool,
+    },
+    Error { message: String },
+    Panicked { message: String },
+    Encrypted { message: String },
+}
+
+/// Result of extracting the text of one file
+#[derive(Debug)]
+pub enum ExtractOutcome {
+    Pages(PageTexts),
+    /// The PDF library returned an error
+    Failed(String),
+    /// The PDF library panicked
+    Panicked(String),
+    /// The PDF is encrypted and could not be decrypted
+    Encrypted(String),
+    /// The worker did not answer within the timeout and was killed
+    TimedOut(Duration),
+    /// The worker died, e.g. from a stack overflow, an abort or the memory limit
+    Crashed(String),
+}
+
+/// Extract text in this process, catching panics but nothing worse
+pub fn extract_in_process(path: &Path, passwords: &[String], options: ExtractOptions) -> ExtractOutcome {
+    let path_buf = path.to_path_buf();
+    match std::panic::catch_unwind(|| pdf_text::extract_pages(&path_buf, passwords, options)) {
+        Ok(Ok(pages)) => ExtractOutcome::Pages(pages),
+        Ok(Err(e)) if e.downcast_ref::<EncryptionError>().is_some() => ExtractOutcome::Encrypted(e.to_string()),
+        Ok(Err(e)) => ExtractOutcome::Failed(format!("{:#}", e)),
+        Err(panic) => ExtractOutcome::Panicked(panic_message(&panic)),
+    }
+}
+
+/// Message carried by a caught panic payload
+pub fn panic_message(panic: &Box<dyn std::any::Any + Send>) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "PDF extraction panicked".to_string())
+}
+
+/// Entry point of a worker process: serve extraction requests from stdin
+/// until it closes. `args` follow `WORKER_ARG`; the first one is the memory
+/// limit in bytes.
+pub fn run_worker(args: &[String]) {
+    if let Some(limit) = args.first().and_then(|a| a.parse::<u64>().ok()) {
+        limit_memory(limit);
+    }
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        let response = match serde_json::from_str::<WorkerRequest>(&line) {
+            Ok(request) => match extract_in_process(&request.path, &request.passwords, request.options) {
+                ExtractOutcome::Pages(pages) => WorkerResponse::Ok {
+                    pages: pages.pages,
+                    annotations: pages.annotations,
+                    form_fields: pages.form_fields,
+                    outline: pages.outline,
+                    tables: pages.tables,
+                    links: pages.links,
+                    attachments: pages.attachments,
+                    password_protected: pages.password_protected,
+                },
+                ExtractOutcome::Panicked(message) => WorkerResponse::Panicked { message },
+                ExtractOutcome::Encrypted(message) => WorkerResponse::Encrypted { message },
+                ExtractOutcome::Failed(message)
+                | ExtractOutcome::Crashed(message) => WorkerResponse::Error { message },
+                ExtractOutcome::TimedOut(_) => unreachable!("in-process extraction has no timeout"),
+            },
+            Err(e) => WorkerResponse::Error { message: format!("Invalid request: {}", e) },
+        };
+
+        let encoded = match serde_json::to_string(&response) {
+            Ok(encoded) => encoded,
+            Err(_) => break,
+        };
+        let mut out = stdout.lock();
+        if writeln!(out, "{}{}", RESPONSE_PREFIX, encoded).is_err() || out.flush().is_err() {
+            break;
+        }
+    }
+}
+
+/// Cap the address space of this process so a runaway PDF fails its own
+/// allocation instead of exhausting the machine's memory
+#[cfg(unix)]
+fn limit_memory(bytes: u64) {
+    let limit = libc::rlimit {
+        rlim_cur: bytes as libc::rlim_t,
+        rlim_max: bytes as libc::rlim_t,
+    };
+    // SAFETY: setrlimit only reads the struct passed by reference
+    if unsafe { libc::setrlimit(libc::RLIMIT_AS, &limit) } != 0 {
+        log::warn!("Could not limit worker memory: {}", std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(not(unix))]
+fn limit_memory(_bytes: u64) {
+    log::debug!("Worker memory limits are not supported on this platform");
+}
+
+/// A running worker process
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    responses: Receiver<String>,
+}
+
+impl Worker {
+    fn spawn(program: &Path, args: &[String]) -> Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to start extraction worker {}", program.display()))?;
+
+        let stdin = child.stdin.take().context("Worker stdin is not piped")?;
+        let stdout = child.stdout.take().context("Worker stdout is not piped")?;
+
+        // Read on a separate thread so the pool can wait with a timeout
+        let (sender, responses) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                match line.strip_prefix(RESPONSE_PREFIX) {
+                    Some(response) => {
+                        if sender.send(response.to_string()).is_err() {
+                            break;
+                        }
+                    }
+                    None => log::debug!("Worker output: {}", line),
+                }
+            }
+        });
+
+        Ok(Worker { child, stdin, responses })
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Pool of extraction worker processes
+/// Each file is extracted in a separate process with a wall-clock timeout, so
+/// a PDF that loops forever, overflows the stack or exhausts memory takes down
+/// only its worker, which is then replaced. Workers are reused between files
+/// and the pool grows to the number of threads extracting concurrently.
+pub struct WorkerPool {
+    program: PathBuf,
+    args: Vec<String>,
+    timeout: Duration,
+    idle: Mutex<Vec<Worker>>,
+}
+
+impl WorkerPool {
+    pub fn new(program: PathBuf, args: Vec<String>, timeout: Duration) -> Self {
+        WorkerPool {
+            program,
+            args,
+            timeout,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Pool of workers running `executable` (normally this app) with `WORKER_ARG`
+    pub fn for_executable(executable: PathBuf, timeout: Duration, memory_limit: u64) -> Self {
+        Self::new(executable, vec![WORKER_ARG.to_string(), memory_limit.to_string()], timeout)
+    }
+
+    pub fn extract(&self, path: &Path, passwords: &[String], options: ExtractOptions) -> ExtractOutcome {
+        let mut worker = match self.take_worker() {
+            Ok(worker) => worker,
+            Err(e) => return ExtractOutcome::Crashed(format!("{:#}", e)),
+        };
+
+        let request = WorkerRequest {
+            path: path.to_path_buf(),
+            passwords: passwords.to_vec(),
+            options,
+        };
+        let request = match serde_json::to_string(&request) {
+            Ok(request) => request,
+            Err(e) => return ExtractOutcome::Failed(format!("Invalid path: {}", e)),
+        };
+        if let Err(e) = writeln!(worker.stdin, "{}", request).and_then(|_| worker.stdin.flush()) {
+            return ExtractOutcome::Crashed(format!("Failed to send request to worker: {}", e));
+        }
+
+        match worker.responses.recv_timeout(self.timeout) {
+            Ok(line) => {
+                let outcome = match serde_json::from_str::<WorkerResponse>(&line) {
+                    Ok(WorkerResponse::Ok {
+                        pages,
+                        annotations,
+                        form_fields,
+                        outline,
+                        tables,
+                        links,
+                        attachments,
+                        password_protected,
+                    }) => ExtractOutcome::Pages(PageTexts {
+                        pages,
+                        annotations,
+                        form_fields,
+                        outline,
+                        tables,
+                        links,
+                        attachments,
+                        password_protected,
+                    }),
+                    Ok(WorkerResponse::Error { message }) => ExtractOutcome::Failed(message),
+                    Ok(WorkerResponse::Panicked { message }) => ExtractOutcome::Panicked(message),
+                    Ok(WorkerResponse::Encrypted { message }) => ExtractOutcome::Encrypted(message),
+                    Err(e) => ExtractOutcome::Crashed(format!("Invalid worker response: {}", e)),
+                };
+                self.idle.lock().unwrap().push(worker);
+                outcome
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                log::warn!("Extraction of {} timed out after {:?}, killing worker", path.display(), self.timeout);
+                ExtractOutcome::TimedOut(self.timeout)
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let status = worker
+                    .child
+                    .wait()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|e| e.to_string());
+                log::warn!("Extraction worker died on {} ({})", path.display(), status);
+                ExtractOutcome::Crashed(format!("Extraction worker exited unexpectedly ({})", status))
+            }
+        }
+    }
+
+    /// Reuse an idle worker that is still running, or start a new one
+    fn take_worker(&self) -> Result<Worker> {
+        let mut idle = self.idle.lock().unwrap();
+        while let Some(mut worker) = idle.pop() {
+            if worker.is_alive() {
+                return Ok(worker);
+            }
+        }
+        drop(idle);
+        Worker::spawn(&self.program, &self.args)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn shell_pool(script: &str, timeout: Duration) -> WorkerPool {
+        WorkerPool::new(
+            PathBuf::from("/bin/sh"),
+            vec!["-c".to_string(), script.to_string()],
+            timeout,
+        )
+    }
+
+    #[test]
+    fn test_worker_response_ignores_library_output() {
+        let script = format!(
+            "while read line; do echo 'Warning: invalid stream length'; echo '{}{{\"status\":\"ok\",\"pages\":[\"one\",\"two\"]}}'; done",
+            RESPONSE_PREFIX
+        );
+        let pool = shell_pool(&script, Duration::from_secs(10));
+
+        for _ in 0..2 {
+            match pool.extract(Path::new("/tmp/a.pdf"), &[], ExtractOptions::default()) {
+                ExtractOutcome::Pages(pages) => assert_eq!(pages.pages, vec!["one", "two"]),
+                other => panic!("unexpected outcome {:?}", other),
+            }
+        }
+        // The worker was reused rather than respawned
+        assert_eq!(pool.idle.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_stuck_worker_is_killed() {
+        let pool = shell_pool("read line; sleep 30", Duration::from_millis(200));
+        let start = std::time::Instant::now();
+
+        assert!(matches!(pool.extract(Path::new("/tmp/a.pdf"), &[], ExtractOptions::default()), ExtractOutcome::TimedOut(_)));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(pool.idle.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_crashed_worker_is_reported() {
+        let pool = shell_pool("read line; exit 3", Duration::from_secs(10));
+        match pool.extract(Path::new("/tmp/a.pdf"), &[], ExtractOptions::default()) {
+            ExtractOutcome::Crashed(message) => assert!(message.contains('3'), "{}", message),
+            other => panic!("unexpected outcome {:?}", other),
+        }
+    }
+}
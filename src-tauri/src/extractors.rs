@@ -0,0 +1,744 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Bytes read from a file without a known extension to recognize its format
+const SNIFF_LEN: usize = 1024;
+
+/// Members of ZIP-based documents larger than this when unpacked are treated
+/// as malformed, guarding against decompression bombs
+const MAX_MEMBER_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Limit on everything unpacked from one ZIP-based document, so a book of
+/// many chapters cannot add up to more than a single oversized member
+const MAX_UNPACKED_SIZE: u64 = 2 * MAX_MEMBER_SIZE;
+
+/// Extracts the text of documents in one format other than PDF, which has
+/// its own pipeline with worker processes, OCR and annotations
+pub trait Extractor: Send + Sync {
+    fn name(&self) -> &str;
+    /// Lowercase file extensions of the format, without the dot
+    fn extensions(&self) -> &[&str];
+    /// Whether `head`, the first bytes of a file, look like this format
+    fn sniff(&self, head: &[u8]) -> bool;
+    fn extract(&self, data: &[u8]) -> Result<String>;
+}
+
+/// The extractors known to the indexer, looked up by file extension or, for
+/// files without one, by their first bytes. Only formats with a signature are
+/// sniffed; plain text is recognized by its extension alone, as too many
+/// files without one (LICENSE, Makefile, Git's own files) would pass for it.
+pub struct ExtractorRegistry {
+    extractors: Vec<Arc<dyn Extractor>>,
+}
+
+impl Default for ExtractorRegistry {
+    fn default() -> Self {
+        // ZIP-based formats come before plain text so sniffing tries them first
+        ExtractorRegistry {
+            extractors: vec![
+                Arc::new(DocxExtractor),
+                Arc::new(OdtExtractor),
+                Arc::new(EpubExtractor),
+                Arc::new(HtmlExtractor),
+                Arc::new(MarkdownExtractor),
+                Arc::new(TextExtractor),
+            ],
+        }
+    }
+}
+
+impl ExtractorRegistry {
+    /// Extractor for the file at `path`, None for PDFs and unsupported files.
+    /// Hidden files such as `.gitignore` or `.pdffinderignore` are never
+    /// sniffed.
+    pub fn for_path(&self, path: &Path) -> Option<Arc<dyn Extractor>> {
+        match path.extension() {
+            Some(extension) => self.for_extension(&extension.to_string_lossy()),
+            None if path.file_name()?.to_string_lossy().starts_with('.') => None,
+            None => {
+                let mut head = Vec::with_capacity(SNIFF_LEN);
+                File::open(path).ok()?.take(SNIFF_LEN as u64).read_to_end(&mut head).ok()?;
+                self.for_content(&head)
+            }
+        }
+    }
+
+    pub fn for_extension(&self, extension: &str) -> Option<Arc<dyn Extractor>> {
+        let extension = extension.to_lowercase();
+        self.extractors
+            .iter()
+            .find(|extractor| extractor.extensions().contains(&extension.as_str()))
+            .cloned()
+    }
+
+    pub fn for_content(&self, head: &[u8]) -> Option<Arc<dyn Extractor>> {
+        if head.is_empty() {
+            return None;
+        }
+        self.extractors.iter().find(|extractor| extractor.sniff(head)).cloned()
+    }
+}
+
+/// Plain text in UTF-8, UTF-16 with a byte order mark, or Latin-1
+pub struct TextExtractor;
+
+impl Extractor for TextExtractor {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt", "text", "log", "csv", "tsv"]
+    }
+
+    fn sniff(&self, _head: &[u8]) -> bool {
+        // Most files without an extension are text of some kind, few of them
+        // documents worth searching
+        false
+    }
+
+    fn extract(&self, data: &[u8]) -> Result<String> {
+        Ok(decode_text(data))
+    }
+}
+
+/// Markdown, indexed without its markup
+pub struct MarkdownExtractor;
+
+impl Extractor for MarkdownExtractor {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["md", "markdown", "mdown", "mkd"]
+    }
+
+    fn sniff(&self, _head: &[u8]) -> bool {
+        // Indistinguishable from plain text, which indexes it just as well
+        false
+    }
+
+    fn extract(&self, data: &[u8]) -> Result<String> {
+        Ok(markdown_text(&decode_text(data)))
+    }
+}
+
+pub struct HtmlExtractor;
+
+impl Extractor for HtmlExtractor {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["html", "htm", "xhtml"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        let head = String::from_utf8_lossy(head).trim_start_matches('\u{feff}').trim_start().to_lowercase();
+        head.starts_with("<!doctype html") || head.starts_with("<html")
+    }
+
+    fn extract(&self, data: &[u8]) -> Result<String> {
+        Ok(markup_text(&decode_text(data), &HTML_RULES))
+    }
+}
+
+/// Word documents (Office Open XML)
+pub struct DocxExtractor;
+
+impl Extractor for DocxExtractor {
+    fn name(&self) -> &str {
+        "docx"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["docx", "docm"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        head.starts_with(b"PK\x03\x04") && contains(head, b"word/")
+    }
+
+    fn extract(&self, data: &[u8]) -> Result<String> {
+        let mut zip = ZipMembers::new(data)?;
+        let document = zip.read("word/document.xml")?;
+        let mut text = markup_text(&document, &DOCX_RULES);
+        // Footnotes and endnotes live in parts of their own
+        for part in ["word/footnotes.xml", "word/endnotes.xml"] {
+            if let Ok(notes) = zip.read(part) {
+                text.push('\n');
+                text.push_str(&markup_text(&notes, &DOCX_RULES));
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// OpenDocument text documents
+pub struct OdtExtractor;
+
+impl Extractor for OdtExtractor {
+    fn name(&self) -> &str {
+        "odt"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["odt"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        zip_mimetype(head).is_some_and(|mimetype| mimetype.starts_with(b"application/vnd.oasis.opendocument.text"))
+    }
+
+    fn extract(&self, data: &[u8]) -> Result<String> {
+        let mut zip = ZipMembers::new(data)?;
+        Ok(markup_text(&zip.read("content.xml")?, &ODT_RULES))
+    }
+}
+
+/// EPUB books, with their chapters in reading order
+pub struct EpubExtractor;
+
+impl Extractor for EpubExtractor {
+    fn name(&self) -> &str {
+        "epub"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["epub"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        zip_mimetype(head).is_some_and(|mimetype| mimetype.starts_with(b"application/epub+zip"))
+    }
+
+    fn extract(&self, data: &[u8]) -> Result<String> {
+        let mut zip = ZipMembers::new(data)?;
+        let container = zip.read("META-INF/container.xml")?;
+        let package_path = start_tags(&container)
+            .find(|(name, _)| local_name(name) == "rootfile")
+            .and_then(|(_, tag)| attribute(tag, "full-path"))
+            .context("EPUB without a package document")?;
+        let package = zip.read(&package_path)?;
+        let base = package_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+
+        let mut manifest = std::collections::HashMap::new();
+        let mut spine = Vec::new();
+        for (name, tag) in start_tags(&package) {
+            match local_name(name) {
+                "item" => {
+                    if let (Some(id), Some(href)) = (attribute(tag, "id"), attribute(tag, "href")) {
+                        manifest.insert(id, href);
+                    }
+                }
+                "itemref" => spine.extend(attribute(tag, "idref")),
+                _ => {}
+            }
+        }
+
+        let mut chapters = Vec::new();
+        for idref in spine {
+            let Some(href) = manifest.get(&idref) else {
+                continue;
+            };
+            let member = resolve_href(base, href);
+            match zip.read(&member) {
+                Ok(chapter) => chapters.push(markup_text(&chapter, &HTML_RULES)),
+                Err(e) if zip.exhausted() => {
+                    log::debug!("Skipping the EPUB chapters from {} on: {:#}", member, e);
+                    break;
+                }
+                Err(e) => log::debug!("Skipping EPUB chapter {}: {:#}", member, e),
+            }
+        }
+        Ok(chapters.join("\n\n"))
+    }
+}
+
+/// How markup is turned into text
+struct MarkupRules {
+    /// Elements that start a new line, e.g. paragraphs
+    blocks: &'static [&'static str],
+    /// Elements that separate words, e.g. tabs and table cells
+    spaces: &'static [&'static str],
+    /// Elements whose content is not text, e.g. scripts
+    skipped: &'static [&'static str],
+}
+
+const HTML_RULES: MarkupRules = MarkupRules {
+    blocks: &[
+        "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "figcaption", "footer", "h1",
+        "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "nav", "ol", "p", "pre", "section", "table", "title",
+        "tr", "ul",
+    ],
+    spaces: &["td", "th"],
+    skipped: &["script", "style", "noscript", "template", "svg"],
+};
+
+const DOCX_RULES: MarkupRules = MarkupRules {
+    blocks: &["w:p", "w:br", "w:cr"],
+    spaces: &["w:tab"],
+    // Field codes and tracked deletions are not part of the visible text
+    skipped: &["w:instrText", "w:delText"],
+};
+
+const ODT_RULES: MarkupRules = MarkupRules {
+    blocks: &["text:p", "text:h", "text:line-break"],
+    spaces: &["text:s", "text:tab"],
+    skipped: &[],
+};
+
+/// Text content of HTML or XML, with block elements on lines of their own
+fn markup_text(markup: &str, rules: &MarkupRules) -> String {
+    let is = |names: &[&str], name: &str| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+
+    let mut text = String::with_capacity(markup.len() / 2);
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..start]));
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or("");
+            continue;
+        }
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            text.push_str(&cdata[..end]);
+            rest = cdata.get(end + 3..).unwrap_or("");
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        let opening = !tag.starts_with('/') && !tag.ends_with('/');
+        if opening && is(rules.skipped, name) {
+            // Jump past the closing tag, as scripts may contain `<` themselves
+            let closing = format!("</{}", name);
+            rest = match find_ignore_ascii_case(rest, &closing) {
+                Some(index) => rest[index..].find('>').map(|end| &rest[index + end + 1..]).unwrap_or(""),
+                None => "",
+            };
+            continue;
+        }
+        if is(rules.blocks, name) {
+            text.push('\n');
+        } else if is(rules.spaces, name) {
+            text.push(' ');
+        }
+    }
+    text.push_str(&decode_entities(rest));
+    tidy_lines(&text)
+}
+
+/// Byte index of the first occurrence of the ASCII `needle` in `haystack`,
+/// ignoring ASCII case
+fn find_ignore_ascii_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Collapse whitespace within lines and drop empty ones
+fn tidy_lines(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replace character references and the common named entities
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').filter(|&end| end <= 10).map(|end| &rest[1..end + 1]);
+        let replacement = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('\u{2013}'),
+            "mdash" => Some('\u{2014}'),
+            "lsquo" => Some('\u{2018}'),
+            "rsquo" => Some('\u{2019}'),
+            "ldquo" => Some('\u{201C}'),
+            "rdquo" => Some('\u{201D}'),
+            "hellip" => Some('\u{2026}'),
+            "copy" => Some('\u{A9}'),
+            _ => {
+                let code = entity.strip_prefix('#')?;
+                let code = match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => code.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, replacement) {
+            (Some(entity), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Text of a Markdown document without headings markers, emphasis and link
+/// targets; code blocks are kept as they are
+fn markdown_text(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let mut line = trimmed.trim_start_matches(['#', '>']).trim_start();
+        for marker in ["- ", "* ", "+ "] {
+            line = line.strip_prefix(marker).unwrap_or(line);
+        }
+        if let Some((number, item)) = line.split_once(". ") {
+            if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+                line = item;
+            }
+        }
+        // Horizontal rules and table separators
+        if line.chars().all(|c| matches!(c, '-' | '*' | '_' | '=' | '|' | ':' | ' ')) {
+            continue;
+        }
+        let line = strip_links(line).replace(['*', '`'], "").replace("~~", "").replace('|', " ");
+        lines.push(decode_entities(&line));
+    }
+    tidy_lines(&lines.join("\n"))
+}
+
+/// Replace `[text](target)` and `![alt](image)` with their text
+fn strip_links(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find('[') {
+        let after = &rest[open + 1..];
+        let link = after.find("](").and_then(|close| {
+            let target_end = after[close + 2..].find(')')?;
+            Some((&after[..close], close + 2 + target_end + 1))
+        });
+        match link {
+            Some((text, len)) => {
+                stripped.push_str(rest[..open].strip_suffix('!').unwrap_or(&rest[..open]));
+                stripped.push_str(text);
+                rest = &after[len..];
+            }
+            None => {
+                stripped.push_str(&rest[..open + 1]);
+                rest = after;
+            }
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Decode text that is UTF-16 with a byte order mark, UTF-8, or failing
+/// that, Latin-1
+fn decode_text(data: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+    if let Some(bytes) = data.strip_prefix(b"\xFF\xFE") {
+        utf16(bytes, u16::from_le_bytes)
+    } else if let Some(bytes) = data.strip_prefix(b"\xFE\xFF") {
+        utf16(bytes, u16::from_be_bytes)
+    } else {
+        let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        match std::str::from_utf8(data) {
+            Ok(text) => text.to_string(),
+            Err(_) => data.iter().map(|&b| b as char).collect(),
+        }
+    }
+}
+
+/// The members of a ZIP-based document, read as text up to `MAX_MEMBER_SIZE`
+/// each and `MAX_UNPACKED_SIZE` in all
+struct ZipMembers<'a> {
+    zip: zip::ZipArchive<Cursor<&'a [u8]>>,
+    remaining: u64,
+}
+
+impl<'a> ZipMembers<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        let zip = zip::ZipArchive::new(Cursor::new(data)).context("Not a ZIP file")?;
+        Ok(Self { zip, remaining: MAX_UNPACKED_SIZE })
+    }
+
+    fn read(&mut self, name: &str) -> Result<String> {
+        if self.exhausted() {
+            anyhow::bail!("The document unpacks to more than {} bytes", MAX_UNPACKED_SIZE);
+        }
+        let limit = MAX_MEMBER_SIZE.min(self.remaining);
+        let member = self.zip.by_name(name).with_context(|| format!("Missing {}", name))?;
+        let mut data = Vec::new();
+        member.take(limit + 1).read_to_end(&mut data)?;
+        self.remaining = self.remaining.saturating_sub(data.len() as u64);
+        if data.len() as u64 > limit {
+            anyhow::bail!("{} is too large", name);
+        }
+        Ok(decode_text(&data))
+    }
+
+    /// Whether the size limit of the whole document has been used up
+    fn exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+/// The `mimetype` member that EPUB and OpenDocument files store uncompressed
+/// at the very start of the ZIP file
+fn zip_mimetype(head: &[u8]) -> Option<&[u8]> {
+    const NAME_OFFSET: usize = 30;
+    if !head.starts_with(b"PK\x03\x04") {
+        return None;
+    }
+    head.get(NAME_OFFSET..)?.strip_prefix(b"mimetype")
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Name and body of each start or empty-element tag of an XML document
+fn start_tags(xml: &str) -> impl Iterator<Item = (&str, &str)> {
+    xml.split('<').skip(1).filter_map(|chunk| {
+        let tag = &chunk[..chunk.find('>')?];
+        if tag.starts_with(['/', '!', '?']) {
+            return None;
+        }
+        let name = tag.split(|c: char| c.is_whitespace() || c == '/').next()?;
+        Some((name, tag))
+    })
+}
+
+/// Name without its namespace prefix
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Value of the attribute `name` in the body of a tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+    while let Some(index) = rest.find(name) {
+        let preceded_by_space = rest[..index].ends_with(|c: char| c.is_whitespace());
+        let after = rest[index + name.len()..].trim_start();
+        rest = &rest[index + name.len()..];
+        let Some(value) = after.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        let Some(quote) = value.chars().next().filter(|&c| c == '"' || c == '\'') else {
+            continue;
+        };
+        if preceded_by_space {
+            let value = &value[1..];
+            return Some(decode_entities(&value[..value.find(quote)?]));
+        }
+    }
+    None
+}
+
+/// Path of an EPUB member referenced by `href` from a document in `base`
+fn resolve_href(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut segments: Vec<&str> = base.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let path = segments.join("/");
+    // Hrefs are URLs, so names with spaces arrive percent-encoded
+    let mut decoded = Vec::with_capacity(path.len());
+    let bytes = path.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+pub(crate) fn write_zip(path: &Path, members: &[(&str, &str)]) {
+    use std::io::Write;
+
+    let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+    for (name, content) in members {
+        // The mimetype member must be stored uncompressed to be recognized
+        let method = if *name == "mimetype" { zip::CompressionMethod::Stored } else { zip::CompressionMethod::Deflated };
+        zip.start_file(*name, zip::write::SimpleFileOptions::default().compression_method(method)).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(path: &Path) -> String {
+        let registry = ExtractorRegistry::default();
+        let extractor = registry.for_path(path).unwrap();
+        extractor.extract(&std::fs::read(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_registry_picks_extractor_by_extension_and_content() {
+        let registry = ExtractorRegistry::default();
+        assert_eq!(registry.for_extension("MD").unwrap().name(), "markdown");
+        assert_eq!(registry.for_extension("htm").unwrap().name(), "html");
+        assert!(registry.for_extension("pdf").is_none());
+        assert!(registry.for_extension("png").is_none());
+
+        assert_eq!(registry.for_content(b"<!DOCTYPE html><html>").unwrap().name(), "html");
+        assert!(registry.for_content("caf\u{e9} notes\n".as_bytes()).is_none());
+        assert!(registry.for_content(b"\x89PNG\r\n\x1a\n\0\0").is_none());
+        assert!(registry.for_content(b"").is_none());
+    }
+
+    #[test]
+    fn test_markup_and_markdown_text() {
+        let html = "<html><head><title>Guide</title><style>p { color: red }</style></head>\
+                    <body><h1>Safety &amp; Grounding</h1><p>Use a <b>bonded</b> chassis.</p>\
+                    <script>if (a < b) { alert('x') }</script><table><tr><td>A</td><td>B&#x27;s</td></tr></table></body></html>";
+        assert_eq!(markup_text(html, &HTML_RULES), "Guide\nSafety & Grounding\nUse a bonded chassis.\nA B's");
+        assert_eq!(markup_text("<p>Before</p><SCRIPT>x < y</Script ><p>after</p>", &HTML_RULES), "Before\nafter");
+
+        let markdown = "# Install\n\nRun the [setup tool](https://example.com/setup) with **care**.\n\n\
+                        ```\ncargo build\n```\n- first item\n1. numbered\n---\n![diagram](d.png)";
+        assert_eq!(
+            markdown_text(markdown),
+            "Install\nRun the setup tool with care.\ncargo build\nfirst item\nnumbered\ndiagram"
+        );
+
+        assert_eq!(decode_text(b"\xFF\xFEh\0i\0"), "hi");
+        assert_eq!(decode_text(b"caf\xE9"), "caf\u{e9}");
+    }
+
+    #[test]
+    fn test_extract_office_documents_and_epub() {
+        let dir = std::env::temp_dir().join(format!("extractors_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let docx = dir.join("report.docx");
+        write_zip(
+            &docx,
+            &[
+                ("[Content_Types].xml", "<Types/>"),
+                (
+                    "word/document.xml",
+                    "<w:document><w:body><w:p><w:r><w:t>Quarterly </w:t></w:r><w:r><w:t>report</w:t></w:r></w:p>\
+                     <w:p><w:r><w:instrText> PAGE </w:instrText></w:r><w:r><w:t>Revenue</w:t><w:tab/><w:t>up</w:t></w:r></w:p>\
+                     </w:body></w:document>",
+                ),
+            ],
+        );
+        assert_eq!(extract(&docx), "Quarterly report\nRevenue up");
+
+        let odt = dir.join("letter.odt");
+        write_zip(
+            &odt,
+            &[
+                ("mimetype", "application/vnd.oasis.opendocument.text"),
+                ("content.xml", "<office:text><text:h>Dear reader</text:h><text:p>Thanks<text:s/>again</text:p></office:text>"),
+            ],
+        );
+        assert_eq!(extract(&odt), "Dear reader\nThanks again");
+
+        // Without an extension the format is recognized by content
+        let epub = dir.join("book");
+        write_zip(
+            &epub,
+            &[
+                ("mimetype", "application/epub+zip"),
+                (
+                    "META-INF/container.xml",
+                    "<container><rootfiles><rootfile full-path=\"OEBPS/content.opf\"/></rootfiles></container>",
+                ),
+                (
+                    "OEBPS/content.opf",
+                    "<package><manifest><item id=\"c2\" href=\"text/chapter%202.xhtml\"/>\
+                     <item id=\"c1\" href=\"text/one.xhtml\"/></manifest>\
+                     <spine><itemref idref=\"c1\"/><itemref idref=\"c2\"/></spine></package>",
+                ),
+                ("OEBPS/text/one.xhtml", "<html><body><p>Call me Ishmael.</p></body></html>"),
+                ("OEBPS/text/chapter 2.xhtml", "<html><body><p>The Carpet-Bag</p></body></html>"),
+            ],
+        );
+        assert_eq!(ExtractorRegistry::default().for_path(&epub).unwrap().name(), "epub");
+        assert_eq!(extract(&epub), "Call me Ishmael.\n\nThe Carpet-Bag");
+
+        // What is unpacked counts against one limit for the whole document
+        let data = std::fs::read(&epub).unwrap();
+        let mut zip = ZipMembers::new(&data).unwrap();
+        zip.remaining = "<html><body><p>Call me Ishmael.</p></body></html>".len() as u64 + 4;
+        assert!(zip.read("OEBPS/text/one.xhtml").is_ok());
+        assert!(zip.read("OEBPS/text/chapter 2.xhtml").is_err());
+        assert!(zip.exhausted());
+        assert!(zip.read("mimetype").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Maximum Hamming distance between SimHashes for two texts to count as
+/// near-duplicates. Must stay below `SIMHASH_BANDS` for banding to find every pair.
+pub const NEAR_DUPLICATE_DISTANCE: u32 = 3;
+
+/// Number of words per shingle fed into SimHash
+const SHINGLE_WORDS: usize = 3;
+/// The 64-bit SimHash is split into this many bands for candidate lookup
+const SIMHASH_BANDS: u32 = 4;
+
+/// SHA-256 of the file's bytes, hex encoded
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open {} for hashing", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// SHA-256 of in-memory bytes, hex encoded like `hash_file`
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 64-bit SimHash of the word shingles of `text`, `None` for texts without words
+/// Reference: Charikar, "Similarity Estimation Techniques from Rounding Algorithms" (STOC 2002)
+pub fn simhash(text: &str) -> Option<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut weights = [0i64; 64];
+    for shingle in words.windows(SHINGLE_WORDS.min(words.len())) {
+        let hash = fnv1a(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    let mut fingerprint = 0u64;
+    for (bit, weight) in weights.iter().enumerate() {
+        if *weight > 0 {
+            fingerprint |= 1 << bit;
+        }
+    }
+    Some(fingerprint)
+}
+
+/// FNV-1a over the words of a shingle; stable across runs and platforms,
+/// unlike `std`'s randomly seeded hasher
+fn fnv1a(words: &[String]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    for word in words {
+        for byte in word.bytes().chain(std::iter::once(b' ')) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Group indices of `hashes` whose SimHashes are within `max_distance` of each
+/// other (transitively). Only groups with at least two members are returned.
+/// Candidates are found by banding: two hashes within distance < SIMHASH_BANDS
+/// agree on at least one band, so only those buckets are compared pairwise.
+pub fn group_near_duplicates(hashes: &[u64], max_distance: u32) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+    let band_bits = 64 / SIMHASH_BANDS;
+
+    for band in 0..SIMHASH_BANDS {
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (idx, hash) in hashes.iter().enumerate() {
+            let key = (hash >> (band * band_bits)) & ((1u64 << band_bits) - 1);
+            buckets.entry(key).or_default().push(idx);
+        }
+
+        for bucket in buckets.values().filter(|b| b.len() > 1) {
+            for (i, &a) in bucket.iter().enumerate() {
+                for &b in &bucket[i + 1..] {
+                    if hamming_distance(hashes[a], hashes[b]) <= max_distance {
+                        union(&mut parent, a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for idx in 0..hashes.len() {
+        let root = find(&mut parent, idx);
+        groups.entry(root).or_default().push(idx);
+    }
+
+    let mut groups: Vec<Vec<usize>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    groups.sort_by_key(|g| g[0]);
+    groups
+}
+
+fn find(parent: &mut [usize], mut idx: usize) -> usize {
+    while parent[idx] != idx {
+        parent[idx] = parent[parent[idx]];
+        idx = parent[idx];
+    }
+    idx
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (root_a, root_b) = (find(parent, a), find(parent, b));
+    if root_a != root_b {
+        parent[root_a.max(root_b)] = root_a.min(root_b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_file() {
+        let path = std::env::temp_dir().join(format!("fingerprint_{}.bin", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            hash_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_simhash_similar_texts_are_close() {
+        let base = "the quick brown fox jumps over the lazy dog near the river bank at dawn \
+                    while the farmer watches from the old wooden porch of the house";
+        let edited = base.replace("dawn", "dusk");
+        let unrelated = "quarterly revenue grew by twelve percent driven by strong demand \
+                         in the enterprise segment and lower logistics costs overall";
+
+        let a = simhash(base).unwrap();
+        let b = simhash(&edited).unwrap();
+        let c = simhash(unrelated).unwrap();
+
+        assert_eq!(simhash(base), simhash(&base.to_uppercase()));
+        assert!(hamming_distance(a, b) < hamming_distance(a, c));
+        assert_eq!(simhash("  ... "), None);
+    }
+
+    #[test]
+    fn test_group_near_duplicates() {
+        let hashes = [
+            0b1111_0000,
+            0b1111_0001, // 1 bit from the first
+            0xFFFF_FFFF_0000_0000,
+            0b1111_0011, // 1 bit from the second
+        ];
+        let groups = group_near_duplicates(&hashes, NEAR_DUPLICATE_DISTANCE);
+        assert_eq!(groups, vec![vec![0, 1, 3]]);
+    }
+}
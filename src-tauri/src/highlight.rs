@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+
+/// Marker inserted by FTS5 `highlight()` before each matched token
+/// Control characters are used so that no PDF text can forge a highlight
+pub const MATCH_START: char = '\u{2}';
+/// Marker inserted by FTS5 `highlight()` after each matched token
+pub const MATCH_END: char = '\u{3}';
+
+/// Number of characters of context shown on each side of a match
+pub const DEFAULT_CONTEXT_CHARS: usize = 120;
+/// Maximum number of snippets returned per search result
+pub const DEFAULT_MAX_SNIPPETS: usize = 3;
+
+const ELLIPSIS: &str = "...";
+
+/// A highlighted range inside a piece of text
+/// Offsets are in Unicode scalar values (chars), end exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighlightRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A plain-text excerpt with the ranges that matched the query
+/// The UI is responsible for escaping `text` and rendering the highlights
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub text: String,
+    pub highlights: Vec<HighlightRange>,
+}
+
+/// Strip FTS5 highlight markers from `marked`, returning the plain text and
+/// the char ranges that were enclosed by the markers.
+/// Unbalanced markers are tolerated: a stray end marker is dropped and an
+/// unterminated start marker is closed at the end of the text.
+pub fn parse_marked_text(marked: &str) -> (String, Vec<HighlightRange>) {
+    let mut text = String::with_capacity(marked.len());
+    let mut ranges = Vec::new();
+    let mut open: Option<usize> = None;
+    let mut char_count = 0;
+
+    for c in marked.chars() {
+        match c {
+            MATCH_START => {
+                if open.is_none() {
+                    open = Some(char_count);
+                }
+            }
+            MATCH_END => {
+                if let Some(start) = open.take() {
+                    if char_count > start {
+                        ranges.push(HighlightRange { start, end: char_count });
+                    }
+                }
+            }
+            _ => {
+                text.push(c);
+                char_count += 1;
+            }
+        }
+    }
+
+    if let Some(start) = open {
+        if char_count > start {
+            ranges.push(HighlightRange { start, end: char_count });
+        }
+    }
+
+    (text, ranges)
+}
+
+/// Add an ellipsis to each end of a fragment at which text was cut off,
+/// moving the matches along
+pub fn mark_cut_ends(
+    text: &str,
+    matches: &[HighlightRange],
+    cut_before: bool,
+    cut_after: bool,
+) -> (String, Vec<HighlightRange>) {
+    let prefix = if cut_before { ELLIPSIS } else { "" };
+    let suffix = if cut_after { ELLIPSIS } else { "" };
+    let shift = prefix.chars().count();
+
+    let matches = matches
+        .iter()
+        .map(|m| HighlightRange { start: m.start + shift, end: m.end + shift })
+        .collect();
+    (format!("{}{}{}", prefix, text, suffix), matches)
+}
+
+/// Cut `text` into excerpts around the given matches
+/// Matches close to each other are merged into a single excerpt. When there are
+/// more excerpts than `max_snippets`, the ones containing the most matches win;
+/// the returned snippets are always in document order.
+pub fn build_snippets(
+    text: &str,
+    matches: &[HighlightRange],
+    context_chars: usize,
+    max_snippets: usize,
+) -> Vec<Snippet> {
+    if matches.is_empty() || max_snippets == 0 {
+        return Vec::new();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let windows = group_into_windows(&chars, matches, context_chars);
+
+    let mut ranked: Vec<(usize, &Window)> = windows.iter().enumerate().collect();
+    ranked.sort_by(|a, b| b.1.matches.len().cmp(&a.1.matches.len()).then(a.0.cmp(&b.0)));
+    ranked.truncate(max_snippets);
+    ranked.sort_by_key(|(idx, _)| *idx);
+
+    ranked
+        .into_iter()
+        .map(|(_, window)| window_to_snippet(&chars, window))
+        .collect()
+}
+
+/// Build one excerpt per match, for listing every hit in a document
+/// The output has exactly one snippet per entry of `matches`, which must lie within `text`
+pub fn build_match_contexts(text: &str, matches: &[HighlightRange], context_chars: usize) -> Vec<Snippet> {
+    let chars: Vec<char> = text.chars().collect();
+
+    matches
+        .iter()
+        .map(|m| {
+            let (start, end) = expand_to_word_boundaries(&chars, m.start, m.end, context_chars);
+            window_to_snippet(&chars, &Window { start, end, matches: vec![*m] })
+        })
+        .collect()
+}
+
+/// Plain excerpt of at most about `max_chars` chars, cut at a word boundary
+pub fn excerpt(text: &str, max_chars: usize) -> Snippet {
+    let chars: Vec<char> = text.chars().collect();
+    let (_, end) = expand_to_word_boundaries(&chars, 0, 0, max_chars);
+    window_to_snippet(&chars, &Window { start: 0, end, matches: Vec::new() })
+}
+
+struct Window {
+    start: usize,
+    end: usize,
+    matches: Vec<HighlightRange>,
+}
+
+fn group_into_windows(chars: &[char], matches: &[HighlightRange], context_chars: usize) -> Vec<Window> {
+    let mut windows: Vec<Window> = Vec::new();
+
+    for m in matches {
+        if m.end > chars.len() || m.start >= m.end {
+            continue;
+        }
+
+        if let Some(last) = windows.last_mut() {
+            // Merge when the next match falls inside the current excerpt
+            if m.start < last.end {
+                let (_, end) = expand_to_word_boundaries(chars, m.start, m.end, context_chars);
+                last.end = last.end.max(end);
+                last.matches.push(*m);
+                continue;
+            }
+        }
+
+        let (start, end) = expand_to_word_boundaries(chars, m.start, m.end, context_chars);
+        windows.push(Window { start, end, matches: vec![*m] });
+    }
+
+    windows
+}
+
+/// Widen `[start, end)` by up to `context_chars` on each side without cutting words
+fn expand_to_word_boundaries(chars: &[char], start: usize, end: usize, context_chars: usize) -> (usize, usize) {
+    let mut window_start = start.saturating_sub(context_chars);
+    if window_start > 0 {
+        while window_start < start && !chars[window_start - 1].is_whitespace() {
+            window_start += 1;
+        }
+    }
+
+    let mut window_end = (end + context_chars).min(chars.len());
+    if window_end < chars.len() {
+        while window_end > end && !chars[window_end].is_whitespace() {
+            window_end -= 1;
+        }
+    }
+
+    (window_start, window_end)
+}
+
+fn window_to_snippet(chars: &[char], window: &Window) -> Snippet {
+    let mut text = String::new();
+    let mut offset = window.start;
+    let mut prefix_len = 0;
+
+    if window.start > 0 {
+        text.push_str(ELLIPSIS);
+        prefix_len = ELLIPSIS.chars().count();
+        // Skip the whitespace separating the ellipsis from the first word
+        let first_match = window.matches.first().map_or(window.end, |m| m.start);
+        while offset < window.end && chars[offset].is_whitespace() && offset < first_match {
+            offset += 1;
+        }
+    }
+
+    text.extend(&chars[offset..window.end]);
+
+    if window.end < chars.len() {
+        text.push_str(ELLIPSIS);
+    }
+
+    let highlights = window
+        .matches
+        .iter()
+        .map(|m| HighlightRange {
+            start: m.start - offset + prefix_len,
+            end: m.end.min(window.end) - offset + prefix_len,
+        })
+        .collect();
+
+    Snippet { text, highlights }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marked(s: &str) -> String {
+        s.replace('[', &MATCH_START.to_string()).replace(']', &MATCH_END.to_string())
+    }
+
+    fn highlighted(snippet: &Snippet) -> Vec<String> {
+        let chars: Vec<char> = snippet.text.chars().collect();
+        snippet
+            .highlights
+            .iter()
+            .map(|r| chars[r.start..r.end].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_mark_cut_ends() {
+        let (text, ranges) = parse_marked_text(&marked("the [quick] fox"));
+        let (text, ranges) = mark_cut_ends(&text, &ranges, true, true);
+        assert_eq!(text, "...the quick fox...");
+        assert_eq!(ranges, vec![HighlightRange { start: 7, end: 12 }]);
+
+        let (text, _) = mark_cut_ends("the quick fox", &[], false, true);
+        assert_eq!(text, "the quick fox...");
+    }
+
+    #[test]
+    fn test_parse_marked_text() {
+        let (text, ranges) = parse_marked_text(&marked("the [quick] fox [jumps]"));
+        assert_eq!(text, "the quick fox jumps");
+        assert_eq!(ranges, vec![
+            HighlightRange { start: 4, end: 9 },
+            HighlightRange { start: 14, end: 19 },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_marked_text_counts_chars_not_bytes() {
+        let (text, ranges) = parse_marked_text(&marked("café [naïve]"));
+        assert_eq!(text, "café naïve");
+        assert_eq!(ranges, vec![HighlightRange { start: 5, end: 10 }]);
+    }
+
+    #[test]
+    fn test_parse_marked_text_unbalanced() {
+        let (text, ranges) = parse_marked_text(&marked("a] b [c"));
+        assert_eq!(text, "a b c");
+        assert_eq!(ranges, vec![HighlightRange { start: 4, end: 5 }]);
+    }
+
+    #[test]
+    fn test_html_is_not_interpreted() {
+        let (text, ranges) = parse_marked_text(&marked("<script>[alert]</script>"));
+        assert_eq!(text, "<script>alert</script>");
+        assert_eq!(ranges, vec![HighlightRange { start: 8, end: 13 }]);
+    }
+
+    #[test]
+    fn test_build_snippets_merges_nearby_matches() {
+        let (text, ranges) = parse_marked_text(&marked("one [two] three [four] five"));
+        let snippets = build_snippets(&text, &ranges, 20, 3);
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].text, "one two three four five");
+        assert_eq!(highlighted(&snippets[0]), vec!["two", "four"]);
+    }
+
+    #[test]
+    fn test_build_snippets_adds_ellipses() {
+        let filler = "lorem ipsum dolor sit amet ".repeat(10);
+        let doc = format!("{}[needle] {}", filler, filler);
+        let (text, ranges) = parse_marked_text(&marked(&doc));
+        let snippets = build_snippets(&text, &ranges, 15, 3);
+
+        assert_eq!(snippets.len(), 1);
+        assert!(snippets[0].text.starts_with("..."));
+        assert!(snippets[0].text.ends_with("..."));
+        assert_eq!(highlighted(&snippets[0]), vec!["needle"]);
+    }
+
+    #[test]
+    fn test_build_snippets_prefers_dense_windows() {
+        let filler = "x ".repeat(200);
+        let doc = format!("[a] {}[b] [b] [b] {}[c]", filler, filler);
+        let (text, ranges) = parse_marked_text(&marked(&doc));
+        let snippets = build_snippets(&text, &ranges, 10, 1);
+
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(highlighted(&snippets[0]), vec!["b", "b", "b"]);
+    }
+
+    #[test]
+    fn test_build_match_contexts_one_per_match() {
+        let (text, ranges) = parse_marked_text(&marked("[a] b [a] c [a]"));
+        let contexts = build_match_contexts(&text, &ranges, 2);
+        assert_eq!(contexts.len(), 3);
+        for context in &contexts {
+            assert_eq!(highlighted(context), vec!["a"]);
+        }
+    }
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("short text", 100).text, "short text");
+
+        let snippet = excerpt("alpha beta gamma delta", 12);
+        assert_eq!(snippet.text, "alpha beta...");
+        assert!(snippet.highlights.is_empty());
+    }
+}
@@ -1,15 +1,37 @@
-use crate::database::{Database, PdfDocument};
+use crate::archive;
+use crate::attachments::{self, Attachment, AttachmentKind};
+use crate::database::{ArchiveRecord, Database, ExtractionStatus, FolderSettings, IndexError, PdfDocument};
+use crate::entities;
+use crate::extract_worker::{self, ExtractOutcome, WorkerPool};
+use crate::extractors::{Extractor, ExtractorRegistry};
+use crate::fingerprint;
+use crate::keyring::Keyring;
+use crate::links::{self, Link};
+use crate::path_filter::PathFilter;
+use crate::ocr::{self, OcrEngine, OcrPage, ScratchDir};
+use crate::outline::OutlineEntry;
+use crate::pdf_text::{Annotation, ExtractOptions, FormField};
+use crate::semantic::EmbeddingModel;
+use crate::tables::Table;
+use crate::text_cleanup;
+use crate::thumbnails::ThumbnailCache;
 use anyhow::{Context, Result};
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
+/// Version of text extraction stored with each document. Raising it makes
+/// the next index extract unchanged files again once, so they get what
+/// extraction learned since, e.g. cleaned-up text, tables or links.
+pub const EXTRACTOR_VERSION: i64 = 1;
+
 /// Configuration for PDF indexing with resource limits
 /// Reference: "Systems Performance" Ch. 6 - CPU Performance
+#[derive(Debug, Clone)]
 pub struct IndexConfig {
     /// Maximum file size to process (bytes). Files larger are skipped.
     pub max_file_size: u64,
@@ -17,6 +39,39 @@ pub struct IndexConfig {
     pub min_file_size: u64,
     /// Maximum number of parallel threads (0 = use all cores)
     pub max_threads: usize,
+    /// Subfolder levels to descend into (None = unlimited, 0 = only the folder itself)
+    pub max_depth: Option<usize>,
+    /// Follow symbolic links while scanning
+    pub follow_symlinks: bool,
+    /// Directories outside the folder that followed symlinks may lead into
+    pub allowed_roots: Vec<PathBuf>,
+    /// Don't descend into directories on another filesystem or mount
+    pub same_file_system: bool,
+    /// Globs a file must match one of to be indexed; empty means all files
+    pub include_patterns: Vec<String>,
+    /// Globs of files and directories to skip, in gitignore syntax
+    pub exclude_patterns: Vec<String>,
+    /// Index PDFs inside ZIP, 7z and tar archives
+    pub index_archives: bool,
+    /// Index PDFs and text files embedded in PDFs as documents of their own
+    pub index_attachments: bool,
+    /// Index text, Markdown, HTML, EPUB, DOCX and ODT files next to PDFs,
+    /// except those in hidden directories
+    pub index_other_formats: bool,
+    /// Reconstruct tables in PDFs from the position of their words
+    pub extract_tables: bool,
+    /// Run OCR on PDFs without a text layer when an OCR engine is available
+    pub enable_ocr: bool,
+    /// Tesseract languages OCR recognizes, e.g. `eng+deu`
+    pub ocr_language: String,
+    /// Wall-clock limit for extracting one file in a worker process (seconds)
+    pub extraction_timeout_secs: u64,
+    /// Address-space limit of each worker process (bytes, Unix only)
+    pub worker_memory_limit: u64,
+    /// Commit extracted documents once this many are pending
+    pub max_batch_docs: usize,
+    /// Commit extracted documents once their text reaches this size (bytes)
+    pub max_batch_bytes: usize,
 }
 
 impl Default for IndexConfig {
@@ -25,13 +80,66 @@ impl Default for IndexConfig {
             max_file_size: 100 * 1024 * 1024, // 100 MB
             min_file_size: 100,                 // 100 bytes
             max_threads: 0,                     // Use all available cores
+            max_depth: None,
+            follow_symlinks: false,
+            allowed_roots: Vec::new(),
+            same_file_system: false,
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            index_archives: true,
+            index_attachments: true,
+            index_other_formats: false,
+            extract_tables: true,
+            enable_ocr: true,
+            ocr_language: ocr::DEFAULT_OCR_LANGUAGE.to_string(),
+            extraction_timeout_secs: 60,
+            worker_memory_limit: 2 * 1024 * 1024 * 1024, // 2 GB
+            max_batch_docs: 200,
+            max_batch_bytes: 64 * 1024 * 1024,          // 64 MB
         }
     }
 }
 
+impl IndexConfig {
+    /// This config with the values a folder overrides
+    pub fn for_folder(&self, settings: &FolderSettings) -> IndexConfig {
+        IndexConfig {
+            max_file_size: settings.max_file_size.unwrap_or(self.max_file_size),
+            min_file_size: settings.min_file_size.unwrap_or(self.min_file_size),
+            max_threads: settings.max_threads.unwrap_or(self.max_threads),
+            max_depth: settings.max_depth.or(self.max_depth),
+            follow_symlinks: settings.follow_symlinks.unwrap_or(self.follow_symlinks),
+            allowed_roots: settings.allowed_roots.iter().map(PathBuf::from).collect(),
+            same_file_system: settings.same_file_system.unwrap_or(self.same_file_system),
+            include_patterns: settings.include_patterns.clone(),
+            exclude_patterns: settings.exclude_patterns.clone(),
+            index_archives: settings.index_archives.unwrap_or(self.index_archives),
+            index_other_formats: settings.index_other_formats.unwrap_or(self.index_other_formats),
+            enable_ocr: settings.enable_ocr.unwrap_or(self.enable_ocr),
+            ocr_language: settings.ocr_language.clone().unwrap_or_else(|| self.ocr_language.clone()),
+            ..self.clone()
+        }
+    }
+}
+
+/// What retrying failed files came to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RetryOutcome {
+    /// Files that index without errors now
+    pub recovered: usize,
+    /// Files that no longer exist, whose errors were dropped
+    pub removed: usize,
+}
+
 pub struct PdfIndexer {
     db: Database,
     config: IndexConfig,
+    embedding_model: Option<Arc<EmbeddingModel>>,
+    ocr_engine: Option<Arc<dyn OcrEngine>>,
+    worker_pool: Option<Arc<WorkerPool>>,
+    keyring: Option<Arc<Keyring>>,
+    thumbnails: Option<Arc<ThumbnailCache>>,
+    extractors: Arc<ExtractorRegistry>,
 }
 
 impl PdfIndexer {
@@ -51,108 +159,137 @@ impl PdfIndexer {
             }
         }
         
-        PdfIndexer { db, config }
+        PdfIndexer {
+            db,
+            config,
+            embedding_model: None,
+            ocr_engine: None,
+            worker_pool: None,
+            keyring: None,
+            thumbnails: None,
+            extractors: Arc::new(ExtractorRegistry::default()),
+        }
+    }
+
+    /// Recognize scanned, image-only PDFs with this OCR engine
+    pub fn with_ocr_engine(mut self, engine: Option<Arc<dyn OcrEngine>>) -> Self {
+        self.ocr_engine = engine;
+        self
+    }
+
+    /// Extract text in worker processes running `executable` (normally this
+    /// app, see `extract_worker::WORKER_ARG`) instead of in-process
+    pub fn with_worker_executable(mut self, executable: Option<PathBuf>) -> Self {
+        self.worker_pool = executable.map(|executable| {
+            Arc::new(WorkerPool::for_executable(
+                executable,
+                Duration::from_secs(self.config.extraction_timeout_secs),
+                self.config.worker_memory_limit,
+            ))
+        });
+        self
+    }
+
+    /// Open password-protected PDFs with the passwords in this keyring
+    pub fn with_keyring(mut self, keyring: Option<Arc<Keyring>>) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
+    /// Render a thumbnail of the first page of each PDF into this cache
+    pub fn with_thumbnail_cache(mut self, thumbnails: Option<Arc<ThumbnailCache>>) -> Self {
+        self.thumbnails = thumbnails;
+        self
+    }
+
+    /// Compute chunk embeddings for semantic search while indexing
+    pub fn with_embedding_model(mut self, model: Option<Arc<EmbeddingModel>>) -> Self {
+        self.embedding_model = model;
+        self
     }
 
     /// Index a folder with improved performance and reliability
     /// - Uses parallel processing for PDF extraction
     /// - Implements incremental indexing (only processes changed files)
-    /// - Batches database operations for better I/O performance
+    /// - Skips files excluded by the folder's globs or `.pdffinderignore` files
+    /// - Streams documents to the database in bounded batches for flat memory use
     /// - Provides detailed performance metrics
     /// Reference: "Systems Performance" Ch. 2 - Methodology (USE Method)
     pub fn index_folder(&self, folder_path: &str) -> Result<usize> {
         let start_time = Instant::now();
         log::info!("Starting indexing for folder: {}", folder_path);
+        let config = self.folder_config(folder_path)?;
 
-        // Collect all PDF files to process
+        // Collect all PDFs and other supported documents to process
         let collect_start = Instant::now();
-        let pdf_files = self.collect_pdf_files(folder_path)?;
+        let CollectedFiles { documents: document_files, archives } = self.collect_files(folder_path, &config)?;
         let collect_duration = collect_start.elapsed();
-        log::info!("Found {} PDF files in {:?}", pdf_files.len(), collect_duration);
+        log::info!("Found {} documents and {} archives in {:?}", document_files.len(), archives.len(), collect_duration);
 
-        if pdf_files.is_empty() {
+        if document_files.is_empty() && archives.is_empty() {
+            // Forgets the documents of archives that were removed
+            self.index_archives(folder_path, &archives, &config)?;
             self.db.add_indexed_folder(folder_path)?;
             return Ok(0);
         }
 
-        // Get existing files from database for incremental indexing
+        // Get existing files from database for incremental indexing; members
+        // of archives are tracked through their archive instead
         let db_query_start = Instant::now();
-        let existing_files = self.db.get_files_in_folder(folder_path)?;
+        let mut existing_files = self.db.get_files_in_folder(folder_path)?;
+        existing_files.retain(|path, _| !archive::is_member_path(path));
         let db_query_duration = db_query_start.elapsed();
         log::debug!("Database query took {:?}", db_query_duration);
         
         // Determine which files need processing
         let filter_start = Instant::now();
-        let files_to_process = self.filter_files_to_process(&pdf_files, &existing_files)?;
+        let outdated = self.db.get_outdated_files(folder_path, EXTRACTOR_VERSION)?;
+        let mut files_to_process = self.filter_files_to_process(&document_files, &existing_files, &outdated)?;
         let filter_duration = filter_start.elapsed();
         log::info!("Processing {} files (skipping {} unchanged) - filtering took {:?}", 
                    files_to_process.len(), 
-                   pdf_files.len() - files_to_process.len(),
+                   document_files.len() - files_to_process.len(),
                    filter_duration);
 
+        // Re-attach moved or renamed files to their existing rows before the
+        // vanished paths are cleaned up, so their text is not extracted again
+        let moves_start = Instant::now();
+        let moved = self.apply_moved_files(folder_path, &mut files_to_process, &document_files, &existing_files)?;
+        if moved > 0 {
+            log::info!("Detected {} moved or renamed files in {:?}", moved, moves_start.elapsed());
+        }
+
         // Remove files that no longer exist
         let cleanup_start = Instant::now();
-        self.remove_deleted_files(folder_path, &pdf_files, &existing_files)?;
+        self.remove_deleted_files(folder_path, &document_files, &existing_files)?;
         let cleanup_duration = cleanup_start.elapsed();
         log::debug!("Cleanup took {:?}", cleanup_duration);
 
+        let archive_count = self.index_archives(folder_path, &archives, &config)?;
+
         if files_to_process.is_empty() {
+            self.analyze_missing_documents(folder_path)?;
+            self.embed_missing_documents(folder_path)?;
             self.db.add_indexed_folder(folder_path)?;
             log::info!("No files to process. Total time: {:?}", start_time.elapsed());
-            return Ok(0);
+            return Ok(archive_count);
         }
 
-        // Process PDFs in parallel using Rayon
-        let extract_start = Instant::now();
-        let processed_docs = Arc::new(Mutex::new(Vec::new()));
-        let errors = Arc::new(Mutex::new(Vec::new()));
+        // Persist the queue first so an interrupted run can be resumed
+        let queue: Vec<String> = files_to_process
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        self.db.start_index_job(folder_path, &queue)?;
 
-        files_to_process.par_iter().for_each(|path| {
-            match self.extract_pdf_data(path, folder_path) {
-                Ok(doc) => {
-                    processed_docs.lock().unwrap().push(doc);
-                }
-                Err(e) => {
-                    log::warn!("Failed to process {}: {}", path.display(), e);
-                    errors.lock().unwrap().push((path.clone(), e.to_string()));
-                }
-            }
-        });
-        
-        let extract_duration = extract_start.elapsed();
-        let docs = processed_docs.lock().unwrap();
-        let count = docs.len();
-        
-        // Calculate and log performance metrics
-        if count > 0 {
-            let throughput = count as f64 / extract_duration.as_secs_f64();
-            log::info!("Extracted {} documents in {:?} ({:.2} docs/sec)", 
-                       count, extract_duration, throughput);
-        }
+        let count = self.process_files(folder_path, &files_to_process)? + archive_count;
 
-        // Batch insert all documents in a single transaction
-        if count > 0 {
-            let insert_start = Instant::now();
-            log::info!("Inserting {} documents into database", count);
-            self.db.batch_insert_pdfs(&docs, folder_path)?;
-            let insert_duration = insert_start.elapsed();
-            log::info!("Database insertion took {:?}", insert_duration);
-        }
+        self.analyze_missing_documents(folder_path)?;
+        self.embed_missing_documents(folder_path)?;
 
         // Update folder timestamp
         self.db.add_indexed_folder(folder_path)?;
-
-        // Log any errors
-        let error_list = errors.lock().unwrap();
-        if !error_list.is_empty() {
-            log::warn!("Completed with {} errors", error_list.len());
-            for (path, error) in error_list.iter().take(5) {
-                log::debug!("  Error in {}: {}", path.display(), error);
-            }
-            if error_list.len() > 5 {
-                log::debug!("  ... and {} more errors", error_list.len() - 5);
-            }
-        }
+        self.db.finish_index_job(folder_path)?;
 
         let total_duration = start_time.elapsed();
         log::info!("Indexing complete: {} documents processed in {:?}", count, total_duration);
@@ -166,41 +303,444 @@ impl PdfIndexer {
         Ok(count)
     }
 
-    /// Collect all PDF files in the folder recursively
-    fn collect_pdf_files(&self, folder_path: &str) -> Result<Vec<PathBuf>> {
-        let mut pdf_files = Vec::new();
+    /// Extract `files` in parallel and stream them to a single database writer
+    /// that commits in batches, so memory stays flat however many files there
+    /// are and a crash only loses the batch in flight
+    /// Returns the number of documents written to the index
+    fn process_files(&self, folder_path: &str, files: &[PathBuf]) -> Result<usize> {
+        let config = self.folder_config(folder_path)?;
+        in_thread_pool(&config, || self.process_files_with(folder_path, files, &config))
+    }
+
+    fn process_files_with(&self, folder_path: &str, files: &[PathBuf], config: &IndexConfig) -> Result<usize> {
+        let start = Instant::now();
+
+        let (written, errors) = self.write_streamed(folder_path, config, |sender| {
+            // Process PDFs in parallel using Rayon; stops early once the
+            // writer has failed and dropped its end of the channel
+            let _ = files.par_iter().try_for_each_with(sender, |sender, path| {
+                let extracted = match self.extract_pdf_data(path, folder_path, config) {
+                    Ok(processed) => Extracted::Document(Box::new(processed)),
+                    Err(e) => {
+                        log::warn!("Failed to process {}: {}", path.display(), e);
+                        Extracted::Failed(IndexError::new(
+                            &path.to_string_lossy(),
+                            folder_path,
+                            PHASE_READ,
+                            "io",
+                            format!("{:#}", e),
+                        ))
+                    }
+                };
+                sender.send(extracted).map_err(|_| ())
+            });
+        })?;
+
+        // Calculate and log performance metrics
+        if written > 0 {
+            let duration = start.elapsed();
+            let throughput = written as f64 / duration.as_secs_f64();
+            log::info!("Extracted and stored {} documents in {:?} ({:.2} docs/sec)",
+                       written, duration, throughput);
+        }
+        if errors > 0 {
+            log::warn!("Completed with {} errors", errors);
+        }
+
+        Ok(written)
+    }
+
+    /// Run `produce` while a writer thread commits everything it sends in
+    /// batches; returns the documents written and errors recorded
+    fn write_streamed<F>(&self, folder_path: &str, config: &IndexConfig, produce: F) -> Result<(usize, usize)>
+    where
+        F: FnOnce(mpsc::SyncSender<Extracted>),
+    {
+        // Bounded, so extractors wait instead of piling up text when the writer falls behind
+        let (sender, receiver) = mpsc::sync_channel::<Extracted>(rayon::current_num_threads() * 2);
+
+        std::thread::scope(|scope| {
+            let writer = scope.spawn(|| {
+                let mut batch = BatchWriter::new(&self.db, folder_path, config);
+                for extracted in receiver {
+                    batch.push(extracted)?;
+                }
+                batch.finish()
+            });
+
+            produce(sender);
+
+            writer
+                .join()
+                .map_err(|_| anyhow::anyhow!("Database writer panicked"))?
+        })
+    }
+
+    /// Index the PDFs inside new or changed archives and forget archives that
+    /// are gone. Returns the number of documents written.
+    fn index_archives(&self, folder_path: &str, archives: &[PathBuf], config: &IndexConfig) -> Result<usize> {
+        let known = self.db.get_archives_in_folder(folder_path)?;
+
+        let current: HashSet<String> = archives.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        for path in known.keys().filter(|path| !current.contains(*path)) {
+            log::info!("Removing documents of vanished archive {}", path);
+            self.db.remove_archive(path)?;
+        }
+
+        // The archive's own mtime and size stand for all of its members, and
+        // a member extracted by an older version has the whole archive read
+        let outdated: HashSet<PathBuf> = self
+            .db
+            .get_outdated_files(folder_path, EXTRACTOR_VERSION)?
+            .iter()
+            .filter_map(|path| archive::split_member_path(path).map(|(archive, _)| archive))
+            .collect();
+        let changed: Vec<PathBuf> = archives
+            .iter()
+            .filter(|path| {
+                let stamp = file_stamp(path).ok();
+                stamp.is_none() || known.get(path.to_string_lossy().as_ref()) != stamp.as_ref() || outdated.contains(*path)
+            })
+            .cloned()
+            .collect();
+        if changed.is_empty() {
+            return Ok(0);
+        }
+
+        in_thread_pool(config, || self.process_archives(folder_path, &changed, config))
+    }
+
+    /// Extract the PDFs inside `archives` and stream them to the database like
+    /// regular files, each archive followed by its record
+    fn process_archives(&self, folder_path: &str, archives: &[PathBuf], config: &IndexConfig) -> Result<usize> {
+        let start = Instant::now();
+
+        let (written, errors) = self.write_streamed(folder_path, config, |sender| {
+            let _ = archives.par_iter().try_for_each_with(sender, |sender, path| {
+                self.extract_archive(path, folder_path, config, sender)
+            });
+        })?;
+
+        log::info!("Indexed {} documents from {} archives in {:?}", written, archives.len(), start.elapsed());
+        if errors > 0 {
+            log::warn!("Completed archives with {} errors", errors);
+        }
+        Ok(written)
+    }
+
+    /// Send the documents of one archive's PDFs, then the archive record.
+    /// Fails only when the writer is gone.
+    fn extract_archive(
+        &self,
+        path: &Path,
+        folder_path: &str,
+        config: &IndexConfig,
+        sender: &mut mpsc::SyncSender<Extracted>,
+    ) -> std::result::Result<(), ()> {
+        let path_str = path.to_string_lossy().to_string();
+        let failed = |kind: &str, e: anyhow::Error| {
+            log::warn!("Failed to read archive {}: {:#}", path.display(), e);
+            Extracted::Failed(IndexError::new(&path_str, folder_path, PHASE_READ, kind, format!("{:#}", e)))
+        };
+
+        let (modified, size) = match file_stamp(path) {
+            Ok(stamp) => stamp,
+            Err(e) => return sender.send(failed("io", e)).map_err(|_| ()),
+        };
+        let scratch = match ScratchDir::new() {
+            Ok(scratch) => scratch,
+            Err(e) => return sender.send(failed("io", e)).map_err(|_| ()),
+        };
+        let members = match archive::extract_pdf_members(path, scratch.path(), config.max_file_size) {
+            Ok(members) => members,
+            Err(e) => return sender.send(failed("archive", e)).map_err(|_| ()),
+        };
+
+        let member_paths: Vec<String> = members
+            .iter()
+            .map(|member| archive::member_path(path, &member.name))
+            .collect();
+
+        members
+            .par_iter()
+            .zip(member_paths.par_iter())
+            .try_for_each_with(sender.clone(), |sender, (member, member_path)| {
+                let extracted = match self.extract_with_attachments(&member.file, member_path, folder_path, config) {
+                    Ok(mut processed) => {
+                        processed.doc.modified = modified;
+                        Extracted::Document(Box::new(processed))
+                    }
+                    Err(e) => Extracted::Failed(IndexError::new(
+                        member_path,
+                        folder_path,
+                        PHASE_READ,
+                        "io",
+                        format!("{:#}", e),
+                    )),
+                };
+                sender.send(extracted).map_err(|_| ())
+            })?;
+
+        sender
+            .send(Extracted::Archive(ArchiveRecord {
+                path: path_str,
+                folder_path: folder_path.to_string(),
+                modified,
+                size,
+                members: member_paths,
+            }))
+            .map_err(|_| ())
+    }
+
+    /// Finish an indexing job interrupted by a crash or shutdown, extracting
+    /// only the queued files that were not committed before it stopped
+    pub fn resume_job(&self, folder_path: &str) -> Result<usize> {
+        let queue = self
+            .db
+            .get_index_job_queue(folder_path)?
+            .with_context(|| format!("No interrupted indexing job for {}", folder_path))?;
+
+        // Files deleted since the job started are simply dropped
+        let files: Vec<PathBuf> = queue
+            .into_iter()
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .collect();
+        log::info!("Resuming indexing of {} with {} files left", folder_path, files.len());
+
+        let count = self.process_files(folder_path, &files)?;
+        self.analyze_missing_documents(folder_path)?;
+        self.embed_missing_documents(folder_path)?;
+        self.db.add_indexed_folder(folder_path)?;
+        self.db.finish_index_job(folder_path)?;
+
+        Ok(count)
+    }
+
+    /// Index files that failed in earlier runs again, e.g. after an update of
+    /// the PDF library or once a locked file is readable. Files that no longer
+    /// exist are forgotten, and counted apart from those that recovered.
+    pub fn retry_failed(&self, folder_path: Option<&str>) -> Result<RetryOutcome> {
+        let failed = self.db.get_index_errors(folder_path)?;
+        if failed.is_empty() {
+            return Ok(RetryOutcome::default());
+        }
+        log::info!("Retrying {} files that failed to index", failed.len());
+
+        let mut by_folder: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        let mut archives_by_folder: HashMap<String, HashSet<PathBuf>> = HashMap::new();
+        let mut retried = Vec::new();
+        let mut removed = 0;
+        for error in &failed {
+            // Attachments are retried by extracting the PDF they came from
+            let error_path = self.db.get_parent_path(&error.path)?.unwrap_or_else(|| error.path.clone());
+            // Failures inside an archive, or of the archive itself, are
+            // retried by reading the whole archive again
+            let archive = match archive::split_member_path(&error_path) {
+                Some((archive, _)) => Some(archive),
+                None if archive::is_archive(Path::new(&error_path)) => Some(PathBuf::from(&error_path)),
+                None => None,
+            };
+            let path = archive.clone().unwrap_or_else(|| PathBuf::from(&error_path));
+            if !path.is_file() {
+                self.db.clear_index_error(&error.path)?;
+                removed += 1;
+                continue;
+            }
+            retried.push(error.path.as_str());
+            if archive.is_some() {
+                archives_by_folder.entry(error.folder_path.clone()).or_default().insert(path);
+            } else {
+                by_folder.entry(error.folder_path.clone()).or_default().push(path);
+            }
+        }
+
+        for (folder, files) in &by_folder {
+            self.process_files(folder, files)?;
+        }
+        for (folder, archives) in &archives_by_folder {
+            let config = self.folder_config(folder)?;
+            let archives: Vec<PathBuf> = archives.iter().cloned().collect();
+            in_thread_pool(&config, || self.process_archives(folder, &archives, &config))?;
+        }
+        let folders: HashSet<&String> = by_folder.keys().chain(archives_by_folder.keys()).collect();
+        for folder in folders {
+            self.analyze_missing_documents(folder)?;
+            self.embed_missing_documents(folder)?;
+        }
+
+        let remaining: HashSet<String> =
+            self.db.get_index_errors(folder_path)?.into_iter().map(|error| error.path).collect();
+        let recovered = retried.iter().filter(|path| !remaining.contains(**path)).count();
+        Ok(RetryOutcome { recovered, removed })
+    }
+
+    /// The indexer's config with the folder's own settings applied
+    fn folder_config(&self, folder_path: &str) -> Result<IndexConfig> {
+        let settings = self.db.get_folder_settings(folder_path)?;
+        Ok(self.config.for_folder(&settings))
+    }
+
+    /// Find links and entities in the text of documents indexed before they
+    /// were extracted, reusing their stored text like `embed_missing_documents`
+    fn analyze_missing_documents(&self, folder_path: &str) -> Result<()> {
+        let pending = self.db.get_documents_without_analysis(folder_path)?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let analyze_start = Instant::now();
+        let analyzed: Vec<_> = pending
+            .par_iter()
+            .map(|(pdf_id, content, page_offsets)| {
+                let links = links::document_links(Vec::new(), content, page_offsets);
+                (*pdf_id, links, entities::extract_entities(content))
+            })
+            .collect();
+
+        for (pdf_id, links, entities) in analyzed {
+            self.db.set_document_analysis(pdf_id, &links, &entities)?;
+        }
+        log::info!("Found links and entities in {} previously indexed documents in {:?}",
+                   pending.len(), analyze_start.elapsed());
+
+        Ok(())
+    }
+
+    /// Embed documents indexed before the embedding model was installed,
+    /// reusing their stored text instead of extracting it again
+    fn embed_missing_documents(&self, folder_path: &str) -> Result<()> {
+        let model = match &self.embedding_model {
+            Some(model) => model,
+            None => return Ok(()),
+        };
+
+        let pending = self.db.get_documents_without_embeddings(folder_path, model.fingerprint(), model.dim())?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let embed_start = Instant::now();
+        let embedded: Vec<_> = pending
+            .par_iter()
+            .map(|(pdf_id, content)| (*pdf_id, model.embed_document(content)))
+            .collect();
+
+        for (pdf_id, chunks) in embedded {
+            self.db.insert_chunk_embeddings(pdf_id, model.fingerprint(), &chunks)?;
+        }
+        log::info!("Embedded {} previously indexed documents in {:?}", pending.len(), embed_start.elapsed());
+
+        Ok(())
+    }
+
+    /// Collect all PDF files and archives in the folder recursively
+    /// Symlinks are followed only when the folder asks for it, and never lead
+    /// outside the folder and its allowed roots. Loops are detected and
+    /// skipped, and a file reachable through several links is listed once.
+    fn collect_files(&self, folder_path: &str, config: &IndexConfig) -> Result<CollectedFiles> {
+        let mut collected = CollectedFiles::default();
         let root_path = std::path::PathBuf::from(folder_path);
         let canonical_root = root_path.canonicalize()
             .context("Failed to canonicalize root path")?;
 
-        for entry in WalkDir::new(folder_path)
-            .follow_links(false)  // Don't follow symlinks to prevent traversal attacks
+        let mut allowed_roots = vec![canonical_root];
+        for root in &config.allowed_roots {
+            match root.canonicalize() {
+                Ok(root) => allowed_roots.push(root),
+                Err(e) => log::warn!("Ignoring allowed root {}: {}", root.display(), e),
+            }
+        }
+        let is_allowed = |path: &Path| allowed_roots.iter().any(|root| path.starts_with(root));
+
+        let filter = PathFilter::new(&root_path, &config.include_patterns, &config.exclude_patterns);
+
+        let mut walker = WalkDir::new(folder_path)
+            .follow_links(config.follow_symlinks)
+            .same_file_system(config.same_file_system);
+        if let Some(depth) = config.max_depth {
+            // WalkDir counts the files directly in the folder as depth 1
+            walker = walker.max_depth(depth + 1);
+        }
+
+        let mut seen = HashSet::new();
+        for entry in walker
             .into_iter()
-            .filter_map(|e| e.ok())
+            .filter_entry(|e| {
+                if e.depth() == 0 {
+                    return true;
+                }
+                // Don't descend through links that lead outside the allowed roots
+                if e.path_is_symlink() {
+                    match e.path().canonicalize() {
+                        Ok(target) if is_allowed(&target) => {}
+                        Ok(target) => {
+                            log::warn!("Skipping symlink {} to {} outside the allowed roots",
+                                       e.path().display(), target.display());
+                            return false;
+                        }
+                        Err(_) => return false,
+                    }
+                }
+                // Pruning excluded directories here skips their contents entirely
+                !filter.is_ignored(e.path(), e.file_type().is_dir())
+            })
         {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    match e.loop_ancestor() {
+                        Some(ancestor) => log::warn!("Skipping symlink loop at {} back to {}",
+                                                     e.path().unwrap_or(Path::new("")).display(), ancestor.display()),
+                        None => log::debug!("Skipping unreadable entry: {}", e),
+                    }
+                    continue;
+                }
+            };
             let path = entry.path();
-            
-            // Ensure the path is still within the root folder (defense in depth)
-            if let Ok(canonical_path) = path.canonicalize() {
-                if !canonical_path.starts_with(&canonical_root) {
+
+            // Ensure the path is still within the allowed roots (defense in depth)
+            let canonical_path = path.canonicalize().ok();
+            if let Some(canonical_path) = &canonical_path {
+                if !is_allowed(canonical_path) {
                     log::warn!("Skipping path outside root: {}", path.display());
                     continue;
                 }
             }
-            
-            if path.is_file() && is_pdf_file(path) {
-                pdf_files.push(path.to_path_buf());
+
+            let is_pdf = is_pdf_file(path);
+            let is_archive = !is_pdf && config.index_archives && archive::is_archive(path);
+            let is_document = is_pdf
+                || (!is_archive
+                    && config.index_other_formats
+                    && !in_hidden_directory(path, &root_path)
+                    && path.is_file()
+                    && self.extractors.for_path(path).is_some());
+            if (is_document || is_archive) && path.is_file() && filter.is_included(path) {
+                if let Some(canonical_path) = canonical_path {
+                    if !seen.insert(canonical_path) {
+                        log::debug!("Skipping {}, already found through another link", path.display());
+                        continue;
+                    }
+                }
+                if is_document {
+                    collected.documents.push(path.to_path_buf());
+                } else {
+                    collected.archives.push(path.to_path_buf());
+                }
             }
         }
 
-        Ok(pdf_files)
+        Ok(collected)
     }
 
-    /// Filter files to only process new or modified files (incremental indexing)
+    /// Filter files to only process new or modified files (incremental indexing),
+    /// and those extracted by an older version, listed in `outdated`
     fn filter_files_to_process(
         &self,
         all_files: &[PathBuf],
         existing_files: &HashMap<String, (i64, i64)>,
+        outdated: &HashSet<String>,
     ) -> Result<Vec<PathBuf>> {
         let mut files_to_process = Vec::new();
 
@@ -220,7 +760,7 @@ impl PdfIndexer {
             let needs_processing = match existing_files.get(&path_str) {
                 Some((existing_modified, existing_size)) => {
                     // Process if size or modification time changed
-                    *existing_modified != modified || *existing_size != size
+                    *existing_modified != modified || *existing_size != size || outdated.contains(&path_str)
                 }
                 None => true, // New file
             };
@@ -233,6 +773,86 @@ impl PdfIndexer {
         Ok(files_to_process)
     }
 
+    /// Match new paths against vanished ones by content fingerprint (size, then
+    /// SHA-256 of the bytes) and move the matching rows to their new paths.
+    /// Matched files are removed from `files_to_process`; returns how many moved.
+    fn apply_moved_files(
+        &self,
+        folder_path: &str,
+        files_to_process: &mut Vec<PathBuf>,
+        current_files: &[PathBuf],
+        existing_files: &HashMap<String, (i64, i64)>,
+    ) -> Result<usize> {
+        let current_paths: std::collections::HashSet<String> = current_files
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+
+        // Vanished paths, bucketed by size so only plausible candidates get hashed
+        let mut vanished_by_size: HashMap<i64, Vec<String>> = HashMap::new();
+        for (path, (_, size)) in existing_files {
+            if !current_paths.contains(path) {
+                vanished_by_size.entry(*size).or_default().push(path.clone());
+            }
+        }
+        if vanished_by_size.is_empty() {
+            return Ok(0);
+        }
+
+        let mut vanished_hashes: HashMap<String, Option<String>> = HashMap::new();
+        let mut moved_paths = std::collections::HashSet::new();
+
+        for new_path in files_to_process.iter() {
+            let new_path_str = new_path.to_string_lossy().to_string();
+            if existing_files.contains_key(&new_path_str) {
+                continue; // Modified in place, not a move
+            }
+
+            let metadata = match fs::metadata(new_path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let candidates = match vanished_by_size.get_mut(&(metadata.len() as i64)) {
+                Some(candidates) if !candidates.is_empty() => candidates,
+                _ => continue,
+            };
+
+            let new_hash = match fingerprint::hash_file(new_path) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    log::debug!("Could not hash {}: {}", new_path.display(), e);
+                    continue;
+                }
+            };
+
+            let mut matched = None;
+            for (idx, old_path) in candidates.iter().enumerate() {
+                if !vanished_hashes.contains_key(old_path) {
+                    vanished_hashes.insert(old_path.clone(), self.db.get_content_hash(old_path)?);
+                }
+                if vanished_hashes[old_path].as_deref() == Some(new_hash.as_str()) {
+                    matched = Some(idx);
+                    break;
+                }
+            }
+
+            if let Some(idx) = matched {
+                let old_path = candidates.swap_remove(idx);
+                let modified = metadata
+                    .modified()?
+                    .duration_since(std::time::UNIX_EPOCH)?
+                    .as_secs() as i64;
+
+                self.db.move_pdf(&old_path, &new_path_str, &title_for_path(new_path), modified, folder_path)?;
+                log::debug!("Moved {} -> {}", old_path, new_path_str);
+                moved_paths.insert(new_path.clone());
+            }
+        }
+
+        files_to_process.retain(|p| !moved_paths.contains(p));
+        Ok(moved_paths.len())
+    }
+
     /// Remove files from database that no longer exist on disk
     fn remove_deleted_files(
         &self,
@@ -264,7 +884,119 @@ impl PdfIndexer {
     }
 
     /// Extract data from a single PDF (used in parallel processing)
-    fn extract_pdf_data(&self, path: &Path, folder_path: &str) -> Result<PdfDocument> {
+    fn extract_pdf_data(&self, path: &Path, folder_path: &str, config: &IndexConfig) -> Result<ProcessedFile> {
+        self.extract_with_attachments(path, &path.to_string_lossy(), folder_path, config)
+    }
+
+    /// Extract a PDF along with the files embedded in it, which become
+    /// documents of their own linked to it
+    fn extract_with_attachments(
+        &self,
+        path: &Path,
+        index_path: &str,
+        folder_path: &str,
+        config: &IndexConfig,
+    ) -> Result<ProcessedFile> {
+        self.extract_document(path, index_path, folder_path, config, config.index_attachments)
+    }
+
+    /// Documents for the indexable `found` files embedded in `parent`, read
+    /// along with its text. Attachments that cannot be indexed are skipped,
+    /// never failing the PDF.
+    fn attachment_documents(
+        &self,
+        found: Vec<Attachment>,
+        password_protected: bool,
+        parent: &PdfDocument,
+        folder_path: &str,
+        config: &IndexConfig,
+    ) -> Vec<ProcessedFile> {
+        let mut names = HashSet::new();
+        let mut children = Vec::new();
+        for attachment in found {
+            let Some(kind) = attachment.kind() else {
+                continue;
+            };
+            if !names.insert(attachment.name.clone()) {
+                log::debug!("Skipping second attachment named {} in {}", attachment.name, parent.path);
+                continue;
+            }
+
+            let child_path = attachments::attachment_path(&parent.path, &attachment.name);
+            let child = match kind {
+                AttachmentKind::Text => Ok(self.text_attachment(&attachment, &child_path, parent)),
+                // Extracting a PDF means writing it to a temporary file, and
+                // decrypted content must never be stored outside the index
+                AttachmentKind::Pdf if password_protected => {
+                    log::debug!("Skipping {}: its PDF is password protected", child_path);
+                    continue;
+                }
+                AttachmentKind::Pdf => self.nested_pdf(&attachment, &child_path, folder_path, config),
+            };
+            match child {
+                Ok(mut child) => {
+                    child.doc.modified = parent.modified;
+                    child.doc.parent_path = Some(parent.path.clone());
+                    children.push(child);
+                }
+                Err(e) => log::warn!("Failed to index attachment {}: {:#}", child_path, e),
+            }
+        }
+
+        if !children.is_empty() {
+            log::debug!("Indexed {} attachments of {}", children.len(), parent.path);
+        }
+        children
+    }
+
+    /// Extract a PDF embedded in another one through a temporary copy
+    fn nested_pdf(&self, attachment: &Attachment, index_path: &str, folder_path: &str, config: &IndexConfig) -> Result<ProcessedFile> {
+        let scratch = ScratchDir::new()?;
+        let file = scratch.path().join("attachment.pdf");
+        fs::write(&file, &attachment.data)
+            .with_context(|| format!("Failed to write {}", file.display()))?;
+        self.extract_document(&file, index_path, folder_path, config, false)
+    }
+
+    fn text_attachment(&self, attachment: &Attachment, index_path: &str, parent: &PdfDocument) -> ProcessedFile {
+        let content = normalize_text(&attachment.text());
+        let chunks = self
+            .embedding_model
+            .as_ref()
+            .map(|model| model.embed_document(&content))
+            .unwrap_or_default();
+
+        let doc = PdfDocument {
+            path: index_path.to_string(),
+            title: title_for_path(Path::new(index_path)),
+            size: attachment.data.len() as i64,
+            modified: parent.modified,
+            content_hash: Some(fingerprint::hash_bytes(&attachment.data)),
+            simhash: fingerprint::simhash(&content).map(|h| h as i64),
+            chunks,
+            embedding_model: self.embedding_model.as_ref().map(|model| model.fingerprint().to_string()),
+            extraction_status: if content.is_empty() { ExtractionStatus::Empty } else { ExtractionStatus::Ok },
+            links: links::document_links(Vec::new(), &content, &[]),
+            entities: entities::extract_entities(&content),
+            content,
+            extractor_version: EXTRACTOR_VERSION,
+            ..Default::default()
+        };
+        ProcessedFile { doc, error: None, attachments: Vec::new() }
+    }
+
+    /// Extract the PDF or other document at `path` into a document indexed
+    /// as `index_path`, which differs for PDFs copied out of an archive.
+    /// With `with_attachments`, the files embedded in a PDF are read by the
+    /// same extraction and indexed too.
+    fn extract_document(
+        &self,
+        path: &Path,
+        index_path: &str,
+        folder_path: &str,
+        config: &IndexConfig,
+        with_attachments: bool,
+    ) -> Result<ProcessedFile> {
         let metadata = fs::metadata(path)
             .context(format!("Failed to read metadata for {}", path.display()))?;
         let size = metadata.len() as i64;
@@ -273,25 +1005,317 @@ impl PdfIndexer {
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as i64;
 
-        let title = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("Untitled")
-            .to_string();
+        let title = title_for_path(Path::new(index_path));
+        let path_str = index_path.to_string();
+        let mut ocr_error = None;
 
         // Extract text from PDF with improved error handling
-        let (content, pages) = extract_text_from_pdf(path, &self.config)?;
+        let passwords = self
+            .keyring
+            .as_ref()
+            .map(|keyring| keyring.passwords_for(&path_str))
+            .unwrap_or_default();
+        let extractor = if is_pdf_file(path) { None } else { self.extractors.for_path(path) };
+        let mut extracted = match &extractor {
+            Some(extractor) => extract_text_with(extractor.as_ref(), path, config)?,
+            None => extract_text_from_pdf(path, config, self.worker_pool.as_deref(), &passwords, with_attachments)?,
+        };
+        // Only ever present when the extraction succeeded
+        let embedded = std::mem::take(&mut extracted.attachments);
+        let password_protected = extracted.password_protected;
+
+        // No text layer: this is likely a scan, so fall back to OCR. Encrypted
+        // files are left alone, as OCR would write their pages to temp files.
+        if extracted.content.is_empty()
+            && extractor.is_none()
+            && extracted.status != ExtractionStatus::Encrypted
+            && config.enable_ocr
+            && is_within_size_limits(size as u64, config)
+        {
+            if let Some(engine) = &self.ocr_engine {
+                let ocr_start = Instant::now();
+                let timeout = Duration::from_secs(config.extraction_timeout_secs);
+                match engine.recognize_pdf(path, &config.ocr_language, timeout) {
+                    Ok(ocr_pages) => {
+                        let page_texts: Vec<String> = ocr_pages.iter().map(|p| p.text.clone()).collect();
+                        let (content, page_offsets) = join_pages(&page_texts);
+                        log::info!("OCR ({}) recognized {} pages of {} in {:?}",
+                                   engine.name(), ocr_pages.len(), path_str, ocr_start.elapsed());
+                        let status = if content.is_empty() { extracted.status } else { ExtractionStatus::Ok };
+                        extracted = ExtractedText {
+                            content,
+                            pages: ocr_pages.len().min(i32::MAX as usize) as i32,
+                            page_offsets,
+                            ocr_pages,
+                            annotations: std::mem::take(&mut extracted.annotations),
+                            form_fields: std::mem::take(&mut extracted.form_fields),
+                            outline: std::mem::take(&mut extracted.outline),
+                            tables: Vec::new(),
+                            links: std::mem::take(&mut extracted.links),
+                            attachments: Vec::new(),
+                            password_protected,
+                            status,
+                            error: None,
+                        };
+                    }
+                    Err(e) => {
+                        log::warn!("OCR failed for {}: {}", path_str, e);
+                        ocr_error = Some(IndexError::new(&path_str, folder_path, PHASE_OCR, "ocr_failed", format!("{:#}", e)));
+                    }
+                }
+            }
+        }
+
+        // Decided only now, as OCR may have recovered the text
+        let error = if extracted.status.is_failure() {
+            Some(IndexError::new(
+                &path_str,
+                folder_path,
+                PHASE_EXTRACT,
+                extracted.status.as_str(),
+                extracted.error.take().unwrap_or_default(),
+            ))
+        } else {
+            ocr_error
+        };
 
-        Ok(PdfDocument {
+        let content_hash = match fingerprint::hash_file(path) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                log::warn!("Failed to hash {}: {}", path_str, e);
+                None
+            }
+        };
+        // A preview failing to render does not affect the indexed text
+        if let (Some(thumbnails), Some(hash), None) = (&self.thumbnails, &content_hash, &extractor) {
+            if !extracted.status.is_failure() {
+                let timeout = Duration::from_secs(config.extraction_timeout_secs);
+                if let Err(e) = thumbnails.get_or_render(path, hash, 1, timeout) {
+                    log::debug!("No thumbnail for {}: {:#}", path_str, e);
+                }
+            }
+        }
+
+        let simhash = fingerprint::simhash(&extracted.content).map(|h| h as i64);
+        let links = links::document_links(std::mem::take(&mut extracted.links), &extracted.content, &extracted.page_offsets);
+        let entities = entities::extract_entities(&extracted.content);
+        let chunks = self
+            .embedding_model
+            .as_ref()
+            .map(|model| model.embed_document(&extracted.content))
+            .unwrap_or_default();
+
+        let doc = PdfDocument {
             id: None,
-            path: path.to_string_lossy().to_string(),
+            path: path_str,
             title,
-            content,
+            content: extracted.content,
             size,
             modified,
-            pages: Some(pages),
-        })
+            // Only PDFs have pages
+            pages: extractor.is_none().then_some(extracted.pages),
+            page_offsets: extracted.page_offsets,
+            chunks,
+            embedding_model: self.embedding_model.as_ref().map(|model| model.fingerprint().to_string()),
+            content_hash,
+            simhash,
+            ocr: !extracted.ocr_pages.is_empty(),
+            ocr_pages: extracted.ocr_pages,
+            annotations: extracted.annotations,
+            form_fields: extracted.form_fields,
+            outline: extracted.outline,
+            tables: extracted.tables,
+            links,
+            entities,
+            extraction_status: extracted.status,
+            parent_path: None,
+            extractor_version: EXTRACTOR_VERSION,
+        };
+
+        let mut processed = ProcessedFile { doc, error, attachments: Vec::new() };
+        if !embedded.is_empty() {
+            processed.attachments = self.attachment_documents(embedded, password_protected, &processed.doc, folder_path, config);
+        }
+        Ok(processed)
+    }
+}
+
+/// Indexing steps recorded with each failure
+const PHASE_READ: &str = "read";
+const PHASE_EXTRACT: &str = "extract";
+const PHASE_OCR: &str = "ocr";
+
+/// A document ready to be stored, with the failure hit while producing it
+struct ProcessedFile {
+    doc: PdfDocument,
+    error: Option<IndexError>,
+    /// Files embedded in the document, written right after it
+    attachments: Vec<ProcessedFile>,
+}
+
+/// Output of an extraction thread, consumed by the database writer
+enum Extracted {
+    Document(Box<ProcessedFile>),
+    /// The file could not be read at all
+    Failed(IndexError),
+    /// All documents of an archive were sent
+    Archive(ArchiveRecord),
+}
+
+/// Files found in a folder
+#[derive(Debug, Default)]
+struct CollectedFiles {
+    /// PDFs and documents in the other supported formats
+    documents: Vec<PathBuf>,
+    archives: Vec<PathBuf>,
+}
+
+/// Accumulates extracted documents and commits them in batches bounded by
+/// document count and text size
+/// Reference: "Managing Gigabytes" Ch. 5 - Index Construction
+struct BatchWriter<'a> {
+    db: &'a Database,
+    folder_path: &'a str,
+    max_docs: usize,
+    max_bytes: usize,
+    docs: Vec<PdfDocument>,
+    errors: Vec<IndexError>,
+    archives: Vec<ArchiveRecord>,
+    bytes: usize,
+    written: usize,
+    error_count: usize,
+}
+
+impl<'a> BatchWriter<'a> {
+    fn new(db: &'a Database, folder_path: &'a str, config: &IndexConfig) -> Self {
+        BatchWriter {
+            db,
+            folder_path,
+            max_docs: config.max_batch_docs.max(1),
+            max_bytes: config.max_batch_bytes,
+            docs: Vec::new(),
+            errors: Vec::new(),
+            archives: Vec::new(),
+            bytes: 0,
+            written: 0,
+            error_count: 0,
+        }
+    }
+
+    fn push(&mut self, extracted: Extracted) -> Result<()> {
+        match extracted {
+            Extracted::Document(processed) => {
+                let ProcessedFile { doc, error, attachments } = *processed;
+                self.bytes += document_bytes(&doc);
+                self.errors.extend(error);
+                self.docs.push(doc);
+                for attachment in attachments {
+                    self.bytes += document_bytes(&attachment.doc);
+                    self.errors.extend(attachment.error);
+                    self.docs.push(attachment.doc);
+                }
+            }
+            Extracted::Failed(error) => self.errors.push(error),
+            Extracted::Archive(record) => self.archives.push(record),
+        }
+
+        if self.docs.len() >= self.max_docs || self.bytes >= self.max_bytes {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.docs.is_empty() {
+            let insert_start = Instant::now();
+            self.db.batch_insert_pdfs(&self.docs, self.folder_path)?;
+            log::debug!("Committed {} documents ({} bytes) in {:?}",
+                        self.docs.len(), self.bytes, insert_start.elapsed());
+            self.written += self.docs.len();
+        }
+
+        // Recorded after the insert, which clears errors of cleanly extracted files
+        if !self.errors.is_empty() {
+            self.db.record_index_errors(&self.errors)?;
+            self.error_count += self.errors.len();
+        }
+
+        // Last, so an archive counts as indexed only once its members are
+        for record in &self.archives {
+            self.db.record_archive(record)?;
+        }
+
+        self.docs.clear();
+        self.errors.clear();
+        self.archives.clear();
+        self.bytes = 0;
+        Ok(())
+    }
+
+    /// Commit what is left; returns the documents written and errors recorded
+    fn finish(mut self) -> Result<(usize, usize)> {
+        self.flush()?;
+        Ok((self.written, self.error_count))
+    }
+}
+
+/// Run `f` on a thread pool of the configured size, or on the current one
+fn in_thread_pool<T, F>(config: &IndexConfig, f: F) -> Result<T>
+where
+    T: Send,
+    F: FnOnce() -> Result<T> + Send,
+{
+    if config.max_threads > 0 && config.max_threads != rayon::current_num_threads() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.max_threads)
+            .build()
+            .context("Failed to create thread pool")?;
+        return pool.install(f);
     }
+    f()
+}
+
+/// Modification time (seconds) and size of a file
+fn file_stamp(path: &Path) -> Result<(i64, i64)> {
+    let metadata = fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata for {}", path.display()))?;
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    Ok((modified, metadata.len() as i64))
+}
+
+/// Approximate memory held by a document waiting to be written
+fn document_bytes(doc: &PdfDocument) -> usize {
+    doc.content.len()
+        + doc.ocr_pages.iter().map(|p| p.text.len()).sum::<usize>()
+        + doc.chunks.iter().map(|c| c.vector.len() * 4).sum::<usize>()
+        + doc.annotations.iter().map(|a| a.contents.len()).sum::<usize>()
+        + doc.form_fields.iter().map(|f| f.name.len() + f.value.len()).sum::<usize>()
+        + doc.outline.iter().map(|e| e.title.len()).sum::<usize>()
+        + doc.tables.iter().flat_map(|t| t.rows.iter().flatten()).map(String::len).sum::<usize>()
+        + doc.links.iter().map(|l| l.url.len()).sum::<usize>()
+        + doc.entities.iter().map(|e| e.value.len()).sum::<usize>()
+}
+
+fn is_within_size_limits(size: u64, config: &IndexConfig) -> bool {
+    size >= config.min_file_size && size <= config.max_file_size
+}
+
+/// Whether a directory between `root` and `path` is hidden, like `.git`
+fn in_hidden_directory(path: &Path, root: &Path) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .parent()
+        .is_some_and(|dir| dir.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')))
+}
+
+fn title_for_path(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Untitled")
+        .to_string()
 }
 
 fn is_pdf_file(path: &Path) -> bool {
@@ -301,10 +1325,46 @@ fn is_pdf_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
+/// Normalized text of a document along with its page layout
+#[derive(Debug, Default)]
+struct ExtractedText {
+    content: String,
+    pages: i32,
+    /// Char offset in `content` at which each page starts
+    page_offsets: Vec<i64>,
+    /// Pages recognized by OCR, when the PDF had no text layer
+    ocr_pages: Vec<OcrPage>,
+    annotations: Vec<Annotation>,
+    form_fields: Vec<FormField>,
+    outline: Vec<OutlineEntry>,
+    tables: Vec<Table>,
+    /// Targets of link annotations; URLs in the text are added later
+    links: Vec<Link>,
+    /// Files embedded in the PDF, when asked for
+    attachments: Vec<Attachment>,
+    /// The PDF was opened with a password from the keyring
+    password_protected: bool,
+    status: ExtractionStatus,
+    /// Why extraction failed, for failed and panicked files
+    error: Option<String>,
+}
+
+impl ExtractedText {
+    fn without_text(status: ExtractionStatus, error: Option<String>) -> Self {
+        ExtractedText { status, error, ..Default::default() }
+    }
+}
+
 /// Extract text from PDF with improved error handling and validation
 /// Reference: "PDF Explained" Ch. 9 - Text Extraction
 /// Reference: "Systems Performance" Ch. 8 - File Systems (I/O optimization)
-fn extract_text_from_pdf(path: &Path, config: &IndexConfig) -> Result<(String, i32)> {
+fn extract_text_from_pdf(
+    path: &Path,
+    config: &IndexConfig,
+    worker_pool: Option<&WorkerPool>,
+    passwords: &[String],
+    with_attachments: bool,
+) -> Result<ExtractedText> {
     // Validate file before processing
     if !path.exists() {
         anyhow::bail!("File does not exist: {}", path.display());
@@ -317,54 +1377,143 @@ fn extract_text_from_pdf(path: &Path, config: &IndexConfig) -> Result<(String, i
     if size < config.min_file_size {
         log::warn!("File too small (< {} bytes), likely corrupt: {}", 
                    config.min_file_size, path.display());
-        return Ok((String::new(), 0));
+        return Ok(ExtractedText::without_text(ExtractionStatus::TooSmall, None));
     }
     
     if size > config.max_file_size {
         log::warn!("File too large (> {} bytes), skipping: {}", 
                    config.max_file_size, path.display());
-        return Ok((String::new(), 0));
+        return Ok(ExtractedText::without_text(ExtractionStatus::TooLarge, None));
     }
 
-    // Try to extract text using pdf-extract, catching panics
+    // Extract text page by page, in a worker process when isolation is
+    // enabled so hangs and crashes cannot take the app down
     // Reference: "Developing with PDF" Ch. 4 - Text Extraction Best Practices
-    let path_buf = path.to_path_buf();
-    let result = std::panic::catch_unwind(|| {
-        pdf_extract::extract_text(&path_buf)
-    });
+    let options = ExtractOptions {
+        tables: config.extract_tables,
+        attachments: with_attachments.then_some(config.max_file_size),
+    };
+    let outcome = match worker_pool {
+        Some(pool) => pool.extract(path, passwords, options),
+        None => extract_worker::extract_in_process(path, passwords, options),
+    };
 
-    match result {
-        Ok(Ok(text)) => {
+    match outcome {
+        ExtractOutcome::Pages(page_texts) => {
             // Successfully extracted text
-            if text.is_empty() {
+            if page_texts.is_empty() {
                 log::debug!("No text content extracted from {}", path.display());
-                return Ok((String::new(), 0));
+                // Comments, form values, bookmarks and links are kept even without a text layer
+                return Ok(ExtractedText {
+                    annotations: page_texts.annotations,
+                    form_fields: page_texts.form_fields,
+                    outline: page_texts.outline,
+                    links: page_texts.links,
+                    attachments: page_texts.attachments,
+                    password_protected: page_texts.password_protected,
+                    ..ExtractedText::without_text(ExtractionStatus::Empty, None)
+                });
             }
             
-            log::debug!("Extracted {} bytes from {}", text.len(), path.display());
-            
             // Normalize text for better indexing and search
             // Reference: "Introduction to Information Retrieval" Ch. 2 - Text Processing
-            let normalized = normalize_text(&text);
-            let pages = estimate_page_count(&text);
+            let (content, page_offsets) = join_pages(&page_texts.pages);
+            log::debug!("Extracted {} bytes from {}", content.len(), path.display());
             
-            Ok((normalized, pages))
+            Ok(ExtractedText {
+                content,
+                pages: page_texts.pages.len().min(i32::MAX as usize) as i32,
+                page_offsets,
+                ocr_pages: Vec::new(),
+                annotations: page_texts.annotations,
+                form_fields: page_texts.form_fields,
+                outline: page_texts.outline,
+                tables: page_texts.tables,
+                links: page_texts.links,
+                attachments: page_texts.attachments,
+                password_protected: page_texts.password_protected,
+                status: ExtractionStatus::Ok,
+                error: None,
+            })
         }
-        Ok(Err(e)) => {
+        ExtractOutcome::Failed(message) => {
             // Extraction returned an error
-            log::warn!("Could not extract text from {}: {}", path.display(), e);
+            log::warn!("Could not extract text from {}: {}", path.display(), message);
             // Return empty content rather than failing the entire indexing
-            Ok((String::new(), 0))
+            Ok(ExtractedText::without_text(ExtractionStatus::Failed, Some(message)))
         }
-        Err(_) => {
+        ExtractOutcome::Panicked(message) => {
             // Extraction panicked (e.g., unsupported PDF encoding)
             log::warn!("PDF extraction panicked for {} (possibly unsupported encoding or corrupt file)", path.display());
             // Return empty content rather than failing the entire indexing
-            Ok((String::new(), 0))
+            Ok(ExtractedText::without_text(ExtractionStatus::Panicked, Some(message)))
         }
+        ExtractOutcome::Encrypted(message) => {
+            log::info!("Cannot decrypt {}: {}", path.display(), message);
+            Ok(ExtractedText::without_text(ExtractionStatus::Encrypted, Some(message)))
+        }
+        ExtractOutcome::TimedOut(timeout) => Ok(ExtractedText::without_text(
+            ExtractionStatus::TimedOut,
+            Some(format!("Extraction did not finish within {:?}", timeout)),
+        )),
+        ExtractOutcome::Crashed(message) => Ok(ExtractedText::without_text(ExtractionStatus::Crashed, Some(message))),
+    }
+}
+
+/// Extract text from a document in another format than PDF, in this process.
+/// The extractors bound what they unpack from ZIP-based formats, and a panic
+/// fails only this file.
+fn extract_text_with(extractor: &dyn Extractor, path: &Path, config: &IndexConfig) -> Result<ExtractedText> {
+    let size = fs::metadata(path)?.len();
+    if size < config.min_file_size {
+        return Ok(ExtractedText::without_text(ExtractionStatus::TooSmall, None));
+    }
+    if size > config.max_file_size {
+        log::warn!("File too large (> {} bytes), skipping: {}", config.max_file_size, path.display());
+        return Ok(ExtractedText::without_text(ExtractionStatus::TooLarge, None));
+    }
+
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| extractor.extract(&data))) {
+        Ok(Ok(text)) => {
+            let content = normalize_text(&text_cleanup::clean_text(&text));
+            log::debug!("Extracted {} bytes from {} ({})", content.len(), path.display(), extractor.name());
+            let status = if content.is_empty() { ExtractionStatus::Empty } else { ExtractionStatus::Ok };
+            Ok(ExtractedText { content, status, ..Default::default() })
+        }
+        Ok(Err(e)) => {
+            log::warn!("Could not extract text from {}: {:#}", path.display(), e);
+            Ok(ExtractedText::without_text(ExtractionStatus::Failed, Some(format!("{:#}", e))))
+        }
+        Err(panic) => Ok(ExtractedText::without_text(
+            ExtractionStatus::Panicked,
+            Some(extract_worker::panic_message(&panic)),
+        )),
     }
 }
 
+/// Clean and normalize each page and join them into a single document body,
+/// recording the char offset where each page starts
+fn join_pages(pages: &[String]) -> (String, Vec<i64>) {
+    let pages = text_cleanup::clean_pages(pages);
+    let mut content = String::new();
+    let mut page_offsets = Vec::with_capacity(pages.len());
+    let mut char_count = 0usize;
+
+    for page in &pages {
+        let normalized = normalize_text(page);
+        if !content.is_empty() && !normalized.is_empty() {
+            content.push(' ');
+            char_count += 1;
+        }
+        page_offsets.push(char_count as i64);
+        char_count += normalized.chars().count();
+        content.push_str(&normalized);
+    }
+
+    (content, page_offsets)
+}
+
 /// Normalize text for better indexing and search quality
 /// Reference: "Introduction to Information Retrieval" Ch. 2.2 - Normalization
 fn normalize_text(text: &str) -> String {
@@ -379,6 +1528,9 @@ fn normalize_text(text: &str) -> String {
                 result.push(' ');
                 prev_was_space = true;
             }
+        } else if c.is_control() {
+            // Drop stray control characters; they are reserved as highlight markers
+            continue;
         } else {
             result.push(c);
             prev_was_space = false;
@@ -388,47 +1540,10 @@ fn normalize_text(text: &str) -> String {
     result.trim().to_string()
 }
 
-/// Estimate page count from extracted text
-/// Uses multiple heuristics for better accuracy
-/// Reference: "PDF Explained" Ch. 3 - Document Structure
-fn estimate_page_count(text: &str) -> i32 {
-    if text.is_empty() {
-        return 0;
-    }
-    
-    // Strategy 1: Count form feeds which often indicate page breaks
-    let form_feeds = text.chars().filter(|&c| c == '\x0C').count();
-    if form_feeds > 0 {
-        // Protect against overflow by capping at i32::MAX
-        let pages = form_feeds.saturating_add(1);
-        return pages.min(i32::MAX as usize) as i32;
-    }
-    
-    // Strategy 2: Look for common page break patterns
-    // Some PDFs use specific strings like "Page N" or similar markers
-    let newline_sequences = text.matches("\n\n\n").count();
-    if newline_sequences > 5 {
-        // Multiple triple-newlines often indicate page breaks
-        // This is a heuristic and may not be accurate for all PDFs
-        let estimated = (newline_sequences as f64 * 0.8) as usize + 1;
-        let capped = estimated.min(i32::MAX as usize);
-        if capped > 1 {
-            return capped as i32;
-        }
-    }
-    
-    // Strategy 3: Character density heuristic
-    // Average page: ~2000-4000 characters depending on density
-    // Use 3000 as a middle ground with better rounding
-    let chars = text.len();
-    let pages = ((chars as f64 / 3000.0).ceil() as usize).max(1);
-    // Cap at reasonable maximum to prevent overflow
-    pages.min(i32::MAX as usize) as i32
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::database::SearchFilters;
 
     #[test]
     fn test_is_pdf_file() {
@@ -440,23 +1555,419 @@ mod tests {
     }
 
     #[test]
-    fn test_estimate_page_count() {
-        assert_eq!(estimate_page_count(""), 0);
-        assert_eq!(estimate_page_count("a"), 1);
-        assert_eq!(estimate_page_count(&"a".repeat(3000)), 1);
-        assert_eq!(estimate_page_count(&"a".repeat(3001)), 2);
-        assert_eq!(estimate_page_count(&"a".repeat(6000)), 2);
-        
-        // Test form feed detection
-        let text_with_breaks = "page1\x0Cpage2\x0Cpage3";
-        assert_eq!(estimate_page_count(text_with_breaks), 3);
-        
-        // Test triple-newline heuristic
-        let text_with_newlines = "page1\n\n\npage2\n\n\npage3\n\n\npage4\n\n\npage5\n\n\npage6";
-        let count = estimate_page_count(text_with_newlines);
-        assert!(count >= 4 && count <= 6); // Should estimate around 5 pages
+    fn test_collect_skips_excluded_files() {
+        let root = std::env::temp_dir().join(format!("indexer_exclude_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::create_dir_all(root.join("reports/2023/q1")).unwrap();
+        let folder = root.to_string_lossy().to_string();
+        for file in ["a.pdf", "a_draft.pdf", "node_modules/pkg/b.pdf", "reports/c.pdf", "reports/d.pdf", "reports/2023/q1/e.pdf"] {
+            fs::write(root.join(file), b"%PDF-1.4").unwrap();
+        }
+        fs::write(root.join("reports").join(crate::path_filter::IGNORE_FILE_NAME), "d.pdf\n").unwrap();
+
+        let db = Database::new(root.join("index.db")).unwrap();
+        let mut settings = FolderSettings {
+            exclude_patterns: vec!["**/node_modules/**".to_string(), "*_draft.pdf".to_string()],
+            ..Default::default()
+        };
+        db.add_indexed_folder(&folder).unwrap();
+        db.set_folder_settings(&folder, &settings).unwrap();
+
+        let indexer = PdfIndexer::new(db.clone());
+        let config = indexer.folder_config(&folder).unwrap();
+        let mut files = indexer.collect_files(&folder, &config).unwrap().documents;
+        files.sort();
+        assert_eq!(files, vec![root.join("a.pdf"), root.join("reports/2023/q1/e.pdf"), root.join("reports/c.pdf")]);
+
+        // Only one level of subfolders
+        settings.max_depth = Some(1);
+        db.set_folder_settings(&folder, &settings).unwrap();
+        let config = indexer.folder_config(&folder).unwrap();
+        let mut files = indexer.collect_files(&folder, &config).unwrap().documents;
+        files.sort();
+        assert_eq!(files, vec![root.join("a.pdf"), root.join("reports/c.pdf")]);
+
+        fs::remove_dir_all(&root).unwrap();
     }
-    
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_follows_symlinks_within_allowed_roots() {
+        use std::os::unix::fs::symlink;
+
+        let base = std::env::temp_dir().join(format!("indexer_links_{}", uuid::Uuid::new_v4()));
+        let root = base.join("root");
+        let shared = base.join("shared");
+        let private = base.join("private");
+        for dir in [root.join("real"), shared.clone(), private.clone()] {
+            fs::create_dir_all(&dir).unwrap();
+        }
+        fs::write(root.join("real/a.pdf"), b"%PDF-1.4").unwrap();
+        fs::write(shared.join("b.pdf"), b"%PDF-1.4").unwrap();
+        fs::write(private.join("c.pdf"), b"%PDF-1.4").unwrap();
+        symlink(root.join("real"), root.join("alias")).unwrap();
+        symlink(&root, root.join("real/loop")).unwrap();
+        symlink(&shared, root.join("shared")).unwrap();
+        symlink(&private, root.join("private")).unwrap();
+        let folder = root.to_string_lossy().to_string();
+
+        let db = Database::new(base.join("index.db")).unwrap();
+        let indexer = PdfIndexer::new(db.clone());
+
+        db.add_indexed_folder(&folder).unwrap();
+
+        // Not followed by default
+        let config = indexer.folder_config(&folder).unwrap();
+        assert_eq!(indexer.collect_files(&folder, &config).unwrap().documents, vec![root.join("real/a.pdf")]);
+
+        let settings = FolderSettings {
+            follow_symlinks: Some(true),
+            allowed_roots: vec![shared.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        db.set_folder_settings(&folder, &settings).unwrap();
+        let config = indexer.folder_config(&folder).unwrap();
+        let files = indexer.collect_files(&folder, &config).unwrap().documents;
+
+        // The loop ends, a.pdf is found once despite the alias, and only the
+        // allowed link outside the root is followed
+        let names: HashSet<_> = files.iter().map(|p| p.file_name().unwrap().to_owned()).collect();
+        assert_eq!(files.len(), 2, "{:?}", files);
+        assert!(names.contains(std::ffi::OsStr::new("a.pdf")));
+        assert!(names.contains(std::ffi::OsStr::new("b.pdf")));
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_renamed_file_is_moved_not_reextracted() {
+        let root = std::env::temp_dir().join(format!("indexer_move_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        let folder = root.to_string_lossy().to_string();
+
+        let db = Database::new(root.join("index.db")).unwrap();
+        let old_path = root.join("old.pdf");
+        fs::write(&old_path, b"%PDF-1.4 not really a pdf").unwrap();
+
+        let metadata = fs::metadata(&old_path).unwrap();
+        let doc = PdfDocument {
+            path: old_path.to_string_lossy().to_string(),
+            title: "old".to_string(),
+            content: "previously extracted text".to_string(),
+            size: metadata.len() as i64,
+            modified: 0,
+            content_hash: Some(fingerprint::hash_file(&old_path).unwrap()),
+            ..Default::default()
+        };
+        db.insert_pdf(&doc, &folder).unwrap();
+
+        let new_path = root.join("sub").join("renamed.pdf");
+        fs::rename(&old_path, &new_path).unwrap();
+
+        let indexer = PdfIndexer::new(db.clone());
+        let existing = db.get_files_in_folder(&folder).unwrap();
+        let mut to_process = vec![new_path.clone()];
+        let moved = indexer
+            .apply_moved_files(&folder, &mut to_process, std::slice::from_ref(&new_path), &existing)
+            .unwrap();
+
+        assert_eq!(moved, 1);
+        assert!(to_process.is_empty());
+        let files = db.get_files_in_folder(&folder).unwrap();
+        assert!(files.contains_key(&new_path.to_string_lossy().to_string()));
+        assert!(!files.contains_key(&old_path.to_string_lossy().to_string()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_files_from_an_older_extractor_are_extracted_again_once() {
+        use std::io::Write;
+
+        let root = std::env::temp_dir().join(format!("indexer_versions_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let folder = root.to_string_lossy().to_string();
+        let broken = "%PDF-1.4 this is not a valid document ".repeat(10);
+        let report = root.join("report.pdf");
+        fs::write(&report, &broken).unwrap();
+        let bundle = root.join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&bundle).unwrap());
+        zip.start_file("a.pdf", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(broken.as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let db = Database::new(root.join("index.db")).unwrap();
+        let indexer = PdfIndexer::with_config(db.clone(), IndexConfig { enable_ocr: false, ..Default::default() });
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 2);
+        assert!(db.get_outdated_files(&folder, EXTRACTOR_VERSION).unwrap().is_empty());
+
+        // Rows as an older version wrote them, for files that did not change
+        let (modified, size) = file_stamp(&report).unwrap();
+        let member = archive::member_path(&bundle, "a.pdf");
+        for path in [report.to_string_lossy().to_string(), member.clone()] {
+            let doc = PdfDocument { path, size, modified, extractor_version: EXTRACTOR_VERSION - 1, ..Default::default() };
+            db.insert_pdf(&doc, &folder).unwrap();
+        }
+        assert_eq!(db.get_outdated_files(&folder, EXTRACTOR_VERSION).unwrap().len(), 2);
+
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 2);
+        assert!(db.get_outdated_files(&folder, EXTRACTOR_VERSION).unwrap().is_empty());
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 0);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_archive_members_follow_their_archive() {
+        use std::io::Write;
+
+        let root = std::env::temp_dir().join(format!("indexer_archives_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let folder = root.to_string_lossy().to_string();
+        let bundle = root.join("bundle.zip");
+        let write_zip = |names: &[&str]| {
+            let mut zip = zip::ZipWriter::new(fs::File::create(&bundle).unwrap());
+            for name in names {
+                zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+                zip.write_all("%PDF-1.4 this is not a valid document ".repeat(10).as_bytes()).unwrap();
+            }
+            zip.finish().unwrap();
+        };
+        let member = |name: &str| archive::member_path(&bundle, name);
+
+        write_zip(&["specs/a.pdf", "b.pdf"]);
+        let db = Database::new(root.join("index.db")).unwrap();
+        let indexer = PdfIndexer::with_config(db.clone(), IndexConfig { enable_ocr: false, ..Default::default() });
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 2);
+        let files = db.get_files_in_folder(&folder).unwrap();
+        assert!(files.contains_key(&member("specs/a.pdf")));
+        assert!(files.contains_key(&member("b.pdf")));
+
+        // Unchanged archives are not read again
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 0);
+
+        write_zip(&["specs/a.pdf"]);
+        let file = fs::OpenOptions::new().append(true).open(&bundle).unwrap();
+        file.set_modified(std::time::SystemTime::now() + Duration::from_secs(10)).unwrap();
+        indexer.index_folder(&folder).unwrap();
+        let files = db.get_files_in_folder(&folder).unwrap();
+        assert!(files.contains_key(&member("specs/a.pdf")));
+        assert!(!files.contains_key(&member("b.pdf")));
+
+        fs::remove_file(&bundle).unwrap();
+        indexer.index_folder(&folder).unwrap();
+        assert!(db.get_files_in_folder(&folder).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_attachments_are_indexed_as_child_documents() {
+        let root = std::env::temp_dir().join(format!("indexer_attachments_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let folder = root.to_string_lossy().to_string();
+        let report = root.join("report.pdf");
+        attachments::write_pdf_with_attachments(&report);
+        fs::write(root.join("other.pdf"), "%PDF-1.4 this is not a valid document ".repeat(10)).unwrap();
+        let report_str = report.to_string_lossy().to_string();
+
+        let db = Database::new(root.join("index.db")).unwrap();
+        let indexer = PdfIndexer::with_config(db.clone(), IndexConfig { enable_ocr: false, ..Default::default() });
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 4);
+
+        let notes = attachments::attachment_path(&report_str, "notes.txt");
+        let nested = attachments::attachment_path(&report_str, "nested.pdf");
+        assert_eq!(db.get_parent_path(&notes).unwrap(), Some(report_str.clone()));
+        assert_eq!(db.get_parent_path(&nested).unwrap(), Some(report_str.clone()));
+        assert_eq!(db.get_parent_path(&report_str).unwrap(), None);
+
+        let filters = SearchFilters::default();
+        let results = db.search("reconciliation", &filters).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, notes);
+        assert_eq!(results[0].parent_path.as_deref(), Some(report_str.as_str()));
+
+        // Attachments are not mistaken for deleted files, and follow their PDF
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 0);
+        assert!(db.get_parent_path(&notes).unwrap().is_some());
+        fs::remove_file(&report).unwrap();
+        indexer.index_folder(&folder).unwrap();
+        assert!(db.search("reconciliation", &filters).unwrap().is_empty());
+        assert_eq!(db.get_parent_path(&nested).unwrap(), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_other_formats_are_indexed_next_to_pdfs() {
+        let root = std::env::temp_dir().join(format!("indexer_formats_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let folder = root.to_string_lossy().to_string();
+        let padding = " filler".repeat(20);
+        fs::write(root.join("notes.md"), format!("# Grounding\n\nBond the **chassis** first.{}", padding)).unwrap();
+        fs::write(root.join("page.html"), format!("<html><body><p>Torque&nbsp;specs</p>{}</body></html>", padding)).unwrap();
+        fs::write(root.join("readme.txt"), format!("Plain readme about calibration{}", padding)).unwrap();
+        fs::write(root.join("photo.png"), [0x89, b'P', b'N', b'G'].repeat(40)).unwrap();
+        fs::write(root.join("other.pdf"), "%PDF-1.4 this is not a valid document ".repeat(10)).unwrap();
+        // Neither text without an extension nor files in hidden directories
+        fs::write(root.join("LICENSE"), format!("Permission is hereby granted{}", padding)).unwrap();
+        fs::create_dir_all(root.join(".git").join("logs")).unwrap();
+        fs::write(root.join(".git").join("logs").join("HEAD.txt"), format!("commit: calibration{}", padding)).unwrap();
+
+        let db = Database::new(root.join("index.db")).unwrap();
+        let config = IndexConfig { enable_ocr: false, index_other_formats: true, ..Default::default() };
+        let indexer = PdfIndexer::with_config(db.clone(), config.clone());
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 4);
+
+        let filters = SearchFilters::default();
+        let results = db.search("chassis", &filters).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].path.ends_with("notes.md"));
+        assert_eq!(results[0].pages, None);
+        assert_eq!(db.search("torque", &filters).unwrap().len(), 1);
+        assert_eq!(db.search("calibration", &filters).unwrap().len(), 1);
+        assert!(db.search("permission", &filters).unwrap().is_empty());
+
+        // Other formats are opt-in
+        let pdfs_only = IndexConfig { index_other_formats: false, ..config };
+        assert_eq!(indexer.collect_files(&folder, &pdfs_only).unwrap().documents, vec![root.join("other.pdf")]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_failed_extraction_is_recorded_and_retried() {
+        let root = std::env::temp_dir().join(format!("indexer_errors_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let folder = root.to_string_lossy().to_string();
+
+        let broken = root.join("broken.pdf");
+        fs::write(&broken, "%PDF-1.4 this is not a valid document ".repeat(10)).unwrap();
+        let tiny = root.join("tiny.pdf");
+        fs::write(&tiny, b"%PDF").unwrap();
+
+        let db = Database::new(root.join("index.db")).unwrap();
+        let indexer = PdfIndexer::with_config(db.clone(), IndexConfig { enable_ocr: false, ..Default::default() });
+        assert_eq!(indexer.index_folder(&folder).unwrap(), 2);
+
+        let broken_str = broken.to_string_lossy().to_string();
+        let status = db.get_extraction_status(&broken_str).unwrap().unwrap();
+        assert!(status.is_failure());
+        // Skipped files are not failures
+        let tiny_status = db.get_extraction_status(&tiny.to_string_lossy()).unwrap();
+        assert_eq!(tiny_status, Some(ExtractionStatus::TooSmall));
+
+        let errors = db.get_index_errors(Some(&folder)).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, broken_str);
+        assert_eq!(errors[0].phase, PHASE_EXTRACT);
+        assert_eq!(errors[0].attempts, 1);
+
+        assert_eq!(indexer.retry_failed(Some(&folder)).unwrap(), RetryOutcome::default());
+        assert_eq!(db.get_index_errors(None).unwrap()[0].attempts, 2);
+
+        // A fixed file recovers
+        attachments::write_pdf_with_attachments(&broken);
+        assert_eq!(indexer.retry_failed(None).unwrap(), RetryOutcome { recovered: 1, removed: 0 });
+        assert!(db.get_index_errors(None).unwrap().is_empty());
+
+        // Vanished files are forgotten, not counted as recovered
+        let lost = root.join("lost.pdf");
+        fs::write(&lost, "%PDF-1.4 this is not a valid document ".repeat(10)).unwrap();
+        indexer.index_folder(&folder).unwrap();
+        assert_eq!(db.get_index_errors(None).unwrap().len(), 1);
+        fs::remove_file(&lost).unwrap();
+        assert_eq!(indexer.retry_failed(None).unwrap(), RetryOutcome { recovered: 0, removed: 1 });
+        assert!(db.get_index_errors(None).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_batch_writer_commits_in_bounded_batches() {
+        let dir = std::env::temp_dir().join(format!("indexer_batches_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(dir.join("index.db")).unwrap();
+        let config = IndexConfig { max_batch_docs: 2, max_batch_bytes: 1000, ..Default::default() };
+
+        let document = |name: &str, content: &str| {
+            Extracted::Document(Box::new(ProcessedFile {
+                doc: PdfDocument {
+                    path: format!("/test/{}.pdf", name),
+                    title: name.to_string(),
+                    content: content.to_string(),
+                    ..Default::default()
+                },
+                error: None,
+                attachments: Vec::new(),
+            }))
+        };
+
+        let mut batch = BatchWriter::new(&db, "/test", &config);
+        batch.push(document("a", "small")).unwrap();
+        assert_eq!(db.get_count().unwrap(), 0);
+        batch.push(document("b", "small")).unwrap();
+        assert_eq!(db.get_count().unwrap(), 2, "document limit commits the batch");
+
+        batch.push(document("c", &"x".repeat(1000))).unwrap();
+        assert_eq!(db.get_count().unwrap(), 3, "byte limit commits the batch");
+
+        batch.push(document("d", "small")).unwrap();
+        batch.push(Extracted::Failed(IndexError::new("/test/e.pdf", "/test", PHASE_READ, "io", "denied"))).unwrap();
+        assert_eq!(batch.finish().unwrap(), (4, 1));
+        assert_eq!(db.get_count().unwrap(), 4);
+        assert_eq!(db.get_index_errors(None).unwrap().len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_job_resumes_with_uncommitted_files() {
+        let root = std::env::temp_dir().join(format!("indexer_resume_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let folder = root.to_string_lossy().to_string();
+
+        let paths: Vec<PathBuf> = (0..3).map(|i| root.join(format!("doc{}.pdf", i))).collect();
+        for path in &paths {
+            fs::write(path, b"%PDF").unwrap();
+        }
+        let queue: Vec<String> = paths.iter().map(|p| p.to_string_lossy().to_string()).collect();
+
+        let db = Database::new(root.join("index.db")).unwrap();
+        let indexer = PdfIndexer::new(db.clone());
+
+        // Simulate a run that committed one document and then died
+        db.start_index_job(&folder, &queue).unwrap();
+        let committed = indexer.extract_pdf_data(&paths[0], &folder, &indexer.config).unwrap();
+        db.insert_pdf(&committed.doc, &folder).unwrap();
+        fs::remove_file(&paths[2]).unwrap();
+
+        let jobs = db.get_interrupted_jobs().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].total_files, 3);
+        assert_eq!(jobs[0].remaining_files, 2);
+
+        assert_eq!(indexer.resume_job(&folder).unwrap(), 1);
+        assert!(db.get_interrupted_jobs().unwrap().is_empty());
+        assert_eq!(db.get_count().unwrap(), 2);
+        assert!(indexer.resume_job(&folder).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_join_pages() {
+        let pages = vec![
+            "first  page".to_string(),
+            "".to_string(),
+            "third\npage".to_string(),
+        ];
+        let (content, offsets) = join_pages(&pages);
+
+        assert_eq!(content, "first page third page");
+        assert_eq!(offsets, vec![0, 10, 11]);
+    }
+
     #[test]
     fn test_normalize_text() {
         // Test whitespace normalization
@@ -471,6 +1982,9 @@ mod tests {
         // Test empty and whitespace-only strings
         assert_eq!(normalize_text(""), "");
         assert_eq!(normalize_text("   "), "");
+
+        // Test control characters are stripped
+        assert_eq!(normalize_text("a\u{2}b\u{3}c"), "abc");
     }
     
     #[test]
@@ -479,5 +1993,41 @@ mod tests {
         assert_eq!(config.max_file_size, 100 * 1024 * 1024);
         assert_eq!(config.min_file_size, 100);
         assert_eq!(config.max_threads, 0);
+        assert_eq!(config.max_depth, None);
+        assert!(!config.follow_symlinks);
+        assert!(config.allowed_roots.is_empty());
+        assert!(!config.same_file_system);
+        assert!(config.index_archives);
+        assert!(config.index_attachments);
+        assert!(!config.index_other_formats);
+        assert!(config.include_patterns.is_empty() && config.exclude_patterns.is_empty());
+        assert!(config.enable_ocr);
+        assert_eq!(config.ocr_language, "eng");
+        assert_eq!(config.extraction_timeout_secs, 60);
+        assert_eq!(config.worker_memory_limit, 2 * 1024 * 1024 * 1024);
+        assert_eq!(config.max_batch_docs, 200);
+        assert_eq!(config.max_batch_bytes, 64 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_folder_settings_override_config() {
+        let base = IndexConfig::default();
+        let settings = FolderSettings {
+            max_file_size: Some(5 * 1024 * 1024),
+            enable_ocr: Some(false),
+            ocr_language: Some("deu+eng".to_string()),
+            follow_symlinks: Some(true),
+            ..Default::default()
+        };
+
+        let config = base.for_folder(&settings);
+        assert_eq!(config.max_file_size, 5 * 1024 * 1024);
+        assert!(!config.enable_ocr);
+        assert_eq!(config.ocr_language, "deu+eng");
+        assert!(config.follow_symlinks);
+        // Unset values keep the defaults
+        assert_eq!(config.min_file_size, base.min_file_size);
+        assert_eq!(config.max_threads, base.max_threads);
+        assert_eq!(config.extraction_timeout_secs, base.extraction_timeout_secs);
     }
 }
@@ -0,0 +1,215 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Context, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const KEYRING_FILE: &str = "keyring.bin";
+const KEY_FILE: &str = "keyring.key";
+
+/// Passwords for protected PDFs
+/// The keyring is stored in the data dir encrypted with AES-256-GCM under a
+/// random key kept in a separate owner-only file, so a copied or synced
+/// keyring file alone does not reveal the passwords.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Keyring {
+    /// Passwords of specific files, by path
+    files: BTreeMap<String, String>,
+    /// Passwords tried on every protected PDF, e.g. a company-wide password
+    candidates: Vec<String>,
+}
+
+/// What the keyring holds, without revealing any password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyringStatus {
+    pub file_passwords: usize,
+    pub candidate_passwords: usize,
+}
+
+impl Keyring {
+    /// Get the directory holding the keyring and its key
+    pub fn get_keyring_dir() -> Result<PathBuf> {
+        let mut path = dirs::data_local_dir()
+            .context("Could not find data directory")?;
+        path.push("pdf-finder-pro");
+        fs::create_dir_all(&path)
+            .context("Failed to create keyring directory")?;
+        Ok(path)
+    }
+
+    /// Load the keyring from the data dir, empty if none was saved yet
+    pub fn load() -> Result<Self> {
+        Self::load_from(&Self::get_keyring_dir()?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::get_keyring_dir()?)
+    }
+
+    pub fn load_from(dir: &Path) -> Result<Self> {
+        let path = dir.join(KEYRING_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let key = load_or_create_key(dir)?;
+        let sealed = fs::read(&path)
+            .context("Failed to read keyring")?;
+        let plaintext = open(&key, &sealed)?;
+        serde_json::from_slice(&plaintext)
+            .context("Failed to parse keyring")
+    }
+
+    pub fn save_to(&self, dir: &Path) -> Result<()> {
+        let key = load_or_create_key(dir)?;
+        let plaintext = serde_json::to_vec(self)
+            .context("Failed to serialize keyring")?;
+        write_private(&dir.join(KEYRING_FILE), &seal(&key, &plaintext)?)
+            .context("Failed to write keyring")
+    }
+
+    /// Passwords to try for `path`: its own password first, then the candidates
+    pub fn passwords_for(&self, path: &str) -> Vec<String> {
+        self.files
+            .get(path)
+            .into_iter()
+            .chain(self.candidates.iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_file_password(&mut self, path: &str, password: &str) {
+        self.files.insert(path.to_string(), password.to_string());
+    }
+
+    pub fn remove_file_password(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+
+    pub fn add_candidate(&mut self, password: &str) {
+        if !self.candidates.iter().any(|p| p == password) {
+            self.candidates.push(password.to_string());
+        }
+    }
+
+    pub fn remove_candidate(&mut self, password: &str) {
+        self.candidates.retain(|p| p != password);
+    }
+
+    pub fn status(&self) -> KeyringStatus {
+        KeyringStatus {
+            file_passwords: self.files.len(),
+            candidate_passwords: self.candidates.len(),
+        }
+    }
+}
+
+fn load_or_create_key(dir: &Path) -> Result<[u8; KEY_LEN]> {
+    let path = dir.join(KEY_FILE);
+    if path.exists() {
+        let bytes = fs::read(&path)
+            .context("Failed to read keyring key")?;
+        return bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Keyring key has the wrong length"));
+    }
+
+    let mut key = [0u8; KEY_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut key);
+    write_private(&path, &key)
+        .context("Failed to write keyring key")?;
+    Ok(key)
+}
+
+/// Write a file readable only by the current user where the platform allows it
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Encrypt `plaintext` under `key`; the random nonce is prepended to the ciphertext
+fn seal(key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|_| anyhow::anyhow!("Invalid keyring key"))?;
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt keyring"))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open(key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        anyhow::bail!("Keyring file is truncated");
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|_| anyhow::anyhow!("Invalid keyring key"))?;
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Keyring could not be decrypted; it was modified or its key was replaced"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyring_roundtrip_is_encrypted() {
+        let dir = std::env::temp_dir().join(format!("keyring_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut keyring = Keyring::default();
+        keyring.set_file_password("/docs/contract.pdf", "s3cret-file");
+        keyring.add_candidate("company-wide");
+        keyring.add_candidate("company-wide");
+        keyring.save_to(&dir).unwrap();
+
+        let stored = fs::read(dir.join(KEYRING_FILE)).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("s3cret-file"));
+
+        let loaded = Keyring::load_from(&dir).unwrap();
+        assert_eq!(
+            loaded.passwords_for("/docs/contract.pdf"),
+            vec!["s3cret-file", "company-wide"]
+        );
+        assert_eq!(loaded.passwords_for("/docs/other.pdf"), vec!["company-wide"]);
+        assert_eq!(loaded.status().candidate_passwords, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tampered_keyring_is_rejected() {
+        let key = [7u8; KEY_LEN];
+        let mut sealed = seal(&key, b"{}").unwrap();
+        assert_eq!(open(&key, &sealed).unwrap(), b"{}");
+
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(open(&key, &sealed).is_err());
+        assert!(open(&[8u8; KEY_LEN], &sealed[..NONCE_LEN]).is_err());
+    }
+}
@@ -1,19 +1,41 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod archive;
+mod attachments;
 mod database;
+mod entities;
+mod extract_worker;
+mod extractors;
+mod fingerprint;
+mod highlight;
 mod indexer;
+mod keyring;
 mod license;
+mod links;
+mod ocr;
+mod outline;
+mod path_filter;
+mod pdf_text;
+mod semantic;
+mod similarity;
+mod tables;
+mod text_cleanup;
+mod thumbnails;
 mod validation;
 
-use database::{Database, SearchFilters, IndexedFolder};
-use indexer::PdfIndexer;
+use database::{Database, SearchFilters, IndexedFolder, IndexError, IndexJob, FolderSettings};
+use indexer::{IndexConfig, PdfIndexer};
+use keyring::{Keyring, KeyringStatus};
 use license::License;
+use semantic::{EmbeddingModel, SearchMode};
+use thumbnails::ThumbnailCache;
 use validation::{LicenseStatus, validate_license};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, TryLockError};
 use tauri::State;
+use tauri_plugin_dialog::DialogExt;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct IndexResult {
@@ -21,12 +43,43 @@ struct IndexResult {
     duration: u128,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct RetryResult {
+    /// Files that index without errors now
+    recovered: usize,
+    /// Files that no longer exist, whose errors were dropped
+    removed: usize,
+    duration: u128,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SemanticStatus {
+    available: bool,
+    model_path: String,
+    embedded_documents: i64,
+}
+
 struct AppState {
     db: Mutex<Option<Database>>,
+    /// Loaded once per run, including the outcome that there is no model
+    embedding_model: OnceLock<Option<Arc<EmbeddingModel>>>,
+    /// Held for the length of an indexing run, so runs never overlap
+    indexing: Mutex<()>,
+}
+
+/// Claim the indexer for one run, failing while another run holds it
+fn start_indexing_run(state: &AppState) -> Result<MutexGuard<'_, ()>, String> {
+    match state.indexing.try_lock() {
+        Ok(guard) => Ok(guard),
+        // A run that panicked left nothing half-written behind the lock
+        Err(TryLockError::Poisoned(poisoned)) => Ok(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => Err("Indexing is already running. Try again when it finishes.".to_string()),
+    }
 }
 
 #[tauri::command]
 async fn index_pdfs(folder_path: String, state: State<'_, AppState>) -> Result<IndexResult, String> {
+    let _run = start_indexing_run(&state)?;
     let start = std::time::Instant::now();
 
     // Get or create database
@@ -41,13 +94,22 @@ async fn index_pdfs(folder_path: String, state: State<'_, AppState>) -> Result<I
     };
 
     let database = db.ok_or("Database not initialized")?;
-    let indexer = PdfIndexer::new(database);
+    let indexer = build_indexer(&state, database.clone());
 
     log::info!("Starting indexing for folder: {}", folder_path);
     let count = indexer
         .index_folder(&folder_path)
         .map_err(|e| format!("Indexing failed: {}", e))?;
 
+    // Thumbnails of files that changed or left the index are not needed anymore
+    if let (Some(cache), Ok(hashes)) = (thumbnail_cache(), database.content_hashes()) {
+        match cache.remove_unused(&hashes) {
+            Ok(removed) if removed > 0 => log::info!("Removed {} unused thumbnails", removed),
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to clean up thumbnails: {}", e),
+        }
+    }
+
     let duration = start.elapsed().as_millis();
     log::info!("Indexing completed: {} PDFs in {}ms", count, duration);
 
@@ -76,8 +138,25 @@ async fn get_index_stats(state: State<'_, AppState>) -> Result<i64, String> {
 async fn search_pdfs(
     query: String,
     filters: SearchFilters,
+    mode: Option<SearchMode>,
     state: State<'_, AppState>,
 ) -> Result<Vec<database::SearchResult>, String> {
+    const SEMANTIC_LIMIT: usize = 100;
+
+    // Operators like has:comments become filters
+    let mut filters = filters;
+    let query = filters.take_query_operators(&query).map_err(|e| e.to_string())?;
+
+    let mode = mode.unwrap_or_default();
+    let model = match mode {
+        // Without search terms there is nothing to rank, only filters to apply
+        _ if query.is_empty() => None,
+        SearchMode::Keyword => None,
+        SearchMode::Semantic | SearchMode::Hybrid => Some(load_embedding_model(&state).ok_or(
+            "Semantic search requires an embedding model. See get_semantic_status for its location.",
+        )?),
+    };
+
     let db_lock = state.db.lock().unwrap();
     let db = db_lock
         .as_ref()
@@ -86,16 +165,302 @@ async fn search_pdfs(
     // Transform query to FTS5 format
     let fts_query = transform_query(&query);
 
-    let results = db
-        .search(&fts_query, &filters)
-        .map_err(|e| format!("Search failed: {}", e))?;
+    let results = match model {
+        None => db.search(&fts_query, &filters),
+        Some(model) => {
+            let query_vector = model
+                .embed(&query)
+                .ok_or("None of the query words are known to the embedding model")?;
+            if mode == SearchMode::Semantic {
+                db.semantic_search(&query_vector, model.fingerprint(), &filters, SEMANTIC_LIMIT)
+            } else {
+                db.hybrid_search(&fts_query, &query_vector, model.fingerprint(), &filters, SEMANTIC_LIMIT)
+            }
+        }
+    }
+    .map_err(|e| format!("Search failed: {}", e))?;
 
     Ok(results)
 }
 
+#[tauri::command]
+async fn get_ocr_status() -> Result<Option<String>, String> {
+    Ok(ocr::detect_engine().map(|engine| engine.name().to_string()))
+}
+
+#[tauri::command]
+async fn get_semantic_status(state: State<'_, AppState>) -> Result<SemanticStatus, String> {
+    let model_path = get_embedding_model_path().map_err(|e| format!("Failed to get model path: {}", e))?;
+    let model = load_embedding_model(&state);
+
+    let db_lock = state.db.lock().unwrap();
+    // Embeddings made by another model are not searched, so they do not count
+    let embedded_documents = match (db_lock.as_ref(), &model) {
+        (Some(db), Some(model)) => db
+            .count_embedded_documents(model.fingerprint())
+            .map_err(|e| format!("Failed to count embeddings: {}", e))?,
+        _ => 0,
+    };
+
+    Ok(SemanticStatus {
+        available: model.is_some(),
+        model_path: model_path.to_string_lossy().to_string(),
+        embedded_documents,
+    })
+}
+
+#[tauri::command]
+async fn get_document_matches(
+    path: String,
+    query: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<database::DocumentMatches, String> {
+    const DEFAULT_LIMIT: usize = 50;
+    const MAX_LIMIT: usize = 500;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    let fts_query = transform_query(&query);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    db.get_document_matches(&path, &fts_query, offset.unwrap_or(0), limit)
+        .map_err(|e| format!("Failed to get matches: {}", e))
+}
+
+#[tauri::command]
+async fn get_annotations(path: String, state: State<'_, AppState>) -> Result<Vec<pdf_text::Annotation>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    db.get_annotations(&path)
+        .map_err(|e| format!("Failed to get annotations: {}", e))
+}
+
+#[tauri::command]
+async fn get_tables(path: String, state: State<'_, AppState>) -> Result<Vec<tables::Table>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    db.get_tables(&path)
+        .map_err(|e| format!("Failed to get tables: {}", e))
+}
+
+/// The entities most often mentioned by the documents matching `query`,
+/// at most `limit` of each kind
+#[tauri::command]
+async fn get_entity_facets(
+    query: String,
+    filters: SearchFilters,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<database::EntityFacet>, String> {
+    let mut filters = filters;
+    let query = filters.take_query_operators(&query).map_err(|e| e.to_string())?;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    db.entity_facets(&transform_query(&query), &filters, limit.unwrap_or(5))
+        .map_err(|e| format!("Failed to count entities: {}", e))
+}
+
+/// Documents linking to `host` or its subdomains, each with its links there
+#[tauri::command]
+async fn get_link_report(host: String, state: State<'_, AppState>) -> Result<Vec<database::LinkingDocument>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    db.documents_linking_to(&host)
+        .map_err(|e| format!("Failed to list documents linking to {}: {}", host, e))
+}
+
+/// Ask where to save table `index` of the document at `path` and write it
+/// there as CSV. Returns the file written, or `None` when the user cancels;
+/// the destination only ever comes from the save dialog.
+#[tauri::command]
+async fn export_table_csv(
+    path: String,
+    index: usize,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let table = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock
+            .as_ref()
+            .ok_or("Database not initialized. Please index PDFs first.")?;
+        db.get_tables(&path)
+            .map_err(|e| format!("Failed to get tables: {}", e))?
+            .into_iter()
+            .nth(index)
+            .ok_or("This document has no such table")?
+    };
+
+    let name = std::path::Path::new(&path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "document".to_string());
+    let Some(destination) = app
+        .dialog()
+        .file()
+        .set_file_name(format!("{} table {}.csv", name, index + 1))
+        .add_filter("CSV", &["csv"])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let destination = destination
+        .into_path()
+        .map_err(|e| format!("Invalid destination: {}", e))?;
+
+    std::fs::write(&destination, tables::to_csv(&table.rows))
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+    Ok(Some(destination.to_string_lossy().into_owned()))
+}
+
+#[tauri::command]
+async fn find_similar(
+    path: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<database::SearchResult>, String> {
+    const DEFAULT_LIMIT: usize = 20;
+    const MAX_LIMIT: usize = 100;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    db.find_similar(&path, limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT))
+        .map_err(|e| format!("Failed to find similar documents: {}", e))
+}
+
+#[tauri::command]
+async fn find_duplicates(state: State<'_, AppState>) -> Result<Vec<database::DuplicateGroup>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    db.find_duplicates()
+        .map_err(|e| format!("Failed to find duplicates: {}", e))
+}
+
+/// Copy a PDF out of its archive into the temp directory so it can be opened
+fn extract_archive_member(archive_path: &std::path::Path, member: &str, max_size: u64) -> Result<String, String> {
+    use std::hash::{Hash, Hasher};
+
+    if !archive_path.is_file() {
+        return Err("Archive does not exist or has been moved".to_string());
+    }
+
+    // One directory per member keeps the original file name for the viewer
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    archive_path.hash(&mut hasher);
+    member.hash(&mut hasher);
+    let dir = opened_members_dir().join(format!("{:016x}", hasher.finish()));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create temporary folder: {}", e))?;
+
+    let file_name = std::path::Path::new(member)
+        .file_name()
+        .ok_or("Invalid archive member")?;
+    let dest = dir.join(file_name);
+    archive::extract_member(archive_path, member, &dest, max_size)
+        .map_err(|e| format!("Failed to extract PDF from archive: {}", e))?;
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Where PDFs copied out of archives to be opened are kept
+fn opened_members_dir() -> PathBuf {
+    std::env::temp_dir().join("pdf-finder-pro-open")
+}
+
+/// Size limit of the folder a document was indexed from, which its copy
+/// out of an archive may not exceed either
+fn max_file_size_for(db: &Database, path: &str) -> u64 {
+    let settings = db
+        .get_folder_path(path)
+        .ok()
+        .flatten()
+        .and_then(|folder| db.get_folder_settings(&folder).ok())
+        .unwrap_or_default();
+    IndexConfig::default().for_folder(&settings).max_file_size
+}
+
+/// Thumbnail of `page` (the first page by default) of the PDF at `path`, as
+/// a PNG file for the asset protocol. Rendered on first use when indexing
+/// did not cache it.
+#[tauri::command]
+async fn get_thumbnail(path: String, page: Option<u32>, state: State<'_, AppState>) -> Result<String, String> {
+    let page = page.unwrap_or(1).max(1);
+    let (content_hash, parent, max_size) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock
+            .as_ref()
+            .ok_or("Database not initialized. Please index PDFs first.")?;
+        let content_hash = db
+            .get_content_hash(&path)
+            .map_err(|e| format!("Failed to look up {}: {}", path, e))?
+            .ok_or("This file is not in the indexed database")?;
+        let parent = db
+            .get_parent_path(&path)
+            .map_err(|e| format!("Failed to look up {}: {}", path, e))?;
+        (content_hash, parent, max_file_size_for(db, &path))
+    };
+
+    let cache = thumbnail_cache().ok_or("Could not find the thumbnail folder")?;
+    let cached = cache.path_for(&content_hash, page);
+    if cached.is_file() {
+        return Ok(cached.to_string_lossy().to_string());
+    }
+
+    // Attachments only exist inside their PDF
+    if parent.is_some() {
+        return Err("No preview available for attachments".to_string());
+    }
+    let is_pdf = std::path::Path::new(&path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false);
+    if !is_pdf {
+        return Err("Previews are only available for PDFs".to_string());
+    }
+    let file = match archive::split_member_path(&path) {
+        Some((archive_path, member)) => extract_archive_member(&archive_path, member, max_size)?,
+        None => path.clone(),
+    };
+    if !std::path::Path::new(&file).is_file() {
+        return Err("File does not exist or has been moved".to_string());
+    }
+
+    let timeout = std::time::Duration::from_secs(IndexConfig::default().extraction_timeout_secs);
+    let thumbnail = cache
+        .get_or_render(std::path::Path::new(&file), &content_hash, page, timeout)
+        .map_err(|e| format!("Failed to render thumbnail: {:#}", e))?;
+    Ok(thumbnail.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 async fn open_pdf(path: String, state: State<'_, AppState>) -> Result<(), String> {
     // Validate that the PDF exists in our database before opening
+    let mut path = path;
+    let mut max_size = IndexConfig::default().max_file_size;
     let db_lock = state.db.lock().unwrap();
     if let Some(db) = db_lock.as_ref() {
         // Check if this path is in our indexed PDFs
@@ -105,16 +470,33 @@ async fn open_pdf(path: String, state: State<'_, AppState>) -> Result<(), String
         if !is_indexed {
             return Err("This file is not in the indexed database".to_string());
         }
+
+        max_size = max_file_size_for(db, &path);
+
+        // Attachments are opened through the PDF they are embedded in
+        if let Some(parent) = db.get_parent_path(&path)
+            .map_err(|e| format!("Failed to validate PDF: {}", e))? {
+            path = parent;
+        }
     }
     
-    // Validate file exists and is a PDF
+    drop(db_lock);
+
+    // PDFs inside archives are copied out to a temporary file first
+    let path = match archive::split_member_path(&path) {
+        Some((archive_path, member)) => extract_archive_member(&archive_path, member, max_size)?,
+        None => path,
+    };
+
+    // Validate file exists and is a PDF or another indexed format
     let file_path = std::path::Path::new(&path);
     if !file_path.exists() {
         return Err("File does not exist or has been moved".to_string());
     }
     
-    if !file_path.extension().and_then(|s| s.to_str()).map(|s| s.eq_ignore_ascii_case("pdf")).unwrap_or(false) {
-        return Err("File is not a PDF".to_string());
+    let is_pdf = file_path.extension().and_then(|s| s.to_str()).map(|s| s.eq_ignore_ascii_case("pdf")).unwrap_or(false);
+    if !is_pdf && extractors::ExtractorRegistry::default().for_path(file_path).is_none() {
+        return Err("File is not a supported document".to_string());
     }
     
     #[cfg(target_os = "windows")]
@@ -144,6 +526,138 @@ async fn open_pdf(path: String, state: State<'_, AppState>) -> Result<(), String
     Ok(())
 }
 
+#[tauri::command]
+async fn get_interrupted_jobs(state: State<'_, AppState>) -> Result<Vec<IndexJob>, String> {
+    let db = {
+        let mut db_lock = state.db.lock().unwrap();
+        if db_lock.is_none() {
+            let db_path = get_db_path().map_err(|e| format!("Failed to get DB path: {}", e))?;
+            let database = Database::new(db_path).map_err(|e| format!("Failed to create database: {}", e))?;
+            *db_lock = Some(database);
+        }
+        db_lock.clone()
+    };
+
+    let database = db.ok_or("Database not initialized")?;
+    database
+        .get_interrupted_jobs()
+        .map_err(|e| format!("Failed to get interrupted jobs: {}", e))
+}
+
+#[tauri::command]
+async fn resume_indexing(folder_path: String, state: State<'_, AppState>) -> Result<IndexResult, String> {
+    let _run = start_indexing_run(&state)?;
+    let start = std::time::Instant::now();
+
+    let database = {
+        let db_lock = state.db.lock().unwrap();
+        db_lock
+            .clone()
+            .ok_or("Database not initialized. Please index PDFs first.")?
+    };
+    let indexer = build_indexer(&state, database);
+
+    let count = indexer
+        .resume_job(&folder_path)
+        .map_err(|e| format!("Resuming failed: {}", e))?;
+
+    let duration = start.elapsed().as_millis();
+    log::info!("Resumed indexing completed: {} PDFs in {}ms", count, duration);
+
+    Ok(IndexResult { count, duration })
+}
+
+/// Drop an interrupted job; documents it already committed stay indexed
+#[tauri::command]
+async fn discard_index_job(folder_path: String, state: State<'_, AppState>) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized")?;
+
+    db.finish_index_job(&folder_path)
+        .map_err(|e| format!("Failed to discard job: {}", e))
+}
+
+#[tauri::command]
+async fn get_index_errors(
+    folder_path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<IndexError>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    db.get_index_errors(folder_path.as_deref())
+        .map_err(|e| format!("Failed to get index errors: {}", e))
+}
+
+#[tauri::command]
+async fn retry_failed(folder_path: Option<String>, state: State<'_, AppState>) -> Result<RetryResult, String> {
+    let _run = start_indexing_run(&state)?;
+    let start = std::time::Instant::now();
+
+    let database = {
+        let db_lock = state.db.lock().unwrap();
+        db_lock
+            .clone()
+            .ok_or("Database not initialized. Please index PDFs first.")?
+    };
+    let indexer = build_indexer(&state, database);
+
+    let outcome = indexer
+        .retry_failed(folder_path.as_deref())
+        .map_err(|e| format!("Retry failed: {}", e))?;
+
+    let duration = start.elapsed().as_millis();
+    log::info!(
+        "Retry completed: {} files recovered, {} missing files forgotten in {}ms",
+        outcome.recovered,
+        outcome.removed,
+        duration
+    );
+
+    Ok(RetryResult { recovered: outcome.recovered, removed: outcome.removed, duration })
+}
+
+/// Store the password of a protected PDF; run `retry_failed` to index it
+#[tauri::command]
+async fn set_pdf_password(path: String, password: String) -> Result<(), String> {
+    let mut keyring = Keyring::load().map_err(|e| format!("Failed to load keyring: {}", e))?;
+    keyring.set_file_password(&path, &password);
+    keyring.save().map_err(|e| format!("Failed to save keyring: {}", e))
+}
+
+#[tauri::command]
+async fn remove_pdf_password(path: String) -> Result<(), String> {
+    let mut keyring = Keyring::load().map_err(|e| format!("Failed to load keyring: {}", e))?;
+    keyring.remove_file_password(&path);
+    keyring.save().map_err(|e| format!("Failed to save keyring: {}", e))
+}
+
+/// Add a password to try on every protected PDF
+#[tauri::command]
+async fn add_keyring_password(password: String) -> Result<(), String> {
+    let mut keyring = Keyring::load().map_err(|e| format!("Failed to load keyring: {}", e))?;
+    keyring.add_candidate(&password);
+    keyring.save().map_err(|e| format!("Failed to save keyring: {}", e))
+}
+
+#[tauri::command]
+async fn remove_keyring_password(password: String) -> Result<(), String> {
+    let mut keyring = Keyring::load().map_err(|e| format!("Failed to load keyring: {}", e))?;
+    keyring.remove_candidate(&password);
+    keyring.save().map_err(|e| format!("Failed to save keyring: {}", e))
+}
+
+#[tauri::command]
+async fn get_keyring_status() -> Result<KeyringStatus, String> {
+    Keyring::load()
+        .map(|keyring| keyring.status())
+        .map_err(|e| format!("Failed to load keyring: {}", e))
+}
+
 #[tauri::command]
 async fn get_indexed_folders(state: State<'_, AppState>) -> Result<Vec<IndexedFolder>, String> {
     let db = {
@@ -173,6 +687,75 @@ async fn remove_indexed_folder(folder_path: String, state: State<'_, AppState>)
         .map_err(|e| format!("Failed to remove folder: {}", e))
 }
 
+#[tauri::command]
+async fn get_folder_settings(folder_path: String, state: State<'_, AppState>) -> Result<FolderSettings, String> {
+    let db = {
+        let mut db_lock = state.db.lock().unwrap();
+        if db_lock.is_none() {
+            let db_path = get_db_path().map_err(|e| format!("Failed to get DB path: {}", e))?;
+            let database = Database::new(db_path).map_err(|e| format!("Failed to create database: {}", e))?;
+            *db_lock = Some(database);
+        }
+        db_lock.clone()
+    };
+
+    let database = db.ok_or("Database not initialized")?;
+    database
+        .get_folder_settings(&folder_path)
+        .map_err(|e| format!("Failed to get folder settings: {}", e))
+}
+
+/// Save the indexing settings of an indexed folder; they apply from its
+/// next index
+#[tauri::command]
+async fn set_folder_settings(
+    folder_path: String,
+    settings: FolderSettings,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if let (Some(min), Some(max)) = (settings.min_file_size, settings.max_file_size) {
+        if min > max {
+            return Err("Minimum file size must not exceed the maximum".to_string());
+        }
+    }
+    if let Some(language) = &settings.ocr_language {
+        if !ocr::is_valid_language(language) {
+            return Err("OCR languages must be Tesseract language codes, like eng or eng+deu".to_string());
+        }
+    }
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock
+        .as_ref()
+        .ok_or("Database not initialized. Please index PDFs first.")?;
+
+    db.set_folder_settings(&folder_path, &settings)
+        .map_err(|e| format!("Failed to save folder settings: {}", e))
+}
+
+/// Folders whose re-index schedule has come due
+#[tauri::command]
+async fn get_folders_due_for_reindex(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| format!("Invalid system time: {}", e))?
+        .as_secs() as i64;
+    let db = {
+        let mut db_lock = state.db.lock().unwrap();
+        if db_lock.is_none() {
+            let db_path = get_db_path().map_err(|e| format!("Failed to get DB path: {}", e))?;
+            let database = Database::new(db_path).map_err(|e| format!("Failed to create database: {}", e))?;
+            *db_lock = Some(database);
+        }
+        db_lock.clone()
+    };
+
+    let database = db.ok_or("Database not initialized")?;
+    database
+        .get_folders_due_for_reindex(now)
+        .map_err(|e| format!("Failed to get scheduled folders: {}", e))
+}
+
 fn get_db_path() -> anyhow::Result<PathBuf> {
     let mut path = dirs::data_local_dir()
         .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
@@ -182,6 +765,69 @@ fn get_db_path() -> anyhow::Result<PathBuf> {
     Ok(path)
 }
 
+/// Location of the optional word-embedding model used for semantic search
+fn get_embedding_model_path() -> anyhow::Result<PathBuf> {
+    let mut path = dirs::data_local_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    path.push("pdf-finder-pro");
+    path.push("models");
+    std::fs::create_dir_all(&path)?;
+    path.push("embeddings.vec");
+    Ok(path)
+}
+
+/// An indexer for `db` with every optional engine that is available here
+fn build_indexer(state: &AppState, db: Database) -> PdfIndexer {
+    PdfIndexer::new(db)
+        .with_embedding_model(load_embedding_model(state))
+        .with_ocr_engine(ocr::detect_engine())
+        .with_worker_executable(std::env::current_exe().ok())
+        .with_keyring(load_keyring())
+        .with_thumbnail_cache(thumbnail_cache().filter(|cache| cache.can_render()))
+}
+
+/// Load the embedding model on first use; `None` when it is not installed or
+/// fails to load. Either outcome is kept until the app restarts.
+fn load_embedding_model(state: &AppState) -> Option<Arc<EmbeddingModel>> {
+    state
+        .embedding_model
+        .get_or_init(|| {
+            let path = get_embedding_model_path().ok()?;
+            if !path.exists() {
+                log::info!("No embedding model at {}, semantic search is off", path.display());
+                return None;
+            }
+            match EmbeddingModel::load(&path) {
+                Ok(model) => Some(Arc::new(model)),
+                Err(e) => {
+                    log::warn!("Failed to load embedding model {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .clone()
+}
+
+/// Cache of page thumbnails in the app's data directory
+fn thumbnail_cache() -> Option<Arc<ThumbnailCache>> {
+    let mut path = dirs::data_local_dir()?;
+    path.push("pdf-finder-pro");
+    path.push("thumbnails");
+    Some(Arc::new(ThumbnailCache::new(path)))
+}
+
+/// Passwords for protected PDFs; indexing continues without them if the
+/// keyring cannot be read
+fn load_keyring() -> Option<Arc<Keyring>> {
+    match Keyring::load() {
+        Ok(keyring) => Some(Arc::new(keyring)),
+        Err(e) => {
+            log::warn!("Failed to load keyring: {}", e);
+            None
+        }
+    }
+}
+
 fn transform_query(query: &str) -> String {
     // Limit query length to prevent abuse
     const MAX_QUERY_LENGTH: usize = 1000;
@@ -277,21 +923,61 @@ pub fn run() {
         .filter_level(log::LevelFilter::Info)
         .init();
     
+    // The app re-runs itself as a sandboxed extraction worker while indexing
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(extract_worker::WORKER_ARG) {
+        extract_worker::run_worker(&args[2..]);
+        return;
+    }
+
     log::info!("Starting PDF Finder Pro");
+
+    // PDFs copied out of archives stay until a later start, so a viewer
+    // still showing one keeps its file
+    let removed = archive::remove_stale_copies(&opened_members_dir(), std::time::Duration::from_secs(24 * 60 * 60));
+    if removed > 0 {
+        log::info!("Removed {} PDFs copied out of archives", removed);
+    }
     
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .manage(AppState {
             db: Mutex::new(None),
+            embedding_model: OnceLock::new(),
+            indexing: Mutex::new(()),
         })
         .invoke_handler(tauri::generate_handler![
             index_pdfs, 
             search_pdfs, 
+            get_semantic_status,
+            get_ocr_status,
+            get_document_matches,
+            get_annotations,
+            get_tables,
+            export_table_csv,
+            get_link_report,
+            get_entity_facets,
+            get_thumbnail,
+            find_similar,
+            find_duplicates,
+            get_index_errors,
+            retry_failed,
+            get_interrupted_jobs,
+            resume_indexing,
+            discard_index_job,
+            set_pdf_password,
+            remove_pdf_password,
+            add_keyring_password,
+            remove_keyring_password,
+            get_keyring_status,
             open_pdf, 
             get_index_stats,
             get_indexed_folders,
             remove_indexed_folder,
+            get_folder_settings,
+            set_folder_settings,
+            get_folders_due_for_reindex,
             get_license_status,
             activate_license,
             get_trial_days_remaining,
@@ -0,0 +1,222 @@
+use pdf_extract::{Document, Object};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Where a URL found in running text may start
+const URL_PREFIXES: &[&str] = &["https://", "http://", "ftp://", "www."];
+
+/// A link of a document: the target of a link annotation or a URL written
+/// in its text
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    /// 1-based page number, when the page layout of the document is known
+    pub page: Option<u32>,
+    pub url: String,
+}
+
+/// What a `link:` filter compares links with
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkFilter {
+    /// Links to this host or one of its subdomains
+    Host(String),
+    /// Links whose URL contains this text, for values with a path
+    Url(String),
+}
+
+impl LinkFilter {
+    pub fn parse(value: &str) -> Option<LinkFilter> {
+        let value = strip_scheme(value.trim()).to_lowercase();
+        let value = value.strip_prefix("www.").unwrap_or(&value).trim_end_matches('/');
+        if value.is_empty() {
+            None
+        } else if value.contains(['/', '?', '#']) {
+            Some(LinkFilter::Url(value.to_string()))
+        } else {
+            Some(LinkFilter::Host(value.to_string()))
+        }
+    }
+}
+
+/// Targets of the URI actions of link annotations, page by page
+pub fn extract_uri_links(doc: &Document) -> Vec<Link> {
+    let mut links = Vec::new();
+    for (page, page_id) in doc.get_pages() {
+        for annotation in doc.get_page_annotations(page_id).unwrap_or_default() {
+            let is_link = annotation
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .map(|kind| kind == b"Link")
+                .unwrap_or(false);
+            if !is_link {
+                continue;
+            }
+            let Ok(action) = annotation.get_deref(b"A", doc).and_then(Object::as_dict) else {
+                continue;
+            };
+            let is_uri = action
+                .get(b"S")
+                .and_then(Object::as_name)
+                .map(|kind| kind == b"URI")
+                .unwrap_or(false);
+            // URIs are 7-bit ASCII, so no text string decoding is needed
+            if let (true, Ok(uri)) = (is_uri, action.get_deref(b"URI", doc).and_then(Object::as_str)) {
+                let url = String::from_utf8_lossy(uri).trim().to_string();
+                if !url.is_empty() {
+                    links.push(Link { page: Some(page), url });
+                }
+            }
+        }
+    }
+    links
+}
+
+/// Links of a document: the link annotations `uri_links` followed by the
+/// URLs written in `content`, each listed once per page. `page_offsets` are
+/// the char offsets at which the pages of `content` start.
+pub fn document_links(uri_links: Vec<Link>, content: &str, page_offsets: &[i64]) -> Vec<Link> {
+    let mut seen = HashSet::new();
+    let mut links = Vec::new();
+    let text_links = find_urls(content).into_iter().map(|(offset, url)| {
+        let page = (!page_offsets.is_empty()).then(|| page_offsets.partition_point(|&start| start <= offset as i64) as u32);
+        Link { page, url }
+    });
+    for link in uri_links.into_iter().chain(text_links) {
+        if seen.insert((link.page, link.url.clone())) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+/// URLs written in `text` with the char offset each starts at
+pub fn find_urls(text: &str) -> Vec<(usize, String)> {
+    // ASCII lowercasing keeps byte offsets, so they apply to `text` too
+    let lower = text.to_ascii_lowercase();
+    let mut urls = Vec::new();
+    let mut pos = 0;
+    let mut chars_before = 0;
+    let mut counted_to = 0;
+    // Next occurrence of each prefix; only those the scan has passed are
+    // searched for again, so the text is not rescanned once per prefix
+    let mut next: Vec<Option<usize>> = URL_PREFIXES.iter().map(|prefix| lower.find(prefix)).collect();
+    loop {
+        for (found, prefix) in next.iter_mut().zip(URL_PREFIXES) {
+            if found.is_some_and(|i| i < pos) {
+                *found = lower[pos..].find(prefix).map(|i| pos + i);
+            }
+        }
+        let Some(start) = next.iter().flatten().copied().min() else {
+            break;
+        };
+        let len = text[start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\'' | '`' | '{' | '}' | '|' | '\\' | '^'))
+            .unwrap_or(text.len() - start);
+        let end = start + len;
+        // "xwww.example.com" or "user@www.example.com" are not URLs
+        let starts_word = text[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_alphanumeric() && !matches!(c, '.' | '/' | '@' | '-'));
+        let url = trim_url(&text[start..end]);
+        if starts_word && host(url).is_some() {
+            chars_before += text[counted_to..start].chars().count();
+            counted_to = start;
+            urls.push((chars_before, url.to_string()));
+        }
+        pos = end.max(start + 1);
+    }
+    urls
+}
+
+/// Drop punctuation that ends the sentence around a URL rather than the URL,
+/// such as the period in "see https://example.com." or the parenthesis in
+/// "(https://example.com)"
+pub fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '*']);
+        let trimmed = match trimmed.chars().next_back() {
+            Some(')') if trimmed.matches('(').count() < trimmed.matches(')').count() => &trimmed[..trimmed.len() - 1],
+            Some(']') if trimmed.matches('[').count() < trimmed.matches(']').count() => &trimmed[..trimmed.len() - 1],
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+/// Host of a URL, lowercase and without a leading `www.`, as in `example.com`
+/// for `https://www.Example.com:8080/a`. None for URLs without a plausible
+/// host name, such as `mailto:` links.
+pub fn host(url: &str) -> Option<String> {
+    let rest = strip_scheme(url);
+    if rest.len() == url.len() && !url.to_ascii_lowercase().starts_with("www.") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit('@').next()?;
+    let host = host_port.split(':').next()?.trim_end_matches('.').to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        });
+    valid.then(|| host.to_string())
+}
+
+fn strip_scheme(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((scheme, rest)) if !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) => rest,
+        _ => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_urls() {
+        let text = "See https://wiki.example.com/display/ENG. Or (www.example.org/a_(b)) and xwww.nope.com, \
+                    mail bob@www.nope.com at ftp://files.example.net:21/pub!";
+        let urls: Vec<String> = find_urls(text).into_iter().map(|(_, url)| url).collect();
+        assert_eq!(
+            urls,
+            vec!["https://wiki.example.com/display/ENG", "www.example.org/a_(b)", "ftp://files.example.net:21/pub"]
+        );
+        // Offsets count chars, not bytes
+        assert_eq!(find_urls("Café – http://a.example.com")[0].0, 7);
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host("https://WWW.Example.com:8080/a?b").as_deref(), Some("example.com"));
+        assert_eq!(host("http://user:pw@wiki.example.com").as_deref(), Some("wiki.example.com"));
+        assert_eq!(host("www.example.org/a").as_deref(), Some("example.org"));
+        assert_eq!(host("mailto:bob@example.com"), None);
+        assert_eq!(host("http://localhost/"), None);
+
+        assert_eq!(LinkFilter::parse("https://www.Wiki.example.com/"), Some(LinkFilter::Host("wiki.example.com".to_string())));
+        assert_eq!(LinkFilter::parse("wiki.example.com/ENG"), Some(LinkFilter::Url("wiki.example.com/eng".to_string())));
+        assert_eq!(LinkFilter::parse(""), None);
+    }
+
+    #[test]
+    fn test_document_links_are_placed_on_pages() {
+        let uri_links = vec![Link { page: Some(2), url: "https://old.example.com/x".to_string() }];
+        let content = "intro https://a.example.com then https://old.example.com/x";
+        let links = document_links(uri_links, content, &[0, 28]);
+        assert_eq!(
+            links,
+            vec![
+                Link { page: Some(2), url: "https://old.example.com/x".to_string() },
+                Link { page: Some(1), url: "https://a.example.com".to_string() },
+            ]
+        );
+        assert_eq!(document_links(Vec::new(), content, &[])[0].page, None);
+    }
+}
@@ -0,0 +1,336 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Resolution used to rasterize pages; 300 DPI is Tesseract's sweet spot
+pub const OCR_DPI: u32 = 300;
+/// Upper bound on pages recognized per document
+pub const MAX_OCR_PAGES: u32 = 200;
+/// Tesseract language recognized unless a folder picks others
+pub const DEFAULT_OCR_LANGUAGE: &str = "eng";
+
+/// Text recognized on one page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrPage {
+    /// 1-based page number
+    pub page: i32,
+    pub text: String,
+    /// Mean word confidence reported by the engine, 0-100
+    pub confidence: f32,
+}
+
+/// A local OCR engine able to recognize the pages of an image-only PDF
+pub trait OcrEngine: Send + Sync {
+    fn name(&self) -> &str;
+    /// Recognize the pages of the PDF at `path` in `language`, giving up once
+    /// the whole document takes longer than `timeout`
+    fn recognize_pdf(&self, path: &Path, language: &str, timeout: Duration) -> Result<Vec<OcrPage>>;
+}
+
+/// Whether `language` is a list of Tesseract language codes, like `eng` or
+/// `eng+deu`. It is passed on the command line, so nothing else is accepted.
+pub fn is_valid_language(language: &str) -> bool {
+    language
+        .split('+')
+        .all(|code| !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// Find an OCR engine installed on this machine, if any
+pub fn detect_engine() -> Option<Arc<dyn OcrEngine>> {
+    TesseractOcr::detect().map(|engine| Arc::new(engine) as Arc<dyn OcrEngine>)
+}
+
+/// OCR through the Tesseract CLI, with pages rasterized by Poppler's `pdftoppm`
+pub struct TesseractOcr {
+    tesseract: PathBuf,
+    pdftoppm: PathBuf,
+}
+
+impl TesseractOcr {
+    pub fn detect() -> Option<Self> {
+        let tesseract = find_executable("tesseract")?;
+        let pdftoppm = find_executable("pdftoppm")?;
+        log::info!("Using OCR engine {} with rasterizer {}", tesseract.display(), pdftoppm.display());
+        Some(Self { tesseract, pdftoppm })
+    }
+
+    fn recognize_image(&self, image: &Path, language: &str, timeout: Duration) -> Result<(String, f32)> {
+        let mut command = Command::new(&self.tesseract);
+        command
+            .arg(image)
+            .arg("stdout")
+            .args(["-l", language])
+            .arg("tsv")
+            // Pages are already processed in parallel by the indexer
+            .env("OMP_THREAD_LIMIT", "1");
+        let output = output_with_timeout(&mut command, timeout).context("Failed to run tesseract")?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "tesseract failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(parse_tesseract_tsv(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+impl OcrEngine for TesseractOcr {
+    fn name(&self) -> &str {
+        "tesseract"
+    }
+
+    fn recognize_pdf(&self, path: &Path, language: &str, timeout: Duration) -> Result<Vec<OcrPage>> {
+        if !is_valid_language(language) {
+            anyhow::bail!("Invalid OCR language: {}", language);
+        }
+        // Rasterizing and every page share one budget for the whole document
+        let deadline = Instant::now() + timeout;
+        let work_dir = ScratchDir::new()?;
+        let prefix = work_dir.path().join("page");
+
+        let mut command = Command::new(&self.pdftoppm);
+        command
+            .args(["-r", &OCR_DPI.to_string(), "-gray", "-png"])
+            .args(["-l", &MAX_OCR_PAGES.to_string()])
+            .arg(path)
+            .arg(&prefix);
+        let status = output_with_timeout(&mut command, time_left(deadline, timeout)?)
+            .context("Failed to run pdftoppm")?;
+
+        if !status.status.success() {
+            anyhow::bail!(
+                "pdftoppm failed: {}",
+                String::from_utf8_lossy(&status.stderr).trim()
+            );
+        }
+
+        // pdftoppm names pages page-1.png or page-001.png depending on the page count
+        let mut images: Vec<(i32, PathBuf)> = fs::read_dir(work_dir.path())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter_map(|image| {
+                let stem = image.file_stem()?.to_str()?;
+                let page = stem.rsplit('-').next()?.parse().ok()?;
+                Some((page, image))
+            })
+            .collect();
+        images.sort_by_key(|(page, _)| *page);
+
+        let mut pages = Vec::with_capacity(images.len());
+        for (page, image) in images {
+            let (text, confidence) = self.recognize_image(&image, language, time_left(deadline, timeout)?)?;
+            pages.push(OcrPage { page, text, confidence });
+        }
+
+        Ok(pages)
+    }
+}
+
+/// Rebuild text and mean confidence from Tesseract's TSV output
+/// Columns: level page_num block_num par_num line_num word_num left top width height conf text
+fn parse_tesseract_tsv(tsv: &str) -> (String, f32) {
+    let mut lines: BTreeMap<(u32, u32, u32), Vec<String>> = BTreeMap::new();
+    let mut confidence_sum = 0.0f32;
+    let mut words = 0;
+
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != "5" {
+            continue; // Only level 5 rows are words
+        }
+
+        let word = columns[11].trim();
+        let confidence: f32 = columns[10].parse().unwrap_or(-1.0);
+        if word.is_empty() || confidence < 0.0 {
+            continue;
+        }
+
+        let key = (
+            columns[2].parse().unwrap_or(0),
+            columns[3].parse().unwrap_or(0),
+            columns[4].parse().unwrap_or(0),
+        );
+        lines.entry(key).or_default().push(word.to_string());
+        confidence_sum += confidence;
+        words += 1;
+    }
+
+    let text = lines
+        .values()
+        .map(|words| words.join(" "))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let confidence = if words > 0 { confidence_sum / words as f32 } else { 0.0 };
+
+    (text, confidence)
+}
+
+/// Locate an executable on PATH or in the usual install locations
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() };
+
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    // GUI apps on macOS do not inherit the shell's PATH
+    dirs.extend(
+        ["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin", "C:\\Program Files\\Tesseract-OCR"]
+            .iter()
+            .map(PathBuf::from),
+    );
+
+    dirs.into_iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Run `command` to completion and collect its output like
+/// Time remaining until `deadline`, or an error once a document that was given
+/// `timeout` has used it up
+fn time_left(deadline: Instant, timeout: Duration) -> Result<Duration> {
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(left) if !left.is_zero() => Ok(left),
+        _ => anyhow::bail!("OCR did not finish within {:?}", timeout),
+    }
+}
+
+/// `Command::output`, but kill it once it runs longer than `timeout`. External
+/// tools run inside the indexing process, so a file that makes one hang must
+/// not stall indexing.
+pub fn output_with_timeout(command: &mut Command, timeout: Duration) -> Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Drained on their own threads, so a full pipe cannot block the child
+    let stdout = child.stdout.take().map(read_to_end_in_background);
+    let stderr = child.stderr.take().map(read_to_end_in_background);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("killed after running for {:?}", timeout);
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    let collect = |reader: Option<JoinHandle<Vec<u8>>>| reader.and_then(|r| r.join().ok()).unwrap_or_default();
+    Ok(Output { status, stdout: collect(stdout), stderr: collect(stderr) })
+}
+
+fn read_to_end_in_background<R: Read + Send + 'static>(mut reader: R) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut data = Vec::new();
+        let _ = reader.read_to_end(&mut data);
+        data
+    })
+}
+
+/// Temporary directory removed when dropped
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    pub fn new() -> Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "pdf-finder-pro-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tesseract_tsv() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   1\t1\t0\t0\t0\t0\t0\t0\t100\t100\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t0\t0\t10\t10\t90\tScanned\n\
+                   5\t1\t1\t1\t1\t2\t0\t0\t10\t10\t80\tinvoice\n\
+                   5\t1\t1\t1\t2\t1\t0\t0\t10\t10\t70\tTotal\n\
+                   5\t1\t1\t1\t2\t2\t0\t0\t10\t10\t-1\t \n";
+
+        let (text, confidence) = parse_tesseract_tsv(tsv);
+        assert_eq!(text, "Scanned invoice\nTotal");
+        assert!((confidence - 80.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parse_tesseract_tsv_empty() {
+        assert_eq!(parse_tesseract_tsv(""), (String::new(), 0.0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_valid_language() {
+        assert!(is_valid_language("eng"));
+        assert!(is_valid_language("eng+deu"));
+        assert!(is_valid_language("chi_sim"));
+        assert!(!is_valid_language(""));
+        assert!(!is_valid_language("eng+"));
+        assert!(!is_valid_language("--psm"));
+        assert!(!is_valid_language("../eng"));
+    }
+
+    #[test]
+    fn test_time_left_runs_out() {
+        let timeout = Duration::from_secs(60);
+        assert!(time_left(Instant::now() + timeout, timeout).unwrap() <= timeout);
+        assert!(time_left(Instant::now() - Duration::from_millis(1), timeout).is_err());
+    }
+
+    #[test]
+    fn test_output_with_timeout() {
+        let output = output_with_timeout(Command::new("/bin/sh").args(["-c", "echo page; echo oops >&2"]), Duration::from_secs(10)).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"page\n");
+        assert_eq!(output.stderr, b"oops\n");
+
+        let start = Instant::now();
+        assert!(output_with_timeout(Command::new("/bin/sh").args(["-c", "sleep 30"]), Duration::from_millis(200)).is_err());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_scratch_dir_is_removed() {
+        let path = {
+            let dir = ScratchDir::new().unwrap();
+            assert!(dir.path().is_dir());
+            dir.path().to_path_buf()
+        };
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,284 @@
+use crate::pdf_text::decode_text_string;
+use pdf_extract::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Bookmarks nested deeper than this are treated as malformed
+const MAX_OUTLINE_DEPTH: u32 = 32;
+
+/// Outlines with more bookmarks than this are cut short
+const MAX_OUTLINE_ENTRIES: usize = 10_000;
+
+/// A bookmark of the document outline
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutlineEntry {
+    /// Nesting depth, 0 for top-level bookmarks
+    pub level: u32,
+    pub title: String,
+    /// 1-based page the bookmark points to, when it could be resolved
+    pub page: Option<u32>,
+}
+
+/// Bookmarks of the document in reading order, with their page targets
+pub fn extract_outline(doc: &Document) -> Vec<OutlineEntry> {
+    let Ok(first) = doc
+        .catalog()
+        .and_then(|catalog| catalog.get_deref(b"Outlines", doc))
+        .and_then(Object::as_dict)
+        .and_then(|outlines| outlines.get(b"First"))
+    else {
+        return Vec::new();
+    };
+
+    let pages: HashMap<ObjectId, u32> = doc.get_pages().into_iter().map(|(number, id)| (id, number)).collect();
+    let mut outline = Outline {
+        doc,
+        pages,
+        named: named_destinations(doc),
+        visited: HashSet::new(),
+        entries: Vec::new(),
+    };
+    outline.collect(first, 0);
+    outline.entries
+}
+
+/// Titles of the bookmarks enclosing `page`, outermost first, as in
+/// `["Chapter 4", "Safety", "4.2 Grounding"]`. The section is the last
+/// bookmark starting at or before the page.
+pub fn section_path(outline: &[OutlineEntry], page: u32) -> Vec<String> {
+    let mut trail: Vec<&str> = Vec::new();
+    let mut best: Option<(u32, Vec<&str>)> = None;
+    for entry in outline {
+        trail.truncate(entry.level as usize);
+        trail.push(&entry.title);
+        if let Some(start) = entry.page.filter(|&start| start <= page) {
+            if best.as_ref().is_none_or(|(best_start, _)| start >= *best_start) {
+                best = Some((start, trail.clone()));
+            }
+        }
+    }
+    best.map(|(_, trail)| trail.into_iter().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+struct Outline<'a> {
+    doc: &'a Document,
+    pages: HashMap<ObjectId, u32>,
+    /// Named destinations by name, from both the catalog's Dests dictionary
+    /// and its Dests name tree
+    named: HashMap<Vec<u8>, &'a Object>,
+    /// Items already seen, so malformed outlines cannot loop
+    visited: HashSet<ObjectId>,
+    entries: Vec<OutlineEntry>,
+}
+
+impl Outline<'_> {
+    /// Add the item `first` and its siblings at `level`, each followed by its kids
+    fn collect(&mut self, first: &Object, level: u32) {
+        let mut next = first.as_reference().ok();
+        while let Some(id) = next {
+            if !self.visited.insert(id) || self.entries.len() >= MAX_OUTLINE_ENTRIES {
+                return;
+            }
+            let Ok(item) = self.doc.get_dictionary(id) else {
+                return;
+            };
+
+            let title = item
+                .get_deref(b"Title", self.doc)
+                .and_then(Object::as_str)
+                .map(decode_text_string)
+                .unwrap_or_default();
+            let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !title.is_empty() {
+                self.entries.push(OutlineEntry { level, title, page: self.item_page(item) });
+                if let (Ok(kids), true) = (item.get(b"First"), level < MAX_OUTLINE_DEPTH) {
+                    self.collect(kids, level + 1);
+                }
+            }
+
+            next = item.get(b"Next").and_then(Object::as_reference).ok();
+        }
+    }
+
+    /// Page of an item's destination, given directly or through a GoTo action
+    fn item_page(&self, item: &Dictionary) -> Option<u32> {
+        if let Ok(dest) = item.get(b"Dest") {
+            return self.destination_page(dest, 0);
+        }
+        let action = item.get_deref(b"A", self.doc).and_then(Object::as_dict).ok()?;
+        let is_goto = action
+            .get(b"S")
+            .and_then(Object::as_name)
+            .map(|kind| kind == b"GoTo")
+            .unwrap_or(false);
+        if !is_goto {
+            return None;
+        }
+        self.destination_page(action.get(b"D").ok()?, 0)
+    }
+
+    fn destination_page(&self, dest: &Object, depth: usize) -> Option<u32> {
+        // Named destinations may point at dictionaries that hold the real one
+        if depth > 2 {
+            return None;
+        }
+        let (_, dest) = self.doc.dereference(dest).ok()?;
+        match dest {
+            Object::Array(target) => match target.first()? {
+                Object::Reference(page_id) => self.pages.get(page_id).copied(),
+                // Some writers give a 0-based page number instead
+                Object::Integer(index) => u32::try_from(*index).ok().map(|index| index + 1),
+                _ => None,
+            },
+            Object::Name(name) | Object::String(name, _) => {
+                self.destination_page(self.named.get(name.as_slice())?, depth + 1)
+            }
+            Object::Dictionary(dict) => self.destination_page(dict.get(b"D").ok()?, depth + 1),
+            _ => None,
+        }
+    }
+}
+
+fn named_destinations(doc: &Document) -> HashMap<Vec<u8>, &Object> {
+    let mut named = HashMap::new();
+    let Ok(catalog) = doc.catalog() else {
+        return named;
+    };
+    // PDF 1.1 style dictionary of names
+    if let Ok(dests) = catalog.get_deref(b"Dests", doc).and_then(Object::as_dict) {
+        for (name, dest) in dests.iter() {
+            named.insert(name.clone(), dest);
+        }
+    }
+    if let Ok(tree) = catalog
+        .get_deref(b"Names", doc)
+        .and_then(Object::as_dict)
+        .and_then(|names| names.get_deref(b"Dests", doc))
+        .and_then(Object::as_dict)
+    {
+        collect_name_tree(doc, tree, 0, &mut named);
+    }
+    named
+}
+
+fn collect_name_tree<'a>(doc: &'a Document, node: &'a Dictionary, depth: u32, named: &mut HashMap<Vec<u8>, &'a Object>) {
+    if depth > MAX_OUTLINE_DEPTH {
+        return;
+    }
+    if let Ok(names) = node.get_deref(b"Names", doc).and_then(Object::as_array) {
+        for pair in names.chunks_exact(2) {
+            if let Ok(key) = pair[0].as_str() {
+                named.insert(key.to_vec(), &pair[1]);
+            }
+        }
+    }
+    if let Ok(kids) = node.get_deref(b"Kids", doc).and_then(Object::as_array) {
+        for kid in kids {
+            if let Ok((_, Object::Dictionary(kid))) = doc.dereference(kid) {
+                collect_name_tree(doc, kid, depth + 1, named);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf_extract::StringFormat;
+
+    fn entry(level: u32, title: &str, page: Option<u32>) -> OutlineEntry {
+        OutlineEntry { level, title: title.to_string(), page }
+    }
+
+    #[test]
+    fn test_extract_outline() {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let page_ids: Vec<ObjectId> = (0..3)
+            .map(|_| {
+                let mut page = Dictionary::new();
+                page.set("Type", Object::Name(b"Page".to_vec()));
+                page.set("Parent", Object::Reference(pages_id));
+                doc.add_object(page)
+            })
+            .collect();
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name(b"Pages".to_vec()));
+        pages.set("Kids", Object::Array(page_ids.iter().map(|&id| Object::Reference(id)).collect()));
+        pages.set("Count", Object::Integer(3));
+        doc.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let title = |text: &str| Object::String(text.as_bytes().to_vec(), StringFormat::Literal);
+        let chapter_id = doc.new_object_id();
+        let appendix_id = doc.new_object_id();
+
+        // A section pointing at page 3 through a GoTo action
+        let mut grounding = Dictionary::new();
+        grounding.set("Title", title("4.2 Grounding"));
+        let mut action = Dictionary::new();
+        action.set("S", Object::Name(b"GoTo".to_vec()));
+        action.set("D", Object::Array(vec![Object::Reference(page_ids[2]), Object::Name(b"Fit".to_vec())]));
+        grounding.set("A", Object::Dictionary(action));
+        grounding.set("Parent", Object::Reference(chapter_id));
+        let grounding_id = doc.add_object(grounding);
+
+        let mut chapter = Dictionary::new();
+        chapter.set("Title", title("Chapter 4"));
+        chapter.set("Dest", Object::Array(vec![Object::Reference(page_ids[1]), Object::Name(b"Fit".to_vec())]));
+        chapter.set("First", Object::Reference(grounding_id));
+        chapter.set("Next", Object::Reference(appendix_id));
+        doc.objects.insert(chapter_id, Object::Dictionary(chapter));
+
+        // A named destination, and a link back to the first bookmark that
+        // must not loop
+        let mut appendix = Dictionary::new();
+        appendix.set("Title", title("Appendix"));
+        appendix.set("Dest", title("appendix"));
+        appendix.set("Next", Object::Reference(chapter_id));
+        doc.objects.insert(appendix_id, Object::Dictionary(appendix));
+
+        let mut outlines = Dictionary::new();
+        outlines.set("First", Object::Reference(chapter_id));
+        let mut dests = Dictionary::new();
+        dests.set(
+            "Names",
+            Object::Array(vec![
+                title("appendix"),
+                Object::Array(vec![Object::Reference(page_ids[2]), Object::Name(b"Fit".to_vec())]),
+            ]),
+        );
+        let mut names = Dictionary::new();
+        names.set("Dests", Object::Dictionary(dests));
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+        catalog.set("Pages", Object::Reference(pages_id));
+        catalog.set("Outlines", Object::Dictionary(outlines));
+        catalog.set("Names", Object::Dictionary(names));
+        let catalog_id = doc.add_object(catalog);
+        doc.trailer.set("Root", Object::Reference(catalog_id));
+
+        assert_eq!(
+            extract_outline(&doc),
+            vec![entry(0, "Chapter 4", Some(2)), entry(1, "4.2 Grounding", Some(3)), entry(0, "Appendix", Some(3))]
+        );
+    }
+
+    #[test]
+    fn test_section_path() {
+        let outline = vec![
+            entry(0, "Introduction", Some(1)),
+            entry(0, "Chapter 4", Some(10)),
+            entry(1, "Safety", Some(10)),
+            entry(2, "4.1 Wiring", Some(11)),
+            entry(2, "4.2 Grounding", Some(13)),
+            entry(1, "Unresolved", None),
+            entry(0, "Chapter 5", Some(20)),
+        ];
+        assert_eq!(section_path(&outline, 14), vec!["Chapter 4", "Safety", "4.2 Grounding"]);
+        assert_eq!(section_path(&outline, 10), vec!["Chapter 4", "Safety"]);
+        assert_eq!(section_path(&outline, 1), vec!["Introduction"]);
+        assert_eq!(section_path(&outline, 25), vec!["Chapter 5"]);
+        assert!(section_path(&outline[1..], 3).is_empty());
+    }
+}
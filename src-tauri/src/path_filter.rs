@@ -0,0 +1,323 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the per-directory ignore file, in gitignore syntax
+pub const IGNORE_FILE_NAME: &str = ".pdffinderignore";
+
+/// One gitignore-style pattern
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Path segments to match; `**` matches any number of segments
+    segments: Vec<String>,
+    /// `!pattern` re-includes what an earlier rule excluded
+    negated: bool,
+    /// `pattern/` only matches directories
+    dir_only: bool,
+}
+
+impl Rule {
+    /// Parse one line of gitignore syntax; blank lines and comments give None
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let line = trim_unescaped_trailing_spaces(line);
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').filter(|r| r.starts_with(['!', '#'])).unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+
+        // A slash anywhere but the end anchors the pattern to its directory;
+        // otherwise it matches a name at any depth
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        let mut segments = Vec::new();
+        if !anchored {
+            segments.push("**".to_string());
+        }
+        segments.extend(line.split('/').filter(|s| !s.is_empty()).map(str::to_string));
+
+        Some(Rule { segments, negated, dir_only })
+    }
+
+    /// Whether the rule matches a path given as components relative to the
+    /// directory the rule belongs to
+    fn matches(&self, components: &[&str], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        match_segments(&self.segments, components)
+    }
+}
+
+fn trim_unescaped_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while end > 0 && line.as_bytes()[end - 1] == b' ' {
+        if end >= 2 && line.as_bytes()[end - 2] == b'\\' {
+            break;
+        }
+        end -= 1;
+    }
+    &line[..end]
+}
+
+fn parse_rules<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<Rule> {
+    lines.into_iter().filter_map(Rule::parse).collect()
+}
+
+fn match_segments(pattern: &[String], components: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => components.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            // Trailing `**` matches everything inside, but not the directory itself
+            if rest.is_empty() {
+                return !components.is_empty();
+            }
+            (0..=components.len()).any(|skip| match_segments(rest, &components[skip..]))
+        }
+        Some((first, rest)) => match components.split_first() {
+            Some((component, remaining)) => {
+                wildcard_match(first.as_bytes(), component.as_bytes()) && match_segments(rest, remaining)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match one path segment against `*`, `?`, `[...]` and `\` escapes
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') => (0..=text.len()).any(|skip| wildcard_match(&pattern[1..], &text[skip..])),
+        Some(b'?') => !text.is_empty() && wildcard_match(&pattern[1..], &text[1..]),
+        Some(b'[') => match (text.first(), parse_class(&pattern[1..])) {
+            (Some(&c), Some((matched, len))) => matched(c) && wildcard_match(&pattern[1 + len..], &text[1..]),
+            (Some(&c), None) => c == b'[' && wildcard_match(&pattern[1..], &text[1..]),
+            (None, _) => false,
+        },
+        Some(b'\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && wildcard_match(&pattern[2..], &text[1..])
+        }
+        Some(&p) => text.first() == Some(&p) && wildcard_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Parse a character class after its `[`, returning a matcher and the number
+/// of pattern bytes consumed including the closing `]`
+fn parse_class(pattern: &[u8]) -> Option<(impl Fn(u8) -> bool, usize)> {
+    let (negated, start) = match pattern.first() {
+        Some(b'!') | Some(b'^') => (true, 1),
+        _ => (false, 0),
+    };
+    // A `]` right after the opening bracket is a literal
+    let close = pattern
+        .iter()
+        .enumerate()
+        .skip(start + 1)
+        .find(|(_, &b)| b == b']')
+        .map(|(i, _)| i)?;
+    let body = pattern[start..close].to_vec();
+
+    let matcher = move |c: u8| {
+        let mut i = 0;
+        let mut found = false;
+        while i < body.len() {
+            if i + 2 < body.len() && body[i + 1] == b'-' {
+                found |= body[i] <= c && c <= body[i + 2];
+                i += 3;
+            } else {
+                found |= body[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matcher, close + 1))
+}
+
+/// Decides which files under an indexed folder are indexed
+/// Combines the folder's include and exclude globs with `.pdffinderignore`
+/// files found in the folder and its subdirectories. Exclude globs behave
+/// like a root-level ignore file read before the real ones, so an ignore file
+/// can re-include what they exclude with `!pattern`. When include globs are
+/// set, a file must also match one of them. Applied while walking a folder,
+/// so there is no watcher: changed patterns and ignore files take effect on
+/// the folder's next index.
+pub struct PathFilter {
+    root: PathBuf,
+    include: Vec<Rule>,
+    exclude: Vec<Rule>,
+    /// Parsed ignore files by directory; empty if the directory has none
+    ignore_files: RefCell<HashMap<PathBuf, Vec<Rule>>>,
+}
+
+impl PathFilter {
+    pub fn new(root: &Path, include_patterns: &[String], exclude_patterns: &[String]) -> Self {
+        PathFilter {
+            root: root.to_path_buf(),
+            include: parse_rules(include_patterns.iter().map(String::as_str)),
+            exclude: parse_rules(exclude_patterns.iter().map(String::as_str)),
+            ignore_files: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the walk should skip `path` (and everything below it if it is a
+    /// directory). Assumes the parent directories were not skipped.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => return false,
+        };
+        let components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        if components.is_empty() {
+            return false;
+        }
+        let components: Vec<&str> = components.iter().map(String::as_str).collect();
+
+        let mut ignored = false;
+        apply_rules(&self.exclude, &components, is_dir, &mut ignored);
+
+        // Deeper ignore files take precedence over shallower ones
+        let mut dir = self.root.clone();
+        for depth in 0..components.len() {
+            if depth > 0 {
+                dir.push(components[depth - 1]);
+            }
+            let mut ignore_files = self.ignore_files.borrow_mut();
+            let rules = ignore_files.entry(dir.clone()).or_insert_with(|| read_ignore_file(&dir));
+            apply_rules(rules, &components[depth..], is_dir, &mut ignored);
+        }
+        ignored
+    }
+
+    /// Whether a file passes the include globs; true when there are none
+    pub fn is_included(&self, path: &Path) -> bool {
+        if self.include.is_empty() {
+            return true;
+        }
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let components: Vec<&str> = components.iter().map(String::as_str).collect();
+        self.include.iter().any(|rule| !rule.negated && rule.matches(&components, false))
+    }
+
+}
+
+/// Rules of the ignore file in `dir`, empty if it has none
+fn read_ignore_file(dir: &Path) -> Vec<Rule> {
+    match fs::read_to_string(dir.join(IGNORE_FILE_NAME)) {
+        Ok(contents) => parse_rules(contents.lines()),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Apply rules in order; the last matching rule decides
+fn apply_rules(rules: &[Rule], components: &[&str], is_dir: bool, ignored: &mut bool) {
+    for rule in rules {
+        if rule.matches(components, is_dir) {
+            *ignored = !rule.negated;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p| p.to_string()).collect()
+    }
+
+    impl PathFilter {
+        /// Whether a walk would skip a file, given the directories above it
+        /// are checked first as the walk does
+        fn is_excluded(&self, path: &Path) -> bool {
+            let ancestors: Vec<PathBuf> = path
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(&self.root) && *dir != self.root)
+                .map(Path::to_path_buf)
+                .collect();
+            if ancestors.iter().rev().any(|dir| self.is_ignored(dir, true)) {
+                return true;
+            }
+            self.is_ignored(path, false) || !self.is_included(path)
+        }
+    }
+
+    #[test]
+    fn test_glob_patterns() {
+        let root = Path::new("/docs");
+        let filter = PathFilter::new(
+            root,
+            &[],
+            &patterns(&["**/node_modules/**", "**/Archive/**", "*_draft.pdf", "/scratch/", "tmp[0-9].pdf"]),
+        );
+
+        assert!(filter.is_excluded(Path::new("/docs/web/node_modules/pkg/readme.pdf")));
+        assert!(filter.is_excluded(Path::new("/docs/2023/Archive/old.pdf")));
+        assert!(filter.is_excluded(Path::new("/docs/deep/report_draft.pdf")));
+        assert!(filter.is_excluded(Path::new("/docs/scratch/a.pdf")));
+        assert!(filter.is_excluded(Path::new("/docs/tmp7.pdf")));
+
+        assert!(!filter.is_excluded(Path::new("/docs/sub/scratch/a.pdf")));
+        assert!(!filter.is_excluded(Path::new("/docs/report_final.pdf")));
+        assert!(!filter.is_excluded(Path::new("/docs/Archive.pdf")));
+        assert!(!filter.is_excluded(Path::new("/docs/tmpx.pdf")));
+    }
+
+    #[test]
+    fn test_include_patterns() {
+        let root = Path::new("/docs");
+        let filter = PathFilter::new(root, &patterns(&["invoices/**", "*.contract.pdf"]), &[]);
+
+        assert!(!filter.is_excluded(Path::new("/docs/invoices/2024/jan.pdf")));
+        assert!(!filter.is_excluded(Path::new("/docs/legal/lease.contract.pdf")));
+        assert!(filter.is_excluded(Path::new("/docs/notes/todo.pdf")));
+    }
+
+    #[test]
+    fn test_ignore_files_are_nested_and_negatable() {
+        let root = std::env::temp_dir().join(format!("path_filter_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("projects/keep")).unwrap();
+        fs::write(root.join(IGNORE_FILE_NAME), "# scratch output\nbuild/\n*.tmp.pdf\n").unwrap();
+        fs::write(root.join("projects").join(IGNORE_FILE_NAME), "!important.tmp.pdf\nkeep/secret.pdf\n").unwrap();
+
+        let filter = PathFilter::new(&root, &[], &patterns(&["*.bak.pdf"]));
+
+        assert!(filter.is_excluded(&root.join("build/out.pdf")));
+        assert!(filter.is_excluded(&root.join("projects/build/out.pdf")));
+        assert!(filter.is_excluded(&root.join("a.tmp.pdf")));
+        assert!(filter.is_excluded(&root.join("projects/a.tmp.pdf")));
+        assert!(filter.is_excluded(&root.join("projects/keep/secret.pdf")));
+        assert!(filter.is_excluded(&root.join("projects/old.bak.pdf")));
+
+        assert!(!filter.is_excluded(&root.join("projects/important.tmp.pdf")));
+        assert!(!filter.is_excluded(&root.join("projects/keep/public.pdf")));
+        // Anchored patterns are relative to the directory of their ignore file
+        assert!(!filter.is_excluded(&root.join("keep/secret.pdf")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
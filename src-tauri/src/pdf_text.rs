@@ -0,0 +1,448 @@
+use crate::attachments::{self, Attachment};
+use crate::links::{self, Link};
+use crate::outline::{self, OutlineEntry};
+use crate::tables::{self, PlacedWord, Table};
+use anyhow::Result;
+use pdf_extract::encryption::DecryptionError;
+use pdf_extract::{Dictionary, Document, Error, MediaBox, Object, OutputDev, OutputError, Transform};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Form fields nested deeper than this are treated as malformed
+const MAX_FIELD_DEPTH: usize = 32;
+
+/// Text extracted from a PDF, one entry per page
+#[derive(Debug, Clone, Default)]
+pub struct PageTexts {
+    pub pages: Vec<String>,
+    /// Comments, notes and markup annotations that carry text
+    pub annotations: Vec<Annotation>,
+    /// Filled-in AcroForm fields
+    pub form_fields: Vec<FormField>,
+    /// Bookmarks, in reading order
+    pub outline: Vec<OutlineEntry>,
+    /// Tables reconstructed from the layout, when asked for
+    pub tables: Vec<Table>,
+    /// Targets of link annotations
+    pub links: Vec<Link>,
+    /// Files embedded in the PDF, when asked for
+    pub attachments: Vec<Attachment>,
+    /// The PDF could only be opened with one of the passwords passed in
+    pub password_protected: bool,
+}
+
+/// What to extract from a PDF besides the text of its pages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExtractOptions {
+    /// Reconstruct tables from the layout of each page
+    #[serde(default)]
+    pub tables: bool,
+    /// Read embedded files up to this size (bytes)
+    #[serde(default)]
+    pub attachments: Option<u64>,
+}
+
+/// Text of an annotation, e.g. a sticky note or a comment on a highlight
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// 1-based page number
+    pub page: u32,
+    /// Annotation subtype, e.g. "Text", "Highlight" or "FreeText"
+    pub kind: String,
+    pub author: Option<String>,
+    pub contents: String,
+}
+
+/// A filled-in form field, named by its fully qualified name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FormField {
+    pub name: String,
+    pub value: String,
+}
+
+impl PageTexts {
+    pub fn is_empty(&self) -> bool {
+        self.pages.iter().all(|p| p.trim().is_empty())
+    }
+}
+
+/// Why the text of an encrypted PDF could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// None of the known passwords opens the document
+    PasswordRequired,
+    /// The document uses an encryption scheme the PDF library cannot decrypt
+    Unsupported,
+}
+
+impl std::fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EncryptionError::PasswordRequired => write!(f, "the PDF is password protected"),
+            EncryptionError::Unsupported => write!(f, "the PDF uses an unsupported encryption scheme"),
+        }
+    }
+}
+
+impl std::error::Error for EncryptionError {}
+
+/// Extract text from every page of the PDF at `path`, trying `passwords` if
+/// it is encrypted. Decrypted content only ever lives in memory.
+/// Mirrors `pdf_extract::extract_text` but keeps page boundaries, which the
+/// plain text output discards. With `options.tables`, the position of each
+/// word is kept too and used to reconstruct tables.
+pub fn extract_pages(path: &Path, passwords: &[String], options: ExtractOptions) -> Result<PageTexts> {
+    let (doc, password_protected) = open_document(path, passwords)?;
+
+    let mut output = PageTextOutput { detect_tables: options.tables, ..Default::default() };
+    pdf_extract::output_doc(&doc, &mut output)?;
+    let attachments = match options.attachments {
+        Some(max_size) => attachments::extract_attachments(&doc, max_size),
+        None => Vec::new(),
+    };
+    Ok(PageTexts {
+        attachments,
+        password_protected,
+        pages: output.pages,
+        annotations: extract_annotations(&doc),
+        form_fields: extract_form_fields(&doc),
+        outline: outline::extract_outline(&doc),
+        tables: output.tables,
+        links: links::extract_uri_links(&doc),
+    })
+}
+
+/// Annotations with text of their own. Popups repeat the text of their parent
+/// and links and form widgets carry none worth searching, so they are left out.
+pub fn extract_annotations(doc: &Document) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    for (page, page_id) in doc.get_pages() {
+        for annotation in doc.get_page_annotations(page_id).unwrap_or_default() {
+            let kind = annotation
+                .get(b"Subtype")
+                .and_then(Object::as_name_str)
+                .unwrap_or_default();
+            if matches!(kind, "Popup" | "Link" | "Widget") {
+                continue;
+            }
+            let contents = text_value(doc, annotation, b"Contents").unwrap_or_default();
+            if contents.trim().is_empty() {
+                continue;
+            }
+            annotations.push(Annotation {
+                page,
+                kind: kind.to_string(),
+                author: text_value(doc, annotation, b"T").filter(|author| !author.trim().is_empty()),
+                contents: contents.trim().to_string(),
+            });
+        }
+    }
+    annotations
+}
+
+/// Fields of the document's AcroForm that have a value
+pub fn extract_form_fields(doc: &Document) -> Vec<FormField> {
+    let mut fields = Vec::new();
+    if let Ok(roots) = doc
+        .catalog()
+        .and_then(|catalog| catalog.get_deref(b"AcroForm", doc))
+        .and_then(Object::as_dict)
+        .and_then(|form| form.get_deref(b"Fields", doc))
+        .and_then(Object::as_array)
+    {
+        for field in roots {
+            collect_form_field(doc, field, "", 0, &mut fields);
+        }
+    }
+    fields
+}
+
+/// Add `field` and its kids; names are joined with dots as in `address.city`
+fn collect_form_field(doc: &Document, field: &Object, parent_name: &str, depth: usize, fields: &mut Vec<FormField>) {
+    if depth > MAX_FIELD_DEPTH {
+        return;
+    }
+    let Ok((_, Object::Dictionary(field))) = doc.dereference(field) else {
+        return;
+    };
+
+    let name = match text_value(doc, field, b"T") {
+        Some(partial) if parent_name.is_empty() => partial,
+        Some(partial) => format!("{}.{}", parent_name, partial),
+        None => parent_name.to_string(),
+    };
+
+    if let Some(value) = field.get_deref(b"V", doc).ok().and_then(|value| field_value(doc, value)) {
+        // Widgets of a field share its name; the value is recorded once
+        if !name.is_empty() && !fields.iter().any(|f: &FormField| f.name == name) {
+            fields.push(FormField { name: name.clone(), value });
+        }
+    }
+
+    if let Ok(kids) = field.get_deref(b"Kids", doc).and_then(Object::as_array) {
+        for kid in kids {
+            collect_form_field(doc, kid, &name, depth + 1, fields);
+        }
+    }
+}
+
+/// Readable value of a field: text, a choice, or the state of a checkbox
+fn field_value(doc: &Document, value: &Object) -> Option<String> {
+    let value = match value {
+        Object::String(bytes, _) => decode_text_string(bytes),
+        // Unchecked boxes and unselected radio buttons say nothing
+        Object::Name(name) if name.as_slice() == b"Off" => return None,
+        Object::Name(name) => String::from_utf8_lossy(name).to_string(),
+        Object::Array(items) => items
+            .iter()
+            .filter_map(|item| doc.dereference(item).ok().and_then(|(_, item)| field_value(doc, item)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => return None,
+    };
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn text_value(doc: &Document, dict: &Dictionary, key: &[u8]) -> Option<String> {
+    dict.get_deref(key, doc)
+        .and_then(Object::as_str)
+        .ok()
+        .map(decode_text_string)
+}
+
+/// Decode a PDF text string, which is UTF-16BE with a byte order mark or
+/// otherwise (close enough to) Latin-1
+pub fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(b"\xFE\xFF") {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else if let Some(utf8) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        String::from_utf8_lossy(utf8).to_string()
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    }
+}
+
+/// Load the PDF at `path`, decrypting it with one of `passwords` if needed,
+/// and tell whether one of them was needed to open it
+fn open_document(path: &Path, passwords: &[String]) -> Result<(Document, bool)> {
+    let mut doc = Document::load(path)?;
+    let password_protected = doc.is_encrypted() && decrypt(&mut doc, passwords)?;
+    Ok((doc, password_protected))
+}
+
+/// Decrypt `doc` with the first password that opens it, returning whether
+/// that was one of `passwords` rather than the empty one
+fn decrypt(doc: &mut Document, passwords: &[String]) -> Result<bool> {
+    // Many PDFs are "encrypted" with an empty user password
+    let candidates = std::iter::once("").chain(passwords.iter().map(String::as_str));
+
+    for password in candidates {
+        match doc.decrypt(password) {
+            Ok(()) => return Ok(!password.is_empty()),
+            Err(Error::Decryption(DecryptionError::IncorrectPassword)) => continue,
+            Err(Error::Decryption(DecryptionError::UnsupportedEncryption)) => {
+                return Err(EncryptionError::Unsupported.into());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(EncryptionError::PasswordRequired.into())
+}
+
+/// `OutputDev` that applies the same spacing heuristics as
+/// `pdf_extract::PlainTextOutput` while collecting each page separately
+#[derive(Default)]
+struct PageTextOutput {
+    pages: Vec<String>,
+    current: String,
+    page_height: f64,
+    last_end: f64,
+    last_y: f64,
+    first_char: bool,
+    /// Collect the words of each page with their position to find tables
+    detect_tables: bool,
+    words: Vec<PlacedWord>,
+    /// The last glyph was whitespace, so the next one starts a word
+    word_ended: bool,
+    tables: Vec<Table>,
+}
+
+impl PageTextOutput {
+    /// Add a glyph to the word being built, or start a new word when there is
+    /// a gap before it or it is on another line
+    fn place_char(&mut self, x: f64, end: f64, y: f64, size: f64, char: &str) {
+        if char.trim().is_empty() {
+            self.word_ended = true;
+            return;
+        }
+        match self.words.last_mut() {
+            Some(word)
+                if !self.word_ended
+                    && (y - word.y).abs() <= size * 0.5
+                    && x <= word.x1 + size * 0.1
+                    && x >= word.x1 - size =>
+            {
+                word.x1 = word.x1.max(end);
+                word.text.push_str(char);
+            }
+            _ => self.words.push(PlacedWord { x0: x, x1: end, y, size, text: char.to_string() }),
+        }
+        self.word_ended = false;
+    }
+}
+
+impl OutputDev for PageTextOutput {
+    fn begin_page(&mut self, _page_num: u32, media_box: &MediaBox, _art_box: Option<(f64, f64, f64, f64)>) -> Result<(), OutputError> {
+        self.current.clear();
+        self.page_height = media_box.ury - media_box.lly;
+        self.last_end = 100000.;
+        self.last_y = 0.;
+        self.first_char = false;
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        self.pages.push(std::mem::take(&mut self.current));
+        if self.detect_tables {
+            let words = std::mem::take(&mut self.words);
+            self.tables.extend(tables::detect_tables(self.pages.len() as u32, &words));
+        }
+        Ok(())
+    }
+
+    fn output_character(&mut self, trm: &Transform, width: f64, _spacing: f64, font_size: f64, char: &str) -> Result<(), OutputError> {
+        // Flip the y axis so that y grows downwards, as in PlainTextOutput
+        let x = trm.m31;
+        let y = self.page_height - trm.m32;
+        let scaled_x = font_size * trm.m11 + font_size * trm.m21;
+        let scaled_y = font_size * trm.m12 + font_size * trm.m22;
+        let transformed_font_size = (scaled_x * scaled_y).abs().sqrt();
+
+        if self.first_char {
+            if (y - self.last_y).abs() > transformed_font_size * 1.5 {
+                self.current.push('\n');
+            }
+
+            // Moved to the left and down: a new line
+            if x < self.last_end && (y - self.last_y).abs() > transformed_font_size * 0.5 {
+                self.current.push('\n');
+            }
+
+            if x > self.last_end + transformed_font_size * 0.1 {
+                self.current.push(' ');
+            }
+        }
+
+        self.current.push_str(char);
+        self.first_char = false;
+        self.last_y = y;
+        self.last_end = x + width * transformed_font_size;
+        if self.detect_tables {
+            self.place_char(x, self.last_end, y, transformed_font_size, char);
+        }
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.first_char = true;
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf_extract::StringFormat;
+
+    fn text(value: &str) -> Object {
+        Object::String(value.as_bytes().to_vec(), StringFormat::Literal)
+    }
+
+    fn name(value: &str) -> Object {
+        Object::Name(value.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_extract_annotations_and_form_fields() {
+        let mut doc = Document::with_version("1.7");
+
+        let mut note = Dictionary::new();
+        note.set("Subtype", name("Text"));
+        note.set("T", text("Alice"));
+        note.set("Contents", text(" Check these numbers "));
+        let mut popup = Dictionary::new();
+        popup.set("Subtype", name("Popup"));
+        popup.set("Contents", text("Check these numbers"));
+        let mut highlight = Dictionary::new();
+        highlight.set("Subtype", name("Highlight"));
+        let annots = vec![
+            Object::Reference(doc.add_object(note)),
+            Object::Reference(doc.add_object(popup)),
+            Object::Reference(doc.add_object(highlight)),
+        ];
+
+        let pages_id = doc.new_object_id();
+        let mut page = Dictionary::new();
+        page.set("Type", name("Page"));
+        page.set("Parent", Object::Reference(pages_id));
+        page.set("Annots", Object::Array(annots));
+        let page_id = doc.add_object(page);
+        let mut pages = Dictionary::new();
+        pages.set("Type", name("Pages"));
+        pages.set("Kids", Object::Array(vec![Object::Reference(page_id)]));
+        pages.set("Count", Object::Integer(1));
+        doc.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let mut city = Dictionary::new();
+        city.set("T", text("city"));
+        city.set("V", text("Rotterdam"));
+        let mut address = Dictionary::new();
+        address.set("T", text("address"));
+        address.set("Kids", Object::Array(vec![Object::Reference(doc.add_object(city))]));
+        let mut agreed = Dictionary::new();
+        agreed.set("T", text("agreed"));
+        agreed.set("V", name("Off"));
+        let mut form = Dictionary::new();
+        form.set(
+            "Fields",
+            Object::Array(vec![Object::Reference(doc.add_object(address)), Object::Reference(doc.add_object(agreed))]),
+        );
+
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", name("Catalog"));
+        catalog.set("Pages", Object::Reference(pages_id));
+        catalog.set("AcroForm", Object::Dictionary(form));
+        let catalog_id = doc.add_object(catalog);
+        doc.trailer.set("Root", Object::Reference(catalog_id));
+
+        // The popup repeats the note and the highlight has no comment
+        assert_eq!(
+            extract_annotations(&doc),
+            vec![Annotation {
+                page: 1,
+                kind: "Text".to_string(),
+                author: Some("Alice".to_string()),
+                contents: "Check these numbers".to_string(),
+            }]
+        );
+        // Unchecked boxes have no value worth indexing
+        assert_eq!(
+            extract_form_fields(&doc),
+            vec![FormField { name: "address.city".to_string(), value: "Rotterdam".to_string() }]
+        );
+    }
+}
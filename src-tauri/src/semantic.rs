@@ -0,0 +1,313 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Target size of a chunk of document text, in chars
+pub const CHUNK_CHARS: usize = 1000;
+/// Number of chars shared by consecutive chunks so sentences are not split
+pub const CHUNK_OVERLAP_CHARS: usize = 200;
+/// Upper bound on chunks per document, keeping huge documents from dominating the index
+pub const MAX_CHUNKS_PER_DOCUMENT: usize = 500;
+/// Constant `k` of reciprocal rank fusion
+/// Reference: Cormack et al., "Reciprocal Rank Fusion outperforms Condorcet and
+/// individual Rank Learning Methods" (SIGIR 2009)
+pub const RRF_K: f64 = 60.0;
+
+/// How search results are ranked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// BM25 full-text ranking only
+    #[default]
+    Keyword,
+    /// Vector similarity only
+    Semantic,
+    /// BM25 and vector rankings fused with reciprocal rank fusion
+    Hybrid,
+}
+
+/// A span of document text and its embedding
+#[derive(Debug, Clone)]
+pub struct ChunkEmbedding {
+    /// Char offset of the chunk in the document content
+    pub start: usize,
+    /// Char offset one past the end of the chunk
+    pub end: usize,
+    pub vector: Vec<f32>,
+}
+
+/// Static word-embedding model evaluated entirely on the CPU
+/// Loads vectors in the word2vec/GloVe text format (`word v1 v2 ... vn` per
+/// line, with an optional `count dim` header) from a local file; a text is
+/// embedded as the normalized mean of its known word vectors. Nothing is
+/// downloaded, so the app stays fully offline.
+pub struct EmbeddingModel {
+    dim: usize,
+    fingerprint: String,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+impl EmbeddingModel {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open embedding model {}", path.display()))?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self> {
+        let mut vectors = HashMap::new();
+        let mut dim = 0;
+        let mut hasher = Sha256::new();
+
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = line?;
+            hasher.update(line.as_bytes());
+            hasher.update(b"\n");
+            let mut parts = line.split_whitespace();
+            let word = match parts.next() {
+                Some(word) => word,
+                None => continue,
+            };
+
+            let values: Vec<f32> = match parts.map(|v| v.parse::<f32>()).collect() {
+                Ok(values) => values,
+                Err(_) => {
+                    log::debug!("Skipping malformed embedding line {}", line_no + 1);
+                    continue;
+                }
+            };
+
+            // word2vec text files start with a "count dim" header
+            if line_no == 0 && values.len() == 1 && word.parse::<usize>().is_ok() {
+                continue;
+            }
+
+            if dim == 0 {
+                dim = values.len();
+            }
+            if values.len() != dim || dim == 0 {
+                log::debug!("Skipping embedding line {} with wrong dimension", line_no + 1);
+                continue;
+            }
+
+            vectors.insert(word.to_lowercase(), values);
+        }
+
+        if vectors.is_empty() {
+            anyhow::bail!("Embedding model contains no vectors");
+        }
+
+        let fingerprint = format!("{:x}", hasher.finalize())[..16].to_string();
+        log::info!("Loaded embedding model {}: {} words, {} dimensions", fingerprint, vectors.len(), dim);
+        Ok(Self { dim, fingerprint, vectors })
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Hash of the model file, stored with every embedding so vectors made by
+    /// another model are embedded again instead of being compared with these
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Embed `text` as a unit vector, or `None` when no word is known to the model
+    pub fn embed(&self, text: &str) -> Option<Vec<f32>> {
+        let mut sum = vec![0f32; self.dim];
+        let mut known = 0;
+
+        for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+            if let Some(vector) = self.vectors.get(&word.to_lowercase()) {
+                for (acc, v) in sum.iter_mut().zip(vector) {
+                    *acc += v;
+                }
+                known += 1;
+            }
+        }
+
+        if known == 0 {
+            return None;
+        }
+
+        normalize(&mut sum);
+        Some(sum)
+    }
+
+    /// Chunk `content` and embed every chunk
+    pub fn embed_document(&self, content: &str) -> Vec<ChunkEmbedding> {
+        chunk_text(content, CHUNK_CHARS, CHUNK_OVERLAP_CHARS)
+            .into_iter()
+            .take(MAX_CHUNKS_PER_DOCUMENT)
+            .filter_map(|(start, end, text)| {
+                self.embed(&text).map(|vector| ChunkEmbedding { start, end, vector })
+            })
+            .collect()
+    }
+}
+
+/// Split `text` into overlapping chunks of roughly `chunk_chars` chars,
+/// breaking only at whitespace. Returns (start, end, text) in char offsets.
+pub fn chunk_text(text: &str, chunk_chars: usize, overlap_chars: usize) -> Vec<(usize, usize, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + chunk_chars).min(chars.len());
+        if end < chars.len() {
+            // Back off to the last whitespace so words stay intact
+            if let Some(ws) = chars[start..end].iter().rposition(|c| c.is_whitespace()) {
+                if ws > 0 {
+                    end = start + ws;
+                }
+            }
+        }
+
+        chunks.push((start, end, chars[start..end].iter().collect()));
+
+        if end >= chars.len() {
+            break;
+        }
+
+        // Step back by the overlap, then forward to the next word start
+        let mut next = end.saturating_sub(overlap_chars).max(start + 1);
+        while next < end && !chars[next - 1].is_whitespace() {
+            next += 1;
+        }
+        while next < chars.len() && chars[next].is_whitespace() {
+            next += 1;
+        }
+        start = next;
+    }
+
+    chunks
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in vector.iter_mut() {
+            *v /= norm;
+        }
+    }
+}
+
+/// Cosine similarity of two unit vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+pub fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Fuse several rankings of the same items with reciprocal rank fusion
+/// Items are returned best first with their fused score
+pub fn reciprocal_rank_fusion(rankings: &[Vec<String>]) -> Vec<(String, f64)> {
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    let mut first_seen: Vec<&str> = Vec::new();
+
+    for ranking in rankings {
+        for (rank, item) in ranking.iter().enumerate() {
+            let score = scores.entry(item.as_str()).or_insert_with(|| {
+                first_seen.push(item.as_str());
+                0.0
+            });
+            *score += 1.0 / (RRF_K + rank as f64 + 1.0);
+        }
+    }
+
+    let mut fused: Vec<(String, f64)> = first_seen
+        .into_iter()
+        .map(|item| (item.to_string(), scores[item]))
+        .collect();
+    // Stable sort keeps first-seen order for ties
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_model() -> EmbeddingModel {
+        let data = "4 3\n\
+                    car 1.0 0.0 0.0\n\
+                    automobile 0.9 0.1 0.0\n\
+                    invoice 0.0 1.0 0.0\n\
+                    payment 0.0 0.9 0.1\n";
+        EmbeddingModel::from_reader(data.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_load_model_skips_header() {
+        let model = test_model();
+        assert_eq!(model.dim(), 3);
+        assert_eq!(model.vectors.len(), 4);
+    }
+
+    #[test]
+    fn test_fingerprint_identifies_the_vectors() {
+        let retrained = EmbeddingModel::from_reader("car 0.0 1.0 0.0\n".as_bytes()).unwrap();
+        assert_eq!(test_model().fingerprint(), test_model().fingerprint());
+        assert_ne!(test_model().fingerprint(), retrained.fingerprint());
+    }
+
+    #[test]
+    fn test_embed_matches_paraphrases() {
+        let model = test_model();
+        let car = model.embed("Car").unwrap();
+        let automobile = model.embed("an automobile").unwrap();
+        let invoice = model.embed("invoice").unwrap();
+
+        assert!(cosine_similarity(&car, &automobile) > 0.9);
+        assert!(cosine_similarity(&car, &invoice) < 0.1);
+        assert!(model.embed("unknown words only").is_none());
+    }
+
+    #[test]
+    fn test_chunk_text_overlaps_at_word_boundaries() {
+        let text = "one two three four five six seven eight nine ten";
+        let chunks = chunk_text(text, 20, 8);
+
+        assert!(chunks.len() > 1);
+        let chars: Vec<char> = text.chars().collect();
+        for (start, end, chunk) in &chunks {
+            assert_eq!(chars[*start..*end].iter().collect::<String>(), *chunk);
+            assert!(!chunk.starts_with(' '));
+        }
+        assert_eq!(chunks.last().unwrap().1, chars.len());
+        // Consecutive chunks overlap
+        assert!(chunks[1].0 < chunks[0].1);
+    }
+
+    #[test]
+    fn test_blob_roundtrip() {
+        let vector = vec![0.25f32, -1.5, 3.0];
+        assert_eq!(blob_to_vector(&vector_to_blob(&vector)), vector);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let keyword = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let semantic = vec!["b".to_string(), "d".to_string(), "c".to_string()];
+        let fused = reciprocal_rank_fusion(&[keyword, semantic]);
+
+        let order: Vec<&str> = fused.iter().map(|(item, _)| item.as_str()).collect();
+        assert_eq!(order, vec!["b", "c", "a", "d"]);
+    }
+}
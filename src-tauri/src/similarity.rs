@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+/// Number of top TF-IDF terms fed back as the "more like this" query
+pub const MAX_QUERY_TERMS: usize = 25;
+
+const MIN_TERM_LEN: usize = 3;
+const MAX_TERM_LEN: usize = 40;
+
+/// Common English words that carry no topical signal
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "all", "also", "and", "any", "are", "because", "been", "before", "being",
+    "between", "both", "but", "can", "could", "did", "does", "each", "for", "from", "had", "has",
+    "have", "her", "here", "him", "his", "how", "into", "its", "may", "more", "most", "not", "now",
+    "only", "other", "our", "out", "over", "page", "same", "shall", "she", "should", "some",
+    "such", "than", "that", "the", "their", "them", "then", "there", "these", "they", "this",
+    "those", "through", "under", "upon", "very", "was", "were", "what", "when", "where", "which",
+    "while", "who", "will", "with", "would", "you", "your",
+];
+
+/// Count how often each indexable term occurs in `text`, highest count first
+/// Reference: "Introduction to Information Retrieval" Ch. 6.2 - Term frequency
+pub fn term_frequencies(text: &str) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let len = word.chars().count();
+        if !(MIN_TERM_LEN..=MAX_TERM_LEN).contains(&len) {
+            continue;
+        }
+        if word.chars().all(|c| c.is_numeric()) {
+            continue;
+        }
+
+        let term = word.to_lowercase();
+        if STOP_WORDS.contains(&term.as_str()) {
+            continue;
+        }
+        *counts.entry(term).or_insert(0) += 1;
+    }
+
+    let mut terms: Vec<(String, usize)> = counts.into_iter().collect();
+    terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    terms
+}
+
+/// TF-IDF weight of a term, using BM25's smoothed inverse document frequency
+/// Reference: "Introduction to Information Retrieval" Ch. 11.4.3 - Okapi BM25
+pub fn tf_idf(term_freq: usize, doc_freq: i64, total_docs: i64) -> f64 {
+    let df = doc_freq as f64;
+    let n = total_docs as f64;
+    let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+    (1.0 + (term_freq as f64).ln()) * idf
+}
+
+/// Keep the `max_terms` terms with the highest weight, heaviest first
+pub fn top_weighted_terms(mut weighted: Vec<(String, f64)>, max_terms: usize) -> Vec<(String, f64)> {
+    weighted.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0))
+    });
+    weighted.truncate(max_terms);
+    weighted
+}
+
+/// Build an FTS5 OR query of the terms, used to mark them in snippets
+pub fn build_or_query(terms: &[(String, f64)]) -> Option<String> {
+    if terms.is_empty() {
+        return None;
+    }
+
+    Some(terms.iter().map(|(term, _)| quote_term(term)).collect::<Vec<_>>().join(" OR "))
+}
+
+/// Order documents by their summed term scores, best first, keeping `limit`
+pub fn rank_by_score(scores: HashMap<i64, f64>, limit: usize) -> Vec<i64> {
+    let mut ranked: Vec<(i64, f64)> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0))
+    });
+    ranked.into_iter().take(limit).map(|(id, _)| id).collect()
+}
+
+/// Quote a term so FTS5 treats it as a plain string, never as an operator
+pub fn quote_term(term: &str) -> String {
+    format!("\"{}\"", term.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_term_frequencies() {
+        let text = "Grounding the panel: grounding wires, grounding rods and the 2024 panel.";
+        let terms = term_frequencies(text);
+
+        assert_eq!(terms[0], ("grounding".to_string(), 3));
+        assert_eq!(terms[1], ("panel".to_string(), 2));
+        // Stop words, short words and numbers are ignored
+        assert!(terms.iter().all(|(t, _)| t != "the" && t != "and" && t != "2024"));
+    }
+
+    #[test]
+    fn test_tf_idf_prefers_rare_terms() {
+        assert!(tf_idf(1, 1, 1000) > tf_idf(1, 500, 1000));
+        assert!(tf_idf(10, 5, 1000) > tf_idf(1, 5, 1000));
+    }
+
+    #[test]
+    fn test_top_weighted_terms() {
+        let weighted = vec![
+            ("low".to_string(), 0.5),
+            ("high".to_string(), 3.0),
+            ("mid".to_string(), 1.0),
+        ];
+        let top = top_weighted_terms(weighted, 2);
+        assert_eq!(top, vec![("high".to_string(), 3.0), ("mid".to_string(), 1.0)]);
+        assert_eq!(build_or_query(&top), Some("\"high\" OR \"mid\"".to_string()));
+        assert_eq!(build_or_query(&[]), None);
+    }
+
+    #[test]
+    fn test_rank_by_score() {
+        let scores = HashMap::from([(1, 0.5), (2, 4.0), (3, 1.5)]);
+        assert_eq!(rank_by_score(scores.clone(), 10), vec![2, 3, 1]);
+        assert_eq!(rank_by_score(scores, 1), vec![2]);
+    }
+}
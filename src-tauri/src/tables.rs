@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+
+/// Gap between two words, in multiples of the font size, above which they
+/// are in different cells. Word spacing is usually a quarter of that.
+const CELL_GAP: f64 = 1.0;
+
+/// Words whose baselines differ by less than this share of the font size are
+/// on the same line
+const SAME_LINE: f64 = 0.5;
+
+/// Lines further apart than this many font sizes do not belong to the same table
+const MAX_ROW_GAP: f64 = 3.0;
+
+/// Fewest rows, header included, for aligned lines to count as a table
+const MIN_ROWS: usize = 3;
+
+/// Fewest columns of a table
+const MIN_COLUMNS: usize = 2;
+
+/// Cells of a table are short; more words than this per cell on average is
+/// more likely text set in several columns
+const MAX_WORDS_PER_CELL: f64 = 4.0;
+
+/// A table reconstructed from the position of the words on a page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    /// 1-based page number
+    pub page: u32,
+    /// Cells row by row, every row with one cell per column
+    pub rows: Vec<Vec<String>>,
+}
+
+/// A word as placed on the page, in points with y growing downwards
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedWord {
+    pub x0: f64,
+    pub x1: f64,
+    /// Baseline
+    pub y: f64,
+    pub size: f64,
+    pub text: String,
+}
+
+/// A run of words on a line, separated from the next by a wide gap
+#[derive(Debug)]
+struct Cell {
+    x0: f64,
+    x1: f64,
+    words: usize,
+    text: String,
+}
+
+#[derive(Debug)]
+struct Line {
+    y: f64,
+    size: f64,
+    cells: Vec<Cell>,
+}
+
+/// Find the tables among the words of page `page`: runs of consecutive lines
+/// that are split into cells lining up in the same columns
+pub fn detect_tables(page: u32, words: &[PlacedWord]) -> Vec<Table> {
+    let lines = group_lines(words);
+    let mut tables = Vec::new();
+    let mut run: Vec<&Line> = Vec::new();
+    for line in &lines {
+        let continues_run = line.cells.len() >= MIN_COLUMNS
+            && run
+                .last()
+                .is_none_or(|last| line.y - last.y <= MAX_ROW_GAP * last.size.max(line.size));
+        if !continues_run {
+            tables.extend(table_from_rows(page, &run));
+            run.clear();
+        }
+        if line.cells.len() >= MIN_COLUMNS {
+            run.push(line);
+        }
+    }
+    tables.extend(table_from_rows(page, &run));
+    tables
+}
+
+/// Lines of the page from top to bottom, each cut into cells at wide gaps
+fn group_lines(words: &[PlacedWord]) -> Vec<Line> {
+    let mut sorted: Vec<&PlacedWord> = words.iter().filter(|w| !w.text.trim().is_empty()).collect();
+    sorted.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x0.total_cmp(&b.x0)));
+
+    let mut lines: Vec<Vec<&PlacedWord>> = Vec::new();
+    for word in sorted {
+        match lines.last_mut() {
+            Some(line) if (word.y - line[0].y).abs() <= SAME_LINE * line[0].size.max(word.size) => line.push(word),
+            _ => lines.push(vec![word]),
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|mut words| {
+            words.sort_by(|a, b| a.x0.total_cmp(&b.x0));
+            let size = words.iter().map(|w| w.size).fold(0.0, f64::max);
+            let mut cells: Vec<Cell> = Vec::new();
+            for word in &words {
+                match cells.last_mut() {
+                    Some(cell) if word.x0 - cell.x1 <= CELL_GAP * size => {
+                        cell.x1 = cell.x1.max(word.x1);
+                        cell.words += 1;
+                        cell.text.push(' ');
+                        cell.text.push_str(word.text.trim());
+                    }
+                    _ => cells.push(Cell {
+                        x0: word.x0,
+                        x1: word.x1,
+                        words: 1,
+                        text: word.text.trim().to_string(),
+                    }),
+                }
+            }
+            Line { y: words[0].y, size, cells }
+        })
+        .collect()
+}
+
+/// Lay out the cells of `rows` in columns, or give up when they do not line up
+fn table_from_rows(page: u32, rows: &[&Line]) -> Option<Table> {
+    if rows.len() < MIN_ROWS {
+        return None;
+    }
+    let cells: Vec<&Cell> = rows.iter().flat_map(|row| &row.cells).collect();
+    let words: usize = cells.iter().map(|cell| cell.words).sum();
+    if words as f64 / cells.len() as f64 > MAX_WORDS_PER_CELL {
+        return None;
+    }
+
+    // Columns are the horizontal extents covered by overlapping cells
+    let mut extents: Vec<(f64, f64)> = cells.iter().map(|cell| (cell.x0, cell.x1)).collect();
+    extents.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut columns: Vec<(f64, f64)> = Vec::new();
+    for (x0, x1) in extents {
+        match columns.last_mut() {
+            Some(column) if x0 <= column.1 => column.1 = column.1.max(x1),
+            _ => columns.push((x0, x1)),
+        }
+    }
+    if columns.len() < MIN_COLUMNS {
+        return None;
+    }
+
+    let mut table = Table { page, rows: Vec::with_capacity(rows.len()) };
+    for row in rows {
+        let mut values = vec![String::new(); columns.len()];
+        for cell in &row.cells {
+            let column = columns.iter().position(|&(x0, x1)| cell.x0 >= x0 && cell.x1 <= x1)?;
+            // Two cells of a row in one column: these lines are not a grid
+            if !values[column].is_empty() {
+                return None;
+            }
+            values[column] = cell.text.clone();
+        }
+        table.rows.push(values);
+    }
+    Some(table)
+}
+
+/// Searchable text of a table: one row per line, cells separated by ` | `
+pub fn table_text(table: &Table) -> String {
+    table
+        .rows
+        .iter()
+        .map(|row| row.join(" | "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Rows of a table as CSV (RFC 4180), quoting cells where needed. Cells that
+/// a spreadsheet would run as a formula are prefixed with `'`, since the text
+/// comes from whatever PDF was indexed.
+pub fn to_csv(rows: &[Vec<String>]) -> String {
+    let mut csv = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| {
+                let cell = if cell.starts_with(['=', '+', '-', '@']) {
+                    format!("'{}", cell)
+                } else {
+                    cell.clone()
+                };
+                if cell.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", cell.replace('"', "\"\""))
+                } else {
+                    cell
+                }
+            })
+            .collect();
+        csv.push_str(&cells.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Words of `text` laid out from `x` on the line at `y`, in a 10pt font
+    /// where each character is 5pt wide
+    fn line(x: f64, y: f64, text: &str) -> Vec<PlacedWord> {
+        let mut words = Vec::new();
+        let mut x = x;
+        for word in text.split(' ') {
+            let width = word.chars().count() as f64 * 5.0;
+            words.push(PlacedWord { x0: x, x1: x + width, y, size: 10.0, text: word.to_string() });
+            x += width + 2.5;
+        }
+        words
+    }
+
+    #[test]
+    fn test_detect_tables() {
+        let mut words = line(50.0, 50.0, "Price list valid from May");
+        for (y, row) in [(100.0, ["Model", "Weight", "Price"]), (114.0, ["X100 Pro", "12 kg", "1,200"]), (128.0, ["X200", "15 kg", "1,450"])] {
+            words.extend(line(50.0, y, row[0]));
+            words.extend(line(150.0, y, row[1]));
+            words.extend(line(250.0, y, row[2]));
+        }
+        // A row missing its middle cell keeps its other cells in place
+        words.extend(line(50.0, 142.0, "X300"));
+        words.extend(line(250.0, 142.0, "on request"));
+        words.extend(line(50.0, 250.0, "Prices exclude shipping and taxes"));
+
+        let tables = detect_tables(3, &words);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].page, 3);
+        assert_eq!(
+            tables[0].rows,
+            vec![
+                vec!["Model", "Weight", "Price"],
+                vec!["X100 Pro", "12 kg", "1,200"],
+                vec!["X200", "15 kg", "1,450"],
+                vec!["X300", "", "on request"],
+            ]
+        );
+        assert_eq!(table_text(&tables[0]).lines().nth(1), Some("X100 Pro | 12 kg | 1,200"));
+    }
+
+    #[test]
+    fn test_prose_in_two_columns_is_not_a_table() {
+        let mut words = Vec::new();
+        for i in 0..6 {
+            let y = 100.0 + i as f64 * 14.0;
+            words.extend(line(50.0, y, "the quick brown fox jumps over"));
+            words.extend(line(320.0, y, "a lazy dog in the afternoon sun"));
+        }
+        assert!(detect_tables(1, &words).is_empty());
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let rows = vec![
+            vec!["Model".to_string(), "Price".to_string()],
+            vec!["X100 \"Pro\"".to_string(), "1,200".to_string()],
+        ];
+        assert_eq!(to_csv(&rows), "Model,Price\r\n\"X100 \"\"Pro\"\"\",\"1,200\"\r\n");
+    }
+
+    #[test]
+    fn test_to_csv_neutralizes_formulas() {
+        let rows = vec![vec![
+            "=HYPERLINK(\"http://evil.example\",\"x\")".to_string(),
+            "+1".to_string(),
+            "-5".to_string(),
+            "@SUM(A1)".to_string(),
+            "a=b".to_string(),
+        ]];
+        assert_eq!(
+            to_csv(&rows),
+            "\"'=HYPERLINK(\"\"http://evil.example\"\",\"\"x\"\")\",'+1,'-5,'@SUM(A1),a=b\r\n"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::{HashMap, HashSet};
+
+/// Running headers and footers are only looked for in documents with at
+/// least this many pages of text
+const MIN_PAGES_FOR_RUNNING_LINES: usize = 3;
+
+/// Lines at the top and at the bottom of a page that may be a running header
+/// or footer
+const EDGE_LINES: usize = 2;
+
+/// A line is a running header or footer when it is on the edge of at least
+/// this share of the pages
+const RUNNING_LINE_RATIO: f64 = 0.6;
+
+/// Clean the text of each page of a document: drop running headers and
+/// footers, then repair what text extraction breaks within the pages
+pub fn clean_pages(pages: &[String]) -> Vec<String> {
+    remove_running_lines(pages).iter().map(|page| clean_text(page)).collect()
+}
+
+/// Expand ligatures and compatibility characters, then rejoin words
+/// hyphenated across line breaks
+pub fn clean_text(text: &str) -> String {
+    dehyphenate(&expand_compatibility_chars(text))
+}
+
+/// Remove lines repeated at the top or bottom of most pages, such as running
+/// titles and page numbers. Digits are ignored when comparing lines, so
+/// "Page 3 of 10" and "Page 4 of 10" count as the same footer.
+fn remove_running_lines(pages: &[String]) -> Vec<String> {
+    let edges: Vec<Vec<usize>> = pages.iter().map(|page| edge_line_indices(page)).collect();
+    let pages_with_text = edges.iter().filter(|lines| !lines.is_empty()).count();
+    if pages_with_text < MIN_PAGES_FOR_RUNNING_LINES {
+        return pages.to_vec();
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (page, lines) in pages.iter().zip(&edges) {
+        let page_lines: Vec<&str> = page.lines().collect();
+        let keys: HashSet<String> = lines.iter().map(|&i| line_key(page_lines[i])).collect();
+        for key in keys {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+    let threshold = ((pages_with_text as f64 * RUNNING_LINE_RATIO).ceil() as usize).max(MIN_PAGES_FOR_RUNNING_LINES);
+    let running: HashSet<String> = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= threshold)
+        .map(|(key, _)| key)
+        .collect();
+    if running.is_empty() {
+        return pages.to_vec();
+    }
+
+    pages
+        .iter()
+        .zip(&edges)
+        .map(|(page, lines)| {
+            let page_lines: Vec<&str> = page.lines().collect();
+            let removed: HashSet<usize> = lines
+                .iter()
+                .copied()
+                .filter(|&i| running.contains(&line_key(page_lines[i])))
+                .collect();
+            page_lines
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !removed.contains(i))
+                .map(|(_, line)| *line)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect()
+}
+
+/// Indices of the first and last few non-empty lines of a page
+fn edge_line_indices(page: &str) -> Vec<usize> {
+    let non_empty: Vec<usize> = page
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, _)| i)
+        .collect();
+    let mut edges: Vec<usize> = non_empty.iter().take(EDGE_LINES).copied().collect();
+    for &i in non_empty.iter().rev().take(EDGE_LINES) {
+        if !edges.contains(&i) {
+            edges.push(i);
+        }
+    }
+    edges
+}
+
+/// A line as compared against the other pages: lowercase, single-spaced,
+/// with each run of digits replaced by `#`
+fn line_key(line: &str) -> String {
+    let mut key = String::with_capacity(line.len());
+    let mut in_number = false;
+    for word in line.split_whitespace() {
+        if !key.is_empty() {
+            key.push(' ');
+            in_number = false;
+        }
+        for c in word.chars() {
+            if c.is_ascii_digit() {
+                if !in_number {
+                    key.push('#');
+                }
+                in_number = true;
+            } else {
+                key.extend(c.to_lowercase());
+                in_number = false;
+            }
+        }
+    }
+    key
+}
+
+/// Replace typographic ligatures and other compatibility characters by their
+/// plain equivalents, so "ﬁnance" matches a search for "finance"
+fn expand_compatibility_chars(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\u{FB00}' => expanded.push_str("ff"),
+            '\u{FB01}' => expanded.push_str("fi"),
+            '\u{FB02}' => expanded.push_str("fl"),
+            '\u{FB03}' => expanded.push_str("ffi"),
+            '\u{FB04}' => expanded.push_str("ffl"),
+            '\u{FB05}' | '\u{FB06}' => expanded.push_str("st"),
+            '\u{0132}' => expanded.push_str("IJ"),
+            '\u{0133}' => expanded.push_str("ij"),
+            '\u{017F}' => expanded.push('s'),
+            '\u{2026}' => expanded.push_str("..."),
+            '\u{2010}' | '\u{2011}' => expanded.push('-'),
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' | '\u{3000}' => expanded.push(' '),
+            // Zero-width characters. Soft hyphens are left to `dehyphenate`,
+            // which needs them to spot words broken across lines.
+            '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => {}
+            // Fullwidth forms of ASCII characters
+            '\u{FF01}'..='\u{FF5E}' => expanded.push(char::from_u32(c as u32 - 0xFEE0).unwrap_or(c)),
+            c => expanded.push(c),
+        }
+    }
+    expanded
+}
+
+/// Rejoin words broken across lines with a hyphen, as in "exam-\nple". The
+/// hyphen is kept out only when the next line starts in lowercase, so
+/// "Jean-\nPaul" keeps its hyphen.
+fn dehyphenate(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut joined = String::with_capacity(text.len());
+    let mut continues_word = false;
+    for (i, line) in lines.iter().enumerate() {
+        let line = if continues_word { line.trim_start() } else { line };
+        if i > 0 && !continues_word {
+            joined.push('\n');
+        }
+
+        let trimmed = line.trim_end();
+        let stem = trimmed
+            .strip_suffix(['-', '\u{00AD}'])
+            .filter(|stem| stem.chars().next_back().is_some_and(char::is_alphabetic));
+        let next_starts_lowercase = lines
+            .get(i + 1)
+            .and_then(|next| next.trim_start().chars().next())
+            .is_some_and(char::is_lowercase);
+        match stem {
+            Some(stem) if next_starts_lowercase => {
+                joined.push_str(stem);
+                continues_word = true;
+            }
+            _ => {
+                joined.push_str(line);
+                continues_word = false;
+            }
+        }
+    }
+    joined.replace('\u{00AD}', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_text() {
+        assert_eq!(clean_text("\u{FB01}nance and e\u{FB03}cient \u{FB02}ows"), "finance and efficient flows");
+        assert_eq!(clean_text("\u{FF21}\u{FF22}\u{FF23} 1\u{00A0}000"), "ABC 1 000");
+        assert_eq!(clean_text("the exam-\n  ple shows\nJean-\nPaul"), "the example shows\nJean-\nPaul");
+        assert_eq!(clean_text("soft\u{00AD}\nhyphen and in\u{00AD}side"), "softhyphen and inside");
+        assert_eq!(clean_text("ends with a dash -\nnext"), "ends with a dash -\nnext");
+    }
+
+    #[test]
+    fn test_running_headers_and_footers_are_removed() {
+        let bodies = ["Revenue grew", "Costs fell", "Outlook", "Risks"];
+        let pages: Vec<String> = bodies
+            .iter()
+            .enumerate()
+            .map(|(i, body)| format!("ACME Corp Annual Report\n{}\nPage {} of 4", body, i + 1))
+            .collect();
+        let cleaned = clean_pages(&pages);
+        assert_eq!(cleaned[0], "Revenue grew");
+        assert_eq!(cleaned[3], "Risks");
+
+        // Lines on only some of the pages stay, as do short documents
+        let mut pages = pages;
+        pages[1] = "Appendix\nfigures".to_string();
+        pages[2] = "Appendix\nmore figures".to_string();
+        assert!(clean_pages(&pages)[1].starts_with("Appendix"));
+        let short = vec!["Title\nfirst".to_string(), "Title\nsecond".to_string()];
+        assert_eq!(clean_pages(&short), short);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::ocr::{self, ScratchDir};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Width of a thumbnail in pixels; the height follows the page's aspect ratio
+pub const THUMBNAIL_WIDTH: u32 = 240;
+
+/// PNG renderings of PDF pages, stored under `<dir>/<hash prefix>/<hash>-<page>.png`.
+/// Keying them by content hash lets a moved file keep its thumbnails and
+/// makes a changed file get new ones.
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    pdftoppm: Option<PathBuf>,
+}
+
+impl ThumbnailCache {
+    /// A cache in `dir`, rendering with Poppler's `pdftoppm` when installed
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, pdftoppm: ocr::find_executable("pdftoppm") }
+    }
+
+    /// Whether thumbnails that are not cached yet can be rendered
+    pub fn can_render(&self) -> bool {
+        self.pdftoppm.is_some()
+    }
+
+    /// Where the thumbnail of `page` (1-based) of the file with `content_hash` is kept
+    pub fn path_for(&self, content_hash: &str, page: u32) -> PathBuf {
+        let prefix: String = content_hash.chars().take(2).collect();
+        self.dir.join(prefix).join(format!("{}-{}.png", content_hash, page))
+    }
+
+    /// The thumbnail of `page` of the PDF at `pdf_path`, rendered first if
+    /// it is not cached. Rendering is given up after `timeout`.
+    pub fn get_or_render(&self, pdf_path: &Path, content_hash: &str, page: u32, timeout: Duration) -> Result<PathBuf> {
+        let thumbnail = self.path_for(content_hash, page);
+        if thumbnail.is_file() {
+            return Ok(thumbnail);
+        }
+        let pdftoppm = self
+            .pdftoppm
+            .as_ref()
+            .context("Page previews need Poppler's pdftoppm, which is not installed")?;
+
+        let work_dir = ScratchDir::new()?;
+        let prefix = work_dir.path().join("thumbnail");
+        let page = page.to_string();
+        let mut command = Command::new(pdftoppm);
+        command
+            .args(["-png", "-singlefile", "-f", &page, "-l", &page])
+            .args(["-scale-to-x", &THUMBNAIL_WIDTH.to_string(), "-scale-to-y", "-1"])
+            .arg(pdf_path)
+            .arg(&prefix);
+        let output = ocr::output_with_timeout(&mut command, timeout).context("Failed to run pdftoppm")?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "pdftoppm failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        // Rendered in a scratch directory and moved in place, so a thumbnail
+        // read by the UI is never half-written
+        let parent = thumbnail.parent().unwrap_or(&self.dir);
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+        let rendered = prefix.with_extension("png");
+        if fs::rename(&rendered, &thumbnail).is_err() {
+            // Across file systems, fall back to copying
+            fs::copy(&rendered, &thumbnail)
+                .with_context(|| format!("Failed to store thumbnail {}", thumbnail.display()))?;
+        }
+        Ok(thumbnail)
+    }
+
+    /// Delete the thumbnails of files whose content hash is not in
+    /// `content_hashes`, returning how many were removed
+    pub fn remove_unused(&self, content_hashes: &HashSet<String>) -> Result<usize> {
+        let mut removed = 0;
+        let Ok(prefixes) = fs::read_dir(&self.dir) else {
+            return Ok(0);
+        };
+        for prefix in prefixes.flatten() {
+            for entry in fs::read_dir(prefix.path())?.flatten() {
+                let path = entry.path();
+                let Some(hash) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.rsplit_once('-'))
+                    .map(|(hash, _)| hash)
+                else {
+                    continue;
+                };
+                if !content_hashes.contains(hash) && fs::remove_file(&path).is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_unused_thumbnails() {
+        let dir = ScratchDir::new().unwrap();
+        let cache = ThumbnailCache::new(dir.path().to_path_buf());
+
+        for (hash, page) in [("ab12", 1), ("ab12", 3), ("cd34", 1)] {
+            let path = cache.path_for(hash, page);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, b"png").unwrap();
+        }
+        assert_eq!(cache.path_for("ab12", 3), dir.path().join("ab").join("ab12-3.png"));
+
+        let live: HashSet<String> = ["ab12".to_string()].into_iter().collect();
+        assert_eq!(cache.remove_unused(&live).unwrap(), 1);
+        assert!(cache.path_for("ab12", 3).is_file());
+        assert!(!cache.path_for("cd34", 1).exists());
+
+        // Cached thumbnails are served without a rasterizer
+        let cache = ThumbnailCache { dir: dir.path().to_path_buf(), pdftoppm: None };
+        let timeout = Duration::from_secs(10);
+        assert!(cache.get_or_render(Path::new("missing.pdf"), "ab12", 1, timeout).is_ok());
+        assert!(cache.get_or_render(Path::new("missing.pdf"), "ab12", 2, timeout).is_err());
+    }
+}